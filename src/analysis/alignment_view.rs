@@ -0,0 +1,180 @@
+//! Variant alignment view: each variant (or matched reference) stacked under
+//! the template oligo, with identical bases shown as dots
+//!
+//! A position counts as a mismatch when the row's base (or every base of its
+//! ambiguity code) differs from the template base. Compatible ambiguity codes
+//! are still shown as letters but do not count.
+
+use super::iupac::{iupac_to_mask, reverse_complement};
+use super::types::WindowAnalysisResult;
+
+/// One row of the alignment view
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlignmentRow {
+    /// "V1", "V2", ... for variants; reference name for references
+    pub label: String,
+    /// '.' where identical to the template, the row's base otherwise
+    pub display: String,
+    /// Number of references represented by the row
+    pub count: usize,
+    pub mismatches: usize,
+}
+
+/// Which sequences to list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignmentRowSource {
+    Variants,
+    /// Individual matched references (needs stored match data)
+    References,
+}
+
+/// Row order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignmentSort {
+    /// Most frequent first
+    Count,
+    /// Fewest mismatches first
+    Mismatches,
+}
+
+/// Compare `seq` to `template`: returns the dotted display string and the
+/// mismatch count.
+pub fn dotted_alignment(template: &str, seq: &str) -> (String, usize) {
+    let mut display = String::with_capacity(seq.len());
+    let mut mismatches = 0;
+    for (i, b) in seq.bytes().enumerate() {
+        let t = template.as_bytes().get(i).copied();
+        if t.is_some_and(|t| t.eq_ignore_ascii_case(&b)) {
+            display.push('.');
+            continue;
+        }
+        display.push(b as char);
+        let compatible = t.is_some_and(|t| {
+            iupac_to_mask(t.to_ascii_uppercase()) & iupac_to_mask(b.to_ascii_uppercase()) != 0
+        });
+        if !compatible {
+            mismatches += 1;
+        }
+    }
+    (display, mismatches)
+}
+
+/// Build alignment rows for a window. `template_oligo` is in template
+/// orientation; with `reverse_complement` both it and the rows are flipped.
+/// Reference rows are labeled with `reference_names` when given, otherwise
+/// with their 1-based index.
+pub fn alignment_rows(
+    analysis: &WindowAnalysisResult,
+    template_oligo: &str,
+    source: AlignmentRowSource,
+    reference_names: &[String],
+    reverse_complement_view: bool,
+) -> Vec<AlignmentRow> {
+    let orient = |s: &str| {
+        if reverse_complement_view {
+            reverse_complement(s)
+        } else {
+            s.to_string()
+        }
+    };
+    let template = orient(template_oligo);
+
+    match source {
+        AlignmentRowSource::Variants => analysis
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let (display, mismatches) = dotted_alignment(&template, &orient(&v.sequence));
+                AlignmentRow {
+                    label: format!("V{}", i + 1),
+                    display,
+                    count: v.count,
+                    mismatches,
+                }
+            })
+            .collect(),
+        AlignmentRowSource::References => analysis
+            .matches
+            .iter()
+            .flat_map(|group| {
+                let (display, mismatches) = dotted_alignment(&template, &orient(&group.sequence));
+                group.references.iter().map(move |&r| AlignmentRow {
+                    label: reference_names
+                        .get(r)
+                        .cloned()
+                        .unwrap_or_else(|| format!("#{}", r + 1)),
+                    display: display.clone(),
+                    count: 1,
+                    mismatches,
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Sort rows; ties keep their current (variant rank / reference) order
+pub fn sort_alignment_rows(rows: &mut [AlignmentRow], sort: AlignmentSort) {
+    match sort {
+        AlignmentSort::Count => {
+            rows.sort_by(|a, b| b.count.cmp(&a.count).then(a.mismatches.cmp(&b.mismatches)))
+        }
+        AlignmentSort::Mismatches => {
+            rows.sort_by(|a, b| a.mismatches.cmp(&b.mismatches).then(b.count.cmp(&a.count)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::{MatchGroup, Variant};
+
+    #[test]
+    fn test_dotted_alignment() {
+        assert_eq!(dotted_alignment("ACGT", "ACGT"), ("....".to_string(), 0));
+        assert_eq!(dotted_alignment("ACGT", "ATGA"), (".T.A".to_string(), 2));
+        // R (A/G) is compatible with G: shown, not counted
+        assert_eq!(dotted_alignment("ACGT", "ACRT"), ("..R.".to_string(), 0));
+    }
+
+    #[test]
+    fn test_rows_sorting_and_references() {
+        let analysis = WindowAnalysisResult {
+            variants: vec![
+                Variant {
+                    sequence: "ATTT".to_string(),
+                    count: 5,
+                    percentage: 50.0,
+                },
+                Variant {
+                    sequence: "ACGT".to_string(),
+                    count: 3,
+                    percentage: 30.0,
+                },
+            ],
+            matches: vec![MatchGroup {
+                sequence: "ACGA".to_string(),
+                mismatches: 1,
+                references: vec![0, 2],
+            }],
+            ..Default::default()
+        };
+
+        let mut rows = alignment_rows(&analysis, "ACGT", AlignmentRowSource::Variants, &[], false);
+        assert_eq!(rows[0].mismatches, 2);
+        sort_alignment_rows(&mut rows, AlignmentSort::Mismatches);
+        assert_eq!(rows[0].label, "V2");
+        assert_eq!(rows[0].display, "....");
+        sort_alignment_rows(&mut rows, AlignmentSort::Count);
+        assert_eq!(rows[0].label, "V1");
+
+        let names = vec!["a".to_string(), "b".to_string()];
+        let refs = alignment_rows(&analysis, "ACGT", AlignmentRowSource::References, &names, true);
+        assert_eq!(refs.len(), 2);
+        assert_eq!(refs[0].label, "a");
+        assert_eq!(refs[1].label, "#3");
+        // Reverse complement: template ACGT, row TCGT
+        assert_eq!(refs[0].display, "T...");
+    }
+}
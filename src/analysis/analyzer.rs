@@ -1,6 +1,6 @@
 //! Core analysis algorithms for oligo variant detection
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use super::iupac::{base_to_bit, sequence_matches_consensus_bytes, IUPAC_FROM_MASK};
 use super::types::{AnalysisMethod, Variant, WindowAnalysisResult};
 
@@ -49,6 +49,8 @@ pub fn analyze_sequences(
         coverage_at_threshold,
         skipped: false,
         skip_reason: None,
+        matches: Vec::new(),
+        mean_mismatches: 0.0,
     }
 }
 
@@ -70,8 +72,8 @@ fn find_variants_no_ambiguities(sequences: &[&str]) -> Vec<Variant> {
         })
         .collect();
 
-    // Sort by count descending
-    variants.sort_by(|a, b| b.count.cmp(&a.count));
+    // Sort by count descending, ties by sequence so the order is reproducible
+    variants.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.sequence.cmp(&b.sequence)));
     variants
 }
 
@@ -92,7 +94,8 @@ fn find_minimum_variants_greedy(
     }
 
     let total = sequences.len() as f64;
-    let mut uncovered: HashSet<&str> = seq_counts.keys().copied().collect();
+    // Ordered set: iteration order decides ties, and must not depend on hashing
+    let mut uncovered: BTreeSet<&str> = seq_counts.keys().copied().collect();
     let mut variants = Vec::new();
 
     while !uncovered.is_empty() {
@@ -141,7 +144,7 @@ fn find_minimum_variants_greedy(
 /// Find the best consensus that covers the most sequences within ambiguity limit.
 /// Uses bitmask tracking for zero-allocation inner loop.
 fn find_best_consensus<'a>(
-    uncovered: &HashSet<&'a str>,
+    uncovered: &BTreeSet<&'a str>,
     seq_counts: &HashMap<&'a str, usize>,
     max_ambiguities: usize,
     exclude_n: bool,
@@ -248,7 +251,8 @@ fn find_incremental_variants(
             *remaining_counts.entry(seq).or_insert(0) += 1;
         }
 
-        let unique_remaining: Vec<&str> = remaining_counts.keys().copied().collect();
+        let mut unique_remaining: Vec<&str> = remaining_counts.keys().copied().collect();
+        unique_remaining.sort_unstable();
 
         let (best_consensus, best_coverage_count) = find_incremental_consensus(
             &unique_remaining,
@@ -398,36 +402,23 @@ fn consensus_from_mask(mask: &[u8], exclude_n: bool) -> (String, usize, bool) {
     (consensus, amb_count, true)
 }
 
-/// Create consensus from sequences using bitmask arithmetic.
-fn create_consensus_from_seqs(sequences: &[&str], exclude_n: bool) -> (String, usize, bool) {
-    if sequences.is_empty() {
-        return (String::new(), 0, true);
-    }
-
-    let seq_len = sequences[0].len();
-    let mut consensus = String::with_capacity(seq_len);
-    let mut ambiguity_count = 0;
-
-    for pos in 0..seq_len {
-        let mut mask: u8 = 0;
-        for &seq in sequences {
-            let bytes = seq.as_bytes();
-            if pos < bytes.len() {
-                mask |= base_to_bit(bytes[pos]);
-            }
-        }
-
-        let code = IUPAC_FROM_MASK[mask as usize];
-        if mask.count_ones() > 1 {
-            if exclude_n && code == b'N' {
-                return (consensus, ambiguity_count, false);
-            }
-            ambiguity_count += 1;
+/// Variants needed to reach `threshold` and the coverage they reach, from
+/// the variant percentages in variant order. All variants and their total
+/// coverage if the threshold is not reached.
+pub fn threshold_coverage(
+    percentages: impl IntoIterator<Item = f64>,
+    threshold: f64,
+) -> (usize, f64) {
+    let mut cumulative = 0.0;
+    let mut count = 0;
+    for percentage in percentages {
+        cumulative += percentage;
+        count += 1;
+        if cumulative >= threshold {
+            break;
         }
-        consensus.push(code as char);
     }
-
-    (consensus, ambiguity_count, true)
+    (count, cumulative)
 }
 
 /// Calculate how many variants are needed to reach coverage threshold
@@ -476,6 +467,14 @@ mod tests {
         assert_eq!(cov, 80.0);
     }
 
+    #[test]
+    fn test_threshold_coverage() {
+        assert_eq!(threshold_coverage([50.0, 30.0, 20.0], 80.0), (2, 80.0));
+        // Not reached: every variant and what they cover
+        assert_eq!(threshold_coverage([50.0, 30.0], 95.0), (2, 80.0));
+        assert_eq!(threshold_coverage([], 95.0), (0, 0.0));
+    }
+
     #[test]
     fn test_incremental_variants() {
         let seqs = vec!["ACGT", "ACGT", "ACGA", "ACGA", "ACGA", "TCGT", "TCGT"];
@@ -0,0 +1,426 @@
+//! Compact binary results format with lazily loaded cell details
+//!
+//! Layout:
+//! ```text
+//! "OSRB" | format version (u32 LE) | header length (u64 LE) | header | blocks...
+//! ```
+//! The header is a zstd-compressed JSON `BinaryHeader`: the full
+//! `ScreeningResults` with every cell's `variants` and `matches` stripped, plus
+//! an index of detail blocks. Each block holds the variants and matches of up
+//! to `BLOCK_POSITIONS` consecutive positions of one oligo length as
+//! zstd-compressed bincode. Opening a file reads only the header; blocks are
+//! read when a cell's details are first needed. The header also keeps each
+//! cell's variant percentages (run-length encoded), so the coverage threshold
+//! can be changed without reading any block.
+//!
+//! Being self-describing, the header also reads files written before a result
+//! type gained a `#[serde(default)]` field.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::types::{MatchGroup, ScreeningResults, Variant};
+
+/// File magic for the binary results format
+pub const BINARY_RESULTS_MAGIC: &[u8; 4] = b"OSRB";
+
+/// Current binary results format version. Detail blocks are bincode, so this
+/// must be bumped if `Variant` or `MatchGroup` change.
+pub const BINARY_RESULTS_VERSION: u32 = 1;
+
+/// Positions per detail block
+const BLOCK_POSITIONS: usize = 256;
+
+/// zstd compression level (fast, still well below JSON size)
+const ZSTD_LEVEL: i32 = 3;
+
+/// Location of one detail block in the file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BlockIndex {
+    oligo_length: u32,
+    /// Index of the first cell in `LengthResult::positions`
+    first: usize,
+    count: usize,
+    /// Byte offset relative to the start of the block section
+    offset: u64,
+    len: u64,
+}
+
+/// Variant percentages of one cell in variant order, as (percentage, run
+/// length) pairs
+type PercentageRuns = Vec<(f64, u32)>;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BinaryHeader {
+    summary: ScreeningResults,
+    blocks: Vec<BlockIndex>,
+    /// Per oligo length, one entry per cell of `LengthResult::positions`
+    variant_percentages: HashMap<u32, Vec<PercentageRuns>>,
+}
+
+fn percentage_runs(variants: &[Variant]) -> PercentageRuns {
+    let mut runs: PercentageRuns = Vec::new();
+    for variant in variants {
+        match runs.last_mut() {
+            Some((percentage, n)) if *percentage == variant.percentage => *n += 1,
+            _ => runs.push((variant.percentage, 1)),
+        }
+    }
+    runs
+}
+
+/// Lazily loaded per-cell data
+#[derive(Debug, Serialize, Deserialize)]
+struct CellDetails {
+    variants: Vec<Variant>,
+    matches: Vec<MatchGroup>,
+}
+
+/// Check whether a file starts with the binary results magic
+pub fn is_binary_results_file(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map(|_| &magic == BINARY_RESULTS_MAGIC)
+        .unwrap_or(false)
+}
+
+/// Write results in the compressed binary format
+pub fn write_binary_results(results: &ScreeningResults, path: &Path) -> Result<(), String> {
+    let mut summary = results.clone();
+    let variant_percentages = results
+        .results_by_length
+        .iter()
+        .map(|(&length, lr)| {
+            let cells = lr
+                .positions
+                .iter()
+                .map(|p| percentage_runs(&p.analysis.variants))
+                .collect();
+            (length, cells)
+        })
+        .collect();
+    let mut blocks = Vec::new();
+    let mut block_data: Vec<Vec<u8>> = Vec::new();
+    let mut offset = 0u64;
+
+    let mut lengths: Vec<u32> = results.results_by_length.keys().copied().collect();
+    lengths.sort();
+
+    for length in lengths {
+        let length_result = summary.results_by_length.get_mut(&length).unwrap();
+        for (chunk_idx, chunk) in length_result
+            .positions
+            .chunks_mut(BLOCK_POSITIONS)
+            .enumerate()
+        {
+            let details: Vec<CellDetails> = chunk
+                .iter_mut()
+                .map(|p| CellDetails {
+                    variants: std::mem::take(&mut p.analysis.variants),
+                    matches: std::mem::take(&mut p.analysis.matches),
+                })
+                .collect();
+            let data = compress(&details)?;
+            blocks.push(BlockIndex {
+                oligo_length: length,
+                first: chunk_idx * BLOCK_POSITIONS,
+                count: chunk.len(),
+                offset,
+                len: data.len() as u64,
+            });
+            offset += data.len() as u64;
+            block_data.push(data);
+        }
+    }
+
+    let header = compress_json(&BinaryHeader {
+        summary,
+        blocks,
+        variant_percentages,
+    })?;
+
+    let file = File::create(path).map_err(|e| format!("Failed to create file: {}", e))?;
+    let mut writer = BufWriter::new(file);
+    let write_err = |e: std::io::Error| format!("Failed to write file: {}", e);
+    writer.write_all(BINARY_RESULTS_MAGIC).map_err(write_err)?;
+    writer
+        .write_all(&BINARY_RESULTS_VERSION.to_le_bytes())
+        .map_err(write_err)?;
+    writer
+        .write_all(&(header.len() as u64).to_le_bytes())
+        .map_err(write_err)?;
+    writer.write_all(&header).map_err(write_err)?;
+    for data in &block_data {
+        writer.write_all(data).map_err(write_err)?;
+    }
+    writer.flush().map_err(write_err)
+}
+
+/// An open binary results file whose cell details are read on demand
+#[derive(Debug)]
+pub struct LazyResultsFile {
+    path: PathBuf,
+    blocks_start: u64,
+    blocks: Vec<BlockIndex>,
+    loaded: Vec<bool>,
+    variant_percentages: HashMap<u32, Vec<PercentageRuns>>,
+}
+
+impl LazyResultsFile {
+    /// Open a binary results file. Returns the results with empty `variants`
+    /// and `matches` for every cell, and the handle to load them later.
+    pub fn open(path: &Path) -> Result<(ScreeningResults, Self), String> {
+        let mut file = File::open(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let read_err = |e: std::io::Error| format!("Failed to read file: {}", e);
+
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic).map_err(read_err)?;
+        if &magic != BINARY_RESULTS_MAGIC {
+            return Err("Not a binary results file".to_string());
+        }
+        let mut buf4 = [0u8; 4];
+        file.read_exact(&mut buf4).map_err(read_err)?;
+        let version = u32::from_le_bytes(buf4);
+        if version != BINARY_RESULTS_VERSION {
+            return Err(format!(
+                "Unsupported binary results version {} (this build reads version {})",
+                version, BINARY_RESULTS_VERSION
+            ));
+        }
+        let mut buf8 = [0u8; 8];
+        file.read_exact(&mut buf8).map_err(read_err)?;
+        let header_len = u64::from_le_bytes(buf8);
+        let file_len = file.metadata().map_err(read_err)?.len();
+        if header_len > file_len.saturating_sub(16) {
+            return Err("Binary results file is truncated or corrupt".to_string());
+        }
+
+        let mut header_bytes = vec![0u8; header_len as usize];
+        file.read_exact(&mut header_bytes).map_err(read_err)?;
+        let header: BinaryHeader = decompress_json(&header_bytes)?;
+
+        let loaded = vec![false; header.blocks.len()];
+        Ok((
+            header.summary,
+            Self {
+                path: path.to_path_buf(),
+                blocks_start: 16 + header_len,
+                blocks: header.blocks,
+                loaded,
+                variant_percentages: header.variant_percentages,
+            },
+        ))
+    }
+
+    pub fn is_fully_loaded(&self) -> bool {
+        self.loaded.iter().all(|&l| l)
+    }
+
+    /// Variant percentages of the cell at `index` of `LengthResult::positions`,
+    /// in variant order, without loading its block
+    pub fn variant_percentages(
+        &self,
+        oligo_length: u32,
+        index: usize,
+    ) -> Option<impl Iterator<Item = f64> + '_> {
+        let runs = self.variant_percentages.get(&oligo_length)?.get(index)?;
+        Some(
+            runs.iter()
+                .flat_map(|&(percentage, n)| std::iter::repeat_n(percentage, n as usize)),
+        )
+    }
+
+    /// Make sure the variants and matches of one cell are present in `results`.
+    pub fn load_cell(
+        &mut self,
+        results: &mut ScreeningResults,
+        oligo_length: u32,
+        position: usize,
+    ) -> Result<(), String> {
+        let Some(length_result) = results.results_by_length.get(&oligo_length) else {
+            return Ok(());
+        };
+        let Ok(index) = length_result
+            .positions
+            .binary_search_by_key(&position, |p| p.position)
+        else {
+            return Ok(());
+        };
+        let Some(block) = self.blocks.iter().position(|b| {
+            b.oligo_length == oligo_length && index >= b.first && index < b.first + b.count
+        }) else {
+            return Ok(());
+        };
+        self.load_block(results, block)
+    }
+
+    /// Load every block not yet loaded (needed before whole-result operations
+    /// such as saving as JSON).
+    pub fn load_all(&mut self, results: &mut ScreeningResults) -> Result<(), String> {
+        for block in 0..self.blocks.len() {
+            self.load_block(results, block)?;
+        }
+        Ok(())
+    }
+
+    fn load_block(&mut self, results: &mut ScreeningResults, block: usize) -> Result<(), String> {
+        if self.loaded[block] {
+            return Ok(());
+        }
+        let index = &self.blocks[block];
+        let read_err = |e: std::io::Error| format!("Failed to read file: {}", e);
+
+        let mut file = File::open(&self.path).map_err(read_err)?;
+        let file_len = file.metadata().map_err(read_err)?.len();
+        let end = self
+            .blocks_start
+            .checked_add(index.offset)
+            .and_then(|start| start.checked_add(index.len));
+        if end.is_none_or(|end| end > file_len) {
+            return Err("Binary results file is truncated or corrupt".to_string());
+        }
+        file.seek(SeekFrom::Start(self.blocks_start + index.offset))
+            .map_err(read_err)?;
+        let mut data = vec![0u8; index.len as usize];
+        file.read_exact(&mut data).map_err(read_err)?;
+        let details: Vec<CellDetails> = decompress(&data)?;
+
+        let Some(length_result) = results.results_by_length.get_mut(&index.oligo_length) else {
+            return Err("Binary results index does not match its contents".to_string());
+        };
+        let cells = length_result
+            .positions
+            .iter_mut()
+            .skip(index.first)
+            .take(index.count);
+        for (cell, detail) in cells.zip(details) {
+            cell.analysis.variants = detail.variants;
+            cell.analysis.matches = detail.matches;
+        }
+        self.loaded[block] = true;
+        Ok(())
+    }
+}
+
+fn compress<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    let raw = bincode::serialize(value).map_err(|e| format!("Failed to serialize: {}", e))?;
+    zstd::encode_all(raw.as_slice(), ZSTD_LEVEL).map_err(|e| format!("Failed to compress: {}", e))
+}
+
+fn decompress<T: for<'de> Deserialize<'de>>(data: &[u8]) -> Result<T, String> {
+    let raw = zstd::decode_all(data).map_err(|e| format!("Failed to decompress: {}", e))?;
+    bincode::deserialize(&raw).map_err(|e| format!("Failed to parse: {}", e))
+}
+
+fn compress_json<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    let raw = serde_json::to_vec(value).map_err(|e| format!("Failed to serialize: {}", e))?;
+    zstd::encode_all(raw.as_slice(), ZSTD_LEVEL).map_err(|e| format!("Failed to compress: {}", e))
+}
+
+fn decompress_json<T: DeserializeOwned>(data: &[u8]) -> Result<T, String> {
+    let raw = zstd::decode_all(data).map_err(|e| format!("Failed to decompress: {}", e))?;
+    serde_json::from_slice(&raw).map_err(|e| format!("Failed to parse: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::fasta::{ReferenceData, TemplateData};
+    use crate::analysis::screener::run_screening;
+    use crate::analysis::types::AnalysisParams;
+
+    #[test]
+    fn test_binary_roundtrip_lazy() {
+        let template = TemplateData {
+            name: "Template".to_string(),
+            sequence: "TATGGTACGTCATGTTCTAGAAATGGGCTGT".to_string(),
+            masked: Vec::new(),
+        };
+        let references = ReferenceData {
+            names: vec!["Ref1".to_string(), "Ref2".to_string()],
+            sequences: vec![
+                "TATGGTACGTCATGTTCTAGAAATGGGCTGT".to_string(),
+                "TATGGTTCGTCATGTTCTAGAAATGGGCTGTTTT".to_string(),
+            ],
+        };
+        let params = AnalysisParams {
+            min_oligo_length: 8,
+            max_oligo_length: 9,
+            ..Default::default()
+        };
+        let results = run_screening(&template, &references, &params, None);
+
+        let path = std::env::temp_dir().join(format!(
+            "oligoscreen_binary_test_{}.osrb",
+            std::process::id()
+        ));
+        write_binary_results(&results, &path).unwrap();
+        assert!(is_binary_results_file(&path));
+
+        let (mut lazy, mut file) = LazyResultsFile::open(&path).unwrap();
+        let first = &lazy.results_by_length[&8].positions[0];
+        assert!(first.analysis.variants.is_empty());
+        let stored: Vec<f64> = file.variant_percentages(8, 0).unwrap().collect();
+        let expected: Vec<f64> = results.results_by_length[&8].positions[0]
+            .analysis
+            .variants
+            .iter()
+            .map(|v| v.percentage)
+            .collect();
+        assert_eq!(stored, expected);
+        assert_eq!(
+            first.variants_needed,
+            results.results_by_length[&8].positions[0].variants_needed
+        );
+
+        file.load_cell(&mut lazy, 8, 0).unwrap();
+        assert_eq!(
+            lazy.results_by_length[&8].positions[0].analysis.variants.len(),
+            results.results_by_length[&8].positions[0].analysis.variants.len()
+        );
+        assert!(lazy.results_by_length[&9].positions[0].analysis.variants.is_empty());
+
+        file.load_all(&mut lazy).unwrap();
+        assert!(file.is_fully_loaded());
+        for (length, lr) in &results.results_by_length {
+            for (a, b) in lr.positions.iter().zip(&lazy.results_by_length[length].positions) {
+                assert_eq!(a.analysis.variants.len(), b.analysis.variants.len());
+                assert_eq!(a.analysis.matches, b.analysis.matches);
+            }
+        }
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_rejects_unreadable_headers() {
+        let path = std::env::temp_dir().join(format!(
+            "oligoscreen_binary_header_test_{}.osrb",
+            std::process::id()
+        ));
+        let write_raw = |version: u32, header_len: u64| {
+            let mut bytes = BINARY_RESULTS_MAGIC.to_vec();
+            bytes.extend(version.to_le_bytes());
+            bytes.extend(header_len.to_le_bytes());
+            bytes.extend([0u8; 8]);
+            std::fs::write(&path, bytes).unwrap();
+        };
+
+        write_raw(BINARY_RESULTS_VERSION + 1, 8);
+        let err = LazyResultsFile::open(&path).unwrap_err();
+        assert!(err.contains("Unsupported binary results version"));
+
+        // A header length past the end of the file is rejected before allocating
+        write_raw(BINARY_RESULTS_VERSION, u64::MAX);
+        let err = LazyResultsFile::open(&path).unwrap_err();
+        assert!(err.contains("truncated"));
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,147 @@
+//! Candidate oligo basket and its FASTA / order sheet export
+//!
+//! A candidate is a (length, position) window picked from the results,
+//! together with the variant sequences needed to reach the coverage threshold
+//! and the ordering options chosen for it.
+
+use serde::{Deserialize, Serialize};
+
+use super::export::{table_to_delimited, DelimitedFormat, Table, TableValue};
+use super::iupac::reverse_complement;
+
+/// Synthesis scales offered in the order sheet
+pub const ORDER_SCALES: [&str; 4] = ["25 nmol", "100 nmol", "250 nmol", "1 umol"];
+
+/// Purification options offered in the order sheet
+pub const ORDER_PURIFICATIONS: [&str; 3] = ["Desalt", "HPLC", "PAGE"];
+
+/// A window selected for ordering
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candidate {
+    /// Name prefix, e.g. "Fwd", "Rev" or "Probe"
+    pub label: String,
+    pub oligo_length: u32,
+    /// 0-based template position
+    pub position: usize,
+    /// Variant sequences in template orientation
+    pub variants: Vec<String>,
+    /// Export the reverse complement of each variant
+    pub reverse_complement: bool,
+    pub scale: String,
+    pub purification: String,
+}
+
+impl Candidate {
+    /// Create a candidate with default ordering options. The label defaults to
+    /// "Rev" for reverse-complemented candidates and "Fwd" otherwise.
+    pub fn new(
+        oligo_length: u32,
+        position: usize,
+        variants: Vec<String>,
+        reverse_complement: bool,
+    ) -> Self {
+        Self {
+            label: if reverse_complement { "Rev" } else { "Fwd" }.to_string(),
+            oligo_length,
+            position,
+            variants,
+            reverse_complement,
+            scale: ORDER_SCALES[0].to_string(),
+            purification: ORDER_PURIFICATIONS[0].to_string(),
+        }
+    }
+
+    /// Named oligo sequences in order orientation, e.g. `Fwd_pos123_len20_v1`
+    /// (positions are 1-based).
+    pub fn oligos(&self) -> Vec<(String, String)> {
+        self.variants
+            .iter()
+            .enumerate()
+            .map(|(i, seq)| {
+                let name = format!(
+                    "{}_pos{}_len{}_v{}",
+                    self.label,
+                    self.position + 1,
+                    self.oligo_length,
+                    i + 1
+                );
+                let seq = if self.reverse_complement {
+                    reverse_complement(seq)
+                } else {
+                    seq.clone()
+                };
+                (name, seq)
+            })
+            .collect()
+    }
+}
+
+/// FASTA text with one record per variant of every candidate
+pub fn candidates_to_fasta(candidates: &[Candidate]) -> String {
+    let mut out = String::new();
+    for candidate in candidates {
+        for (name, seq) in candidate.oligos() {
+            out.push('>');
+            out.push_str(&name);
+            out.push('\n');
+            out.push_str(&seq);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Vendor-style order sheet (name, sequence, scale, purification) as CSV
+pub fn candidates_to_order_csv(candidates: &[Candidate]) -> String {
+    let rows = candidates
+        .iter()
+        .flat_map(|c| {
+            c.oligos().into_iter().map(move |(name, seq)| {
+                vec![
+                    TableValue::Text(name),
+                    TableValue::Text(seq),
+                    TableValue::Text(c.scale.clone()),
+                    TableValue::Text(c.purification.clone()),
+                ]
+            })
+        })
+        .collect();
+
+    let table = Table {
+        name: "Order".to_string(),
+        headers: ["Name", "Sequence", "Scale", "Purification"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect(),
+        rows,
+    };
+    table_to_delimited(&table, DelimitedFormat::Csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidate_fasta_names_and_orientation() {
+        let fwd = Candidate::new(4, 122, vec!["ACGG".to_string(), "ACGT".to_string()], false);
+        let rev = Candidate::new(4, 9, vec!["AACG".to_string()], true);
+        let fasta = candidates_to_fasta(&[fwd, rev]);
+        assert_eq!(
+            fasta,
+            ">Fwd_pos123_len4_v1\nACGG\n>Fwd_pos123_len4_v2\nACGT\n>Rev_pos10_len4_v1\nCGTT\n"
+        );
+    }
+
+    #[test]
+    fn test_order_csv() {
+        let mut c = Candidate::new(4, 0, vec!["ACGT".to_string()], false);
+        c.scale = "100 nmol".to_string();
+        c.purification = "HPLC".to_string();
+        let csv = candidates_to_order_csv(&[c]);
+        assert_eq!(
+            csv,
+            "Name,Sequence,Scale,Purification\nFwd_pos1_len4_v1,ACGT,100 nmol,HPLC\n"
+        );
+    }
+}
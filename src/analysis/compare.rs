@@ -0,0 +1,245 @@
+//! Comparison of two screening runs over the same template
+//!
+//! Pairs up the (length, position) cells of two `ScreeningResults` and
+//! reports how variant counts, no-match rates and variant sets changed.
+
+use std::collections::HashSet;
+
+use super::types::{PositionResult, ScreeningResults};
+
+/// Difference between two runs at one (length, position) cell
+#[derive(Debug, Clone)]
+pub struct CellDiff {
+    pub oligo_length: u32,
+    pub position: usize,
+    pub variants_before: usize,
+    pub variants_after: usize,
+    pub no_match_rate_before: f64,
+    pub no_match_rate_after: f64,
+    pub skipped_before: bool,
+    pub skipped_after: bool,
+    /// True if the set of variant sequences differs between the runs
+    pub variant_set_changed: bool,
+}
+
+impl CellDiff {
+    /// Change in variants needed (positive = more variants = worse)
+    pub fn variants_delta(&self) -> i64 {
+        self.variants_after as i64 - self.variants_before as i64
+    }
+
+    /// Change in no-match fraction (positive = more references lost = worse)
+    pub fn no_match_delta(&self) -> f64 {
+        self.no_match_rate_after - self.no_match_rate_before
+    }
+
+    /// True if the cell was screened before and is skipped now
+    pub fn became_skipped(&self) -> bool {
+        !self.skipped_before && self.skipped_after
+    }
+
+    /// True if the cell got worse: it became skipped, or it is screened in
+    /// both runs and needs more variants or loses more references
+    pub fn is_regression(&self) -> bool {
+        self.became_skipped()
+            || (!self.skipped_before
+                && !self.skipped_after
+                && (self.variants_delta() > 0 || self.no_match_delta() > 0.0))
+    }
+}
+
+/// Cell-by-cell comparison of two screening runs
+#[derive(Debug, Clone)]
+pub struct ScreeningComparison {
+    pub template_length: usize,
+    /// Oligo lengths present in both runs, ascending
+    pub lengths: Vec<u32>,
+    /// Cells present in both runs, ordered by length then position
+    pub cells: Vec<CellDiff>,
+}
+
+impl ScreeningComparison {
+    /// The `n` cells that got worst: cells that became skipped first, then by
+    /// variant increase and by no-match increase. Cells that did not get
+    /// worse are not included.
+    pub fn biggest_regressions(&self, n: usize) -> Vec<&CellDiff> {
+        let mut regressed: Vec<&CellDiff> =
+            self.cells.iter().filter(|c| c.is_regression()).collect();
+        regressed.sort_by(|a, b| {
+            b.became_skipped()
+                .cmp(&a.became_skipped())
+                .then(b.variants_delta().cmp(&a.variants_delta()))
+                .then(b.no_match_delta().total_cmp(&a.no_match_delta()))
+        });
+        regressed.truncate(n);
+        regressed
+    }
+}
+
+/// Compare two screening runs. Both must have been computed on the same
+/// template sequence; only cells present in both runs are compared.
+pub fn compare_results(
+    before: &ScreeningResults,
+    after: &ScreeningResults,
+) -> Result<ScreeningComparison, String> {
+    if before.template_sequence != after.template_sequence {
+        return Err(format!(
+            "Runs use different templates ({} bp vs {} bp); they cannot be compared",
+            before.template_length, after.template_length
+        ));
+    }
+
+    let mut lengths: Vec<u32> = before
+        .results_by_length
+        .keys()
+        .filter(|l| after.results_by_length.contains_key(l))
+        .copied()
+        .collect();
+    lengths.sort();
+
+    if lengths.is_empty() {
+        return Err("Runs have no oligo lengths in common".to_string());
+    }
+
+    let mut cells = Vec::new();
+    for &length in &lengths {
+        let before_lr = &before.results_by_length[&length];
+        let after_lr = &after.results_by_length[&length];
+
+        for b in &before_lr.positions {
+            let Ok(idx) = after_lr
+                .positions
+                .binary_search_by_key(&b.position, |p| p.position)
+            else {
+                continue;
+            };
+            cells.push(diff_cell(length, b, &after_lr.positions[idx]));
+        }
+    }
+
+    Ok(ScreeningComparison {
+        template_length: before.template_length,
+        lengths,
+        cells,
+    })
+}
+
+fn diff_cell(oligo_length: u32, before: &PositionResult, after: &PositionResult) -> CellDiff {
+    let before_set: HashSet<&str> = before
+        .analysis
+        .variants
+        .iter()
+        .map(|v| v.sequence.as_str())
+        .collect();
+    let after_set: HashSet<&str> = after
+        .analysis
+        .variants
+        .iter()
+        .map(|v| v.sequence.as_str())
+        .collect();
+
+    CellDiff {
+        oligo_length,
+        position: before.position,
+        variants_before: before.variants_needed,
+        variants_after: after.variants_needed,
+        no_match_rate_before: no_match_rate(before),
+        no_match_rate_after: no_match_rate(after),
+        skipped_before: before.analysis.skipped,
+        skipped_after: after.analysis.skipped,
+        variant_set_changed: before_set != after_set,
+    }
+}
+
+fn no_match_rate(result: &PositionResult) -> f64 {
+    if result.analysis.total_sequences == 0 {
+        0.0
+    } else {
+        result.analysis.no_match_count as f64 / result.analysis.total_sequences as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::{AnalysisParams, LengthResult, Variant, WindowAnalysisResult};
+
+    fn run(template: &str, cells: &[(usize, usize, &[&str])]) -> ScreeningResults {
+        let mut results =
+            ScreeningResults::new(AnalysisParams::default(), template.len(), 10, template.to_string());
+        let positions = cells
+            .iter()
+            .map(|&(position, no_match, seqs)| PositionResult {
+                position,
+                variants_needed: seqs.len(),
+                source: None,
+                analysis: WindowAnalysisResult {
+                    variants: seqs
+                        .iter()
+                        .map(|s| Variant {
+                            sequence: s.to_string(),
+                            count: 1,
+                            percentage: 10.0,
+                        })
+                        .collect(),
+                    total_sequences: 10,
+                    no_match_count: no_match,
+                    variants_for_threshold: seqs.len(),
+                    ..Default::default()
+                },
+            })
+            .collect();
+        results.results_by_length.insert(
+            4,
+            LengthResult {
+                oligo_length: 4,
+                positions,
+                column_profile: Vec::new(),
+            },
+        );
+        results
+    }
+
+    #[test]
+    fn test_rejects_different_templates() {
+        let a = run("ACGTACGT", &[(0, 0, &["ACGT"])]);
+        let b = run("ACGTACGA", &[(0, 0, &["ACGT"])]);
+        assert!(compare_results(&a, &b).is_err());
+    }
+
+    #[test]
+    fn test_detects_changes_and_regressions() {
+        let a = run("ACGTACGT", &[(0, 0, &["ACGT"]), (1, 0, &["CGTA"]), (2, 0, &["GTAC"])]);
+        let b = run(
+            "ACGTACGT",
+            &[(0, 0, &["ACGT"]), (1, 0, &["CGTA", "CGTT"]), (2, 3, &["GTAA"])],
+        );
+        let cmp = compare_results(&a, &b).unwrap();
+        assert_eq!(cmp.cells.len(), 3);
+        assert!(!cmp.cells[0].variant_set_changed);
+        assert!(cmp.cells[1].variant_set_changed);
+        assert!(cmp.cells[2].variant_set_changed);
+
+        let worst = cmp.biggest_regressions(10);
+        assert_eq!(worst.len(), 2);
+        assert_eq!(worst[0].position, 1);
+        assert_eq!(worst[1].position, 2);
+    }
+
+    #[test]
+    fn test_newly_skipped_cell_is_worst_regression() {
+        let mut a = run("ACGTACGT", &[(0, 0, &["ACGT"]), (1, 0, &["CGTA"]), (2, 0, &[])]);
+        let mut b = run(
+            "ACGTACGT",
+            &[(0, 0, &[]), (1, 0, &["CGTA", "CGTT", "CGTC"]), (2, 0, &["GTAC"])],
+        );
+        a.results_by_length.get_mut(&4).unwrap().positions[2].analysis.skipped = true;
+        b.results_by_length.get_mut(&4).unwrap().positions[0].analysis.skipped = true;
+
+        let cmp = compare_results(&a, &b).unwrap();
+        assert!(cmp.cells[0].became_skipped());
+        let worst: Vec<usize> = cmp.biggest_regressions(10).iter().map(|c| c.position).collect();
+        // Skipped -> screened at position 2 is an improvement
+        assert_eq!(worst, vec![0, 1]);
+    }
+}
@@ -0,0 +1,446 @@
+//! Tabular export of screening results (CSV, TSV, XLSX)
+//!
+//! Three tables can be built from `ScreeningResults`:
+//! - a length × position matrix of variants needed,
+//! - a long table with one row per (length, position) cell,
+//! - a variant table with one row per variant of every cell.
+
+use std::path::Path;
+
+use super::thermo::{gc_content, melting_temperature};
+use super::types::ScreeningResults;
+
+/// A single table cell value
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableValue {
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Empty,
+}
+
+impl TableValue {
+    fn to_text(&self) -> String {
+        match self {
+            Self::Text(s) => s.clone(),
+            Self::Int(n) => n.to_string(),
+            Self::Float(x) => format!("{:.4}", x),
+            Self::Empty => String::new(),
+        }
+    }
+}
+
+/// A named table ready for export
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: String,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<TableValue>>,
+}
+
+/// Delimited text formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DelimitedFormat {
+    Csv,
+    Tsv,
+}
+
+impl DelimitedFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Tsv => "tsv",
+        }
+    }
+
+    fn separator(&self) -> char {
+        match self {
+            Self::Csv => ',',
+            Self::Tsv => '\t',
+        }
+    }
+}
+
+fn sorted_lengths(results: &ScreeningResults) -> Vec<u32> {
+    let mut lengths: Vec<u32> = results.results_by_length.keys().copied().collect();
+    lengths.sort();
+    lengths
+}
+
+/// Length × position matrix of `variants_needed` (empty for skipped cells).
+/// Positions are 1-based template coordinates.
+pub fn variants_matrix_table(results: &ScreeningResults) -> Table {
+    let lengths = sorted_lengths(results);
+    let mut positions: Vec<usize> = results
+        .results_by_length
+        .values()
+        .flat_map(|lr| lr.positions.iter().map(|p| p.position))
+        .collect();
+    positions.sort();
+    positions.dedup();
+
+    let mut headers = vec!["length".to_string()];
+    headers.extend(positions.iter().map(|p| (p + 1).to_string()));
+
+    let rows = lengths
+        .iter()
+        .map(|length| {
+            let lr = &results.results_by_length[length];
+            let mut row = vec![TableValue::Int(*length as i64)];
+            row.extend(positions.iter().map(|&pos| {
+                match lr.positions.binary_search_by_key(&pos, |p| p.position) {
+                    Ok(i) if !lr.positions[i].analysis.skipped => {
+                        TableValue::Int(lr.positions[i].variants_needed as i64)
+                    }
+                    _ => TableValue::Empty,
+                }
+            }));
+            row
+        })
+        .collect();
+
+    Table {
+        name: "Variants matrix".to_string(),
+        headers,
+        rows,
+    }
+}
+
+/// One row per (length, position) cell with coverage and no-match details.
+/// Cells taken from an additional template name it and give the window's
+/// position on it.
+pub fn cell_table(results: &ScreeningResults) -> Table {
+    let headers = [
+        "length",
+        "position",
+        "variants_needed",
+        "coverage_at_threshold",
+        "matched",
+        "total_references",
+        "no_match_count",
+        "no_match_percent",
+        "skip_reason",
+        "source_template",
+        "source_position",
+    ]
+    .iter()
+    .map(|h| h.to_string())
+    .collect();
+
+    let mut rows = Vec::new();
+    for length in sorted_lengths(results) {
+        for p in &results.results_by_length[&length].positions {
+            let a = &p.analysis;
+            let no_match_pct = if a.total_sequences > 0 {
+                a.no_match_count as f64 / a.total_sequences as f64 * 100.0
+            } else {
+                0.0
+            };
+            rows.push(vec![
+                TableValue::Int(length as i64),
+                TableValue::Int(p.position as i64 + 1),
+                if a.skipped {
+                    TableValue::Empty
+                } else {
+                    TableValue::Int(p.variants_needed as i64)
+                },
+                TableValue::Float(a.coverage_at_threshold),
+                TableValue::Int(a.sequences_analyzed as i64),
+                TableValue::Int(a.total_sequences as i64),
+                TableValue::Int(a.no_match_count as i64),
+                TableValue::Float(no_match_pct),
+                match &a.skip_reason {
+                    Some(reason) => TableValue::Text(reason.clone()),
+                    None => TableValue::Empty,
+                },
+                match p.source.and_then(|s| results.extra_templates.get(s.template)) {
+                    Some(t) => TableValue::Text(t.name.clone()),
+                    None => TableValue::Empty,
+                },
+                match p.source {
+                    Some(s) => TableValue::Int(s.position as i64 + 1),
+                    None => TableValue::Empty,
+                },
+            ]);
+        }
+    }
+
+    Table {
+        name: "Cells".to_string(),
+        headers,
+        rows,
+    }
+}
+
+/// One row per variant of every cell. With `include_thermo`, GC content and
+/// a melting temperature estimate are added for each variant sequence.
+pub fn variant_table(results: &ScreeningResults, include_thermo: bool) -> Table {
+    let mut headers: Vec<String> = ["length", "position", "rank", "sequence", "count", "percentage"]
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+    if include_thermo {
+        headers.push("gc_percent".to_string());
+        headers.push("tm_celsius".to_string());
+    }
+
+    let mut rows = Vec::new();
+    for length in sorted_lengths(results) {
+        for p in &results.results_by_length[&length].positions {
+            for (i, v) in p.analysis.variants.iter().enumerate() {
+                let mut row = vec![
+                    TableValue::Int(length as i64),
+                    TableValue::Int(p.position as i64 + 1),
+                    TableValue::Int(i as i64 + 1),
+                    TableValue::Text(v.sequence.clone()),
+                    TableValue::Int(v.count as i64),
+                    TableValue::Float(v.percentage),
+                ];
+                if include_thermo {
+                    row.push(TableValue::Float(gc_content(&v.sequence)));
+                    row.push(TableValue::Float(melting_temperature(&v.sequence)));
+                }
+                rows.push(row);
+            }
+        }
+    }
+
+    Table {
+        name: "Variants".to_string(),
+        headers,
+        rows,
+    }
+}
+
+/// Render a table as CSV or TSV text. CSV fields are quoted when needed;
+/// TSV replaces tabs and newlines inside fields with spaces.
+pub fn table_to_delimited(table: &Table, format: DelimitedFormat) -> String {
+    let sep = format.separator();
+    let escape = |field: String| -> String {
+        match format {
+            DelimitedFormat::Csv => {
+                if field.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", field.replace('"', "\"\""))
+                } else {
+                    field
+                }
+            }
+            DelimitedFormat::Tsv => field.replace(['\t', '\n', '\r'], " "),
+        }
+    };
+
+    let mut out = String::new();
+    let header: Vec<String> = table.headers.iter().cloned().map(escape).collect();
+    out.push_str(&header.join(&sep.to_string()));
+    out.push('\n');
+    for row in &table.rows {
+        let fields: Vec<String> = row.iter().map(|v| escape(v.to_text())).collect();
+        out.push_str(&fields.join(&sep.to_string()));
+        out.push('\n');
+    }
+    out
+}
+
+/// Rows and columns of one XLSX sheet
+const XLSX_MAX_ROWS: usize = 1_048_576;
+const XLSX_MAX_COLUMNS: usize = 16_384;
+
+fn write_xlsx_value(
+    sheet: &mut rust_xlsxwriter::Worksheet,
+    row: usize,
+    col: usize,
+    value: &TableValue,
+) -> Result<(), rust_xlsxwriter::XlsxError> {
+    let (row, col) = (row as u32, col as u16);
+    match value {
+        TableValue::Text(s) => sheet.write_string(row, col, s).map(|_| ()),
+        TableValue::Int(n) => sheet.write_number(row, col, *n as f64).map(|_| ()),
+        TableValue::Float(x) => sheet.write_number(row, col, *x).map(|_| ()),
+        TableValue::Empty => Ok(()),
+    }
+}
+
+/// Write tables to one XLSX workbook, one sheet per table. A table with more
+/// columns than a sheet holds (the matrix of a long template) is written
+/// transposed, its headers down the first column. A table with more rows
+/// continues on sheets named "<name> (2)", "<name> (3)", ..., each repeating
+/// the header row. Tables that fit neither way are rejected before anything
+/// is written.
+pub fn write_xlsx(tables: &[Table], path: &Path) -> Result<(), String> {
+    use rust_xlsxwriter::{Format, Workbook};
+
+    let mut transposed = Vec::new();
+    for table in tables {
+        let transpose = table.headers.len() > XLSX_MAX_COLUMNS;
+        let fits_transposed =
+            table.rows.len() < XLSX_MAX_COLUMNS && table.headers.len() <= XLSX_MAX_ROWS;
+        if transpose && !fits_transposed {
+            return Err(format!(
+                "Table \"{}\" ({} rows × {} columns) does not fit an XLSX sheet \
+                 ({} rows × {} columns) either way round; export it as CSV or TSV",
+                table.name,
+                table.rows.len() + 1,
+                table.headers.len(),
+                XLSX_MAX_ROWS,
+                XLSX_MAX_COLUMNS
+            ));
+        }
+        transposed.push(transpose);
+    }
+
+    let xlsx_err = |e: rust_xlsxwriter::XlsxError| format!("Failed to write XLSX: {}", e);
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+
+    for (table, transpose) in tables.iter().zip(transposed) {
+        if transpose {
+            let sheet = workbook.add_worksheet();
+            sheet.set_name(&table.name).map_err(xlsx_err)?;
+            for (r, header) in table.headers.iter().enumerate() {
+                sheet
+                    .write_string_with_format(r as u32, 0, header, &bold)
+                    .map_err(xlsx_err)?;
+            }
+            for (c, row) in table.rows.iter().enumerate() {
+                for (r, value) in row.iter().enumerate() {
+                    write_xlsx_value(sheet, r, c + 1, value).map_err(xlsx_err)?;
+                }
+            }
+            continue;
+        }
+
+        let rows_per_sheet = XLSX_MAX_ROWS - 1;
+        let parts = table.rows.len().div_ceil(rows_per_sheet).max(1);
+        for part in 0..parts {
+            let sheet = workbook.add_worksheet();
+            let name = if part == 0 {
+                table.name.clone()
+            } else {
+                format!("{} ({})", table.name, part + 1)
+            };
+            sheet.set_name(&name).map_err(xlsx_err)?;
+            for (col, header) in table.headers.iter().enumerate() {
+                sheet
+                    .write_string_with_format(0, col as u16, header, &bold)
+                    .map_err(xlsx_err)?;
+            }
+            let start = part * rows_per_sheet;
+            let end = (start + rows_per_sheet).min(table.rows.len());
+            for (r, row) in table.rows[start..end].iter().enumerate() {
+                for (col, value) in row.iter().enumerate() {
+                    write_xlsx_value(sheet, r + 1, col, value).map_err(xlsx_err)?;
+                }
+            }
+        }
+    }
+
+    workbook.save(path).map_err(xlsx_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::{
+        AnalysisParams, LengthResult, PositionResult, Variant, WindowAnalysisResult,
+    };
+
+    fn sample_results() -> ScreeningResults {
+        let mut results =
+            ScreeningResults::new(AnalysisParams::default(), 8, 4, "ACGTACGT".to_string());
+        results.results_by_length.insert(
+            4,
+            LengthResult {
+                oligo_length: 4,
+                positions: vec![
+                    PositionResult {
+                        position: 0,
+                        variants_needed: 2,
+                        source: None,
+                        analysis: WindowAnalysisResult {
+                            variants: vec![
+                                Variant {
+                                    sequence: "ACGT".to_string(),
+                                    count: 3,
+                                    percentage: 75.0,
+                                },
+                                Variant {
+                                    sequence: "ACGA".to_string(),
+                                    count: 1,
+                                    percentage: 25.0,
+                                },
+                            ],
+                            total_sequences: 4,
+                            sequences_analyzed: 4,
+                            variants_for_threshold: 2,
+                            coverage_at_threshold: 100.0,
+                            ..Default::default()
+                        },
+                    },
+                    PositionResult {
+                        position: 1,
+                        variants_needed: 0,
+                        source: None,
+                        analysis: WindowAnalysisResult {
+                            total_sequences: 4,
+                            no_match_count: 4,
+                            skipped: true,
+                            skip_reason: Some("No match, at all".to_string()),
+                            ..Default::default()
+                        },
+                    },
+                ],
+                column_profile: Vec::new(),
+            },
+        );
+        results
+    }
+
+    #[test]
+    fn test_matrix_table() {
+        let table = variants_matrix_table(&sample_results());
+        assert_eq!(table.headers, vec!["length", "1", "2"]);
+        assert_eq!(
+            table.rows[0],
+            vec![TableValue::Int(4), TableValue::Int(2), TableValue::Empty]
+        );
+    }
+
+    #[test]
+    fn test_csv_quoting_and_variant_rows() {
+        let results = sample_results();
+        let csv = table_to_delimited(&cell_table(&results), DelimitedFormat::Csv);
+        assert!(csv.contains("\"No match, at all\""));
+        assert_eq!(csv.lines().count(), 3);
+
+        let variants = variant_table(&results, true);
+        assert_eq!(variants.rows.len(), 2);
+        assert_eq!(variants.headers.len(), 8);
+        let tsv = table_to_delimited(&variants, DelimitedFormat::Tsv);
+        assert!(tsv.lines().nth(1).unwrap().starts_with("4\t1\t1\tACGT\t3\t"));
+    }
+
+    #[test]
+    fn test_xlsx_sheet_limits() {
+        let path = std::env::temp_dir().join(format!(
+            "oligoscreen_xlsx_test_{}.xlsx",
+            std::process::id()
+        ));
+        // Wider than a sheet: written transposed
+        let wide = Table {
+            name: "Variants matrix".to_string(),
+            headers: (0..=XLSX_MAX_COLUMNS).map(|i| i.to_string()).collect(),
+            rows: vec![vec![TableValue::Int(1); XLSX_MAX_COLUMNS + 1]],
+        };
+        assert!(write_xlsx(std::slice::from_ref(&wide), &path).is_ok());
+
+        // Too wide and, transposed, still too wide
+        let too_large = Table {
+            rows: vec![Vec::new(); XLSX_MAX_COLUMNS],
+            ..wide
+        };
+        let err = write_xlsx(&[too_large], &path).unwrap_err();
+        assert!(err.contains("CSV or TSV"));
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -1,4 +1,7 @@
-//! FASTA file parsing for template and reference sequences
+//! FASTA parsing for the template, and the sequence set types
+//!
+//! References and background sequences are read by `sequence_file`, which
+//! reports invalid characters instead of dropping them.
 
 use super::iupac::{is_ambiguous_base, is_gap, is_standard_base};
 
@@ -6,7 +9,10 @@ use super::iupac::{is_ambiguous_base, is_gap, is_standard_base};
 #[derive(Debug, Clone)]
 pub struct TemplateData {
     pub name: String,
+    /// Uppercase; may contain ambiguity codes but no gaps
     pub sequence: String,
+    /// Soft-masked (lowercase in the input) ranges, 0-based half-open
+    pub masked: Vec<(usize, usize)>,
 }
 
 /// Parsed reference sequences (multiple, unaligned)
@@ -31,6 +37,19 @@ impl ReferenceData {
     pub fn len(&self) -> usize {
         self.sequences.len()
     }
+
+    /// The sequences as FASTA text, one line per sequence
+    pub fn to_fasta(&self) -> String {
+        let mut text = String::new();
+        for (name, sequence) in self.names.iter().zip(&self.sequences) {
+            text.push('>');
+            text.push_str(name);
+            text.push('\n');
+            text.push_str(sequence);
+            text.push('\n');
+        }
+        text
+    }
 }
 
 impl Default for ReferenceData {
@@ -54,12 +73,13 @@ pub fn parse_template_fasta(text: &str) -> Result<TemplateData, String> {
         ));
     }
 
-    // Validate template has only standard bases (no gaps or ambiguities)
+    // Ambiguity codes are allowed (windows over them are skipped or
+    // resolved during screening), gaps are not
     let seq = &sequences[0];
     for (i, c) in seq.chars().enumerate() {
-        if !is_standard_base(c) {
+        if !is_standard_base(c) && !is_ambiguous_base(c) {
             return Err(format!(
-                "Template contains invalid character '{}' at position {}. Only A, C, G, T are allowed.",
+                "Template contains invalid character '{}' at position {}. Only A, C, G, T and IUPAC ambiguity codes are allowed.",
                 c, i + 1
             ));
         }
@@ -68,21 +88,36 @@ pub fn parse_template_fasta(text: &str) -> Result<TemplateData, String> {
     Ok(TemplateData {
         name: names[0].clone(),
         sequence: sequences[0].clone(),
+        masked: soft_masked_ranges(text),
     })
 }
 
-/// Parse multi-sequence FASTA as reference set (unaligned, no length normalization).
-pub fn parse_reference_fasta(text: &str) -> Result<ReferenceData, String> {
-    let (names, sequences) = parse_fasta_sequences(text)?;
-
-    if sequences.is_empty() {
-        return Err("No valid sequences found in reference input".to_string());
+/// Lowercase (soft-masked) ranges of the sequence characters kept by
+/// `parse_fasta_sequences`, in sequence coordinates. Meant for single-sequence
+/// input.
+fn soft_masked_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut index = 0;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('>') {
+            continue;
+        }
+        for c in line.chars() {
+            let upper = c.to_ascii_uppercase();
+            if !(is_standard_base(upper) || is_ambiguous_base(upper) || is_gap(upper)) {
+                continue;
+            }
+            if c.is_ascii_lowercase() {
+                match ranges.last_mut() {
+                    Some(last) if last.1 == index => last.1 += 1,
+                    _ => ranges.push((index, index + 1)),
+                }
+            }
+            index += 1;
+        }
     }
-
-    let mut data = ReferenceData::new();
-    data.names = names;
-    data.sequences = sequences;
-    Ok(data)
+    ranges
 }
 
 /// Core FASTA parsing: extract names and sequences from FASTA text.
@@ -181,19 +216,16 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_template_rejects_gaps() {
-        let fasta = ">Template\nAC-TACGT";
-        assert!(parse_template_fasta(fasta).is_err());
+    fn test_parse_template_ambiguity_and_soft_masking() {
+        let fasta = ">Template\nACGTnnnnAC\nGTRacgtA";
+        let data = parse_template_fasta(fasta).unwrap();
+        assert_eq!(data.sequence, "ACGTNNNNACGTRACGTA");
+        assert_eq!(data.masked, vec![(4, 8), (13, 17)]);
     }
 
     #[test]
-    fn test_parse_references() {
-        let fasta = ">Ref1\nACGTACGT\n>Ref2\nACGTACGTTT\n>Ref3\nACGT";
-        let data = parse_reference_fasta(fasta).unwrap();
-        assert_eq!(data.len(), 3);
-        // Sequences should NOT be padded to same length
-        assert_eq!(data.sequences[0].len(), 8);
-        assert_eq!(data.sequences[1].len(), 10);
-        assert_eq!(data.sequences[2].len(), 4);
+    fn test_parse_template_rejects_gaps() {
+        let fasta = ">Template\nAC-TACGT";
+        assert!(parse_template_fasta(fasta).is_err());
     }
 }
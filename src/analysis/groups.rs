@@ -0,0 +1,299 @@
+//! Inclusivity / exclusivity of an assay per reference group
+//!
+//! References are grouped by a `[tag=value]` field in their FASTA header or
+//! by a mapping file. A reference counts as detected by an oligo when its
+//! matched sequence at the oligo's window (from the stored match data) is
+//! covered by one of the oligo's variants; an assay of several oligos
+//! detects a reference only if every oligo does.
+
+use std::collections::{BTreeMap, HashSet};
+
+use super::candidates::Candidate;
+use super::export::{Table, TableValue};
+use super::iupac::sequence_matches_consensus_bytes;
+use super::types::{ScreeningResults, WindowAnalysisResult};
+
+/// Group of references without a tag or mapping entry
+pub const UNGROUPED: &str = "Ungrouped";
+
+/// Group per reference from a `[tag=value]` header field (tag matched
+/// ignoring ASCII case, which keeps byte offsets into the header valid)
+pub fn groups_from_header_tag(names: &[String], tag: &str) -> Vec<String> {
+    let prefix = format!("[{}=", tag.trim().to_ascii_lowercase());
+    names
+        .iter()
+        .map(|name| {
+            let lower = name.to_ascii_lowercase();
+            lower
+                .find(&prefix)
+                .and_then(|start| {
+                    let value_start = start + prefix.len();
+                    let end = name[value_start..].find(']')?;
+                    Some(name[value_start..value_start + end].trim().to_string())
+                })
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| UNGROUPED.to_string())
+        })
+        .collect()
+}
+
+/// Group per reference from mapping text with one `reference<TAB>group` line
+/// per reference. The reference column is compared to the full header and
+/// to its first word (the sequence ID).
+pub fn groups_from_mapping(names: &[String], mapping: &str) -> Result<Vec<String>, String> {
+    let mut map = BTreeMap::new();
+    for (i, line) in mapping.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((reference, group)) = line.split_once('\t') else {
+            return Err(format!("Mapping line {} has no tab separator", i + 1));
+        };
+        map.insert(reference.trim().to_string(), group.trim().to_string());
+    }
+    if map.is_empty() {
+        return Err("Mapping file is empty".to_string());
+    }
+
+    Ok(names
+        .iter()
+        .map(|name| {
+            let id = name.split_whitespace().next().unwrap_or("");
+            map.get(name.trim())
+                .or_else(|| map.get(id))
+                .cloned()
+                .unwrap_or_else(|| UNGROUPED.to_string())
+        })
+        .collect())
+}
+
+/// Which references the given variants (template orientation) detect at a
+/// window. Needs the window's stored match data.
+pub fn detected_references(
+    analysis: &WindowAnalysisResult,
+    variants: &[String],
+    total_references: usize,
+) -> Result<Vec<bool>, String> {
+    if analysis.matches.is_empty() && analysis.sequences_analyzed > 0 {
+        return Err(
+            "Per-reference match data is needed. Enable it in Analysis Setup and re-run."
+                .to_string(),
+        );
+    }
+    let mut detected = vec![false; total_references];
+    for group in &analysis.matches {
+        let covered = variants.iter().any(|v| {
+            sequence_matches_consensus_bytes(group.sequence.as_bytes(), v.as_bytes())
+        });
+        if covered {
+            for &r in &group.references {
+                if let Some(d) = detected.get_mut(r) {
+                    *d = true;
+                }
+            }
+        }
+    }
+    Ok(detected)
+}
+
+/// References detected by every candidate of an assay
+pub fn assay_detection(
+    results: &ScreeningResults,
+    candidates: &[Candidate],
+) -> Result<Vec<bool>, String> {
+    let mut detected = vec![true; results.total_sequences];
+    for candidate in candidates {
+        let analysis = results
+            .results_by_length
+            .get(&candidate.oligo_length)
+            .and_then(|lr| {
+                lr.positions
+                    .binary_search_by_key(&candidate.position, |p| p.position)
+                    .ok()
+                    .map(|i| &lr.positions[i].analysis)
+            })
+            .ok_or_else(|| {
+                format!(
+                    "No result for {} at position {} ({} bp)",
+                    candidate.label,
+                    candidate.position + 1,
+                    candidate.oligo_length
+                )
+            })?;
+        let by_candidate =
+            detected_references(analysis, &candidate.variants, results.total_sequences)?;
+        for (d, c) in detected.iter_mut().zip(by_candidate) {
+            *d &= c;
+        }
+    }
+    Ok(detected)
+}
+
+/// Detection counts of one group
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupCoverage {
+    pub group: String,
+    pub references: usize,
+    pub detected: usize,
+}
+
+impl GroupCoverage {
+    pub fn percent(&self) -> f64 {
+        if self.references > 0 {
+            self.detected as f64 / self.references as f64 * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Target groups pass when every reference is detected, non-target
+    /// groups when none is
+    pub fn passes(&self, target: bool) -> bool {
+        if target {
+            self.detected == self.references
+        } else {
+            self.detected == 0
+        }
+    }
+}
+
+/// Per-group counts, sorted by group name
+pub fn group_coverage(groups: &[String], detected: &[bool]) -> Vec<GroupCoverage> {
+    let mut by_group: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for (group, &d) in groups.iter().zip(detected) {
+        let entry = by_group.entry(group).or_default();
+        entry.0 += 1;
+        entry.1 += d as usize;
+    }
+    by_group
+        .into_iter()
+        .map(|(group, (references, detected))| GroupCoverage {
+            group: group.to_string(),
+            references,
+            detected,
+        })
+        .collect()
+}
+
+/// Report table; groups in `targets` are inclusivity targets, all others
+/// are checked for exclusivity
+pub fn inclusivity_table(coverage: &[GroupCoverage], targets: &HashSet<String>) -> Table {
+    let headers = [
+        "group",
+        "role",
+        "references",
+        "detected",
+        "coverage_percent",
+        "result",
+    ]
+    .iter()
+    .map(|h| h.to_string())
+    .collect();
+
+    let rows = coverage
+        .iter()
+        .map(|g| {
+            let target = targets.contains(&g.group);
+            vec![
+                TableValue::Text(g.group.clone()),
+                TableValue::Text(if target { "target" } else { "non-target" }.to_string()),
+                TableValue::Int(g.references as i64),
+                TableValue::Int(g.detected as i64),
+                TableValue::Float(g.percent()),
+                TableValue::Text(if g.passes(target) { "pass" } else { "fail" }.to_string()),
+            ]
+        })
+        .collect();
+
+    Table {
+        name: "Inclusivity".to_string(),
+        headers,
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::MatchGroup;
+
+    #[test]
+    fn test_grouping_from_tags_and_mapping() {
+        let names = vec![
+            "seq1 [Species=E. coli] strain K12".to_string(),
+            "seq2 [species=Shigella]".to_string(),
+            "seq3 no tag".to_string(),
+        ];
+        assert_eq!(
+            groups_from_header_tag(&names, "species"),
+            vec!["E. coli", "Shigella", UNGROUPED]
+        );
+
+        let mapping = "# id\tgroup\nseq1\tTarget\nseq3 no tag\tOther\n";
+        assert_eq!(
+            groups_from_mapping(&names, mapping).unwrap(),
+            vec!["Target", UNGROUPED, "Other"]
+        );
+        assert!(groups_from_mapping(&names, "seq1 Target").is_err());
+    }
+
+    #[test]
+    fn test_grouping_from_tags_in_non_ascii_headers() {
+        // 'İ' lowercases to three bytes, which shifted the tag offsets
+        let names = vec![
+            "İİİ isolate [Country=Türkiye] [host=İnsan]".to_string(),
+            "Ärzte [COUNTRY=Österreich]".to_string(),
+        ];
+        assert_eq!(
+            groups_from_header_tag(&names, "country"),
+            vec!["Türkiye", "Österreich"]
+        );
+        assert_eq!(
+            groups_from_header_tag(&names, "Host"),
+            vec!["İnsan", UNGROUPED]
+        );
+    }
+
+    #[test]
+    fn test_detection_and_group_report() {
+        let analysis = WindowAnalysisResult {
+            sequences_analyzed: 3,
+            matches: vec![
+                MatchGroup {
+                    sequence: "ACGT".to_string(),
+                    mismatches: 0,
+                    references: vec![0, 2],
+                },
+                MatchGroup {
+                    sequence: "ACGA".to_string(),
+                    mismatches: 1,
+                    references: vec![3],
+                },
+            ],
+            ..Default::default()
+        };
+        let detected = detected_references(&analysis, &["ACGT".to_string()], 4).unwrap();
+        assert_eq!(detected, vec![true, false, true, false]);
+        // W = A/T covers both matched sequences
+        let degenerate = detected_references(&analysis, &["ACGW".to_string()], 4).unwrap();
+        assert_eq!(degenerate, vec![true, false, true, true]);
+
+        let groups: Vec<String> = ["A", "A", "B", "B"].iter().map(|s| s.to_string()).collect();
+        let coverage = group_coverage(&groups, &detected);
+        assert_eq!(coverage.len(), 2);
+        assert_eq!((coverage[0].references, coverage[0].detected), (2, 1));
+        assert_eq!(coverage[0].percent(), 50.0);
+
+        let targets: HashSet<String> = ["A".to_string()].into();
+        let table = inclusivity_table(&coverage, &targets);
+        assert_eq!(table.rows[0][5], TableValue::Text("fail".to_string()));
+        assert_eq!(table.rows[1][1], TableValue::Text("non-target".to_string()));
+
+        let without_data = WindowAnalysisResult {
+            sequences_analyzed: 3,
+            ..Default::default()
+        };
+        assert!(detected_references(&without_data, &[], 3).is_err());
+    }
+}
@@ -0,0 +1,720 @@
+//! Heatmap figure rendering to SVG and PNG
+//!
+//! `render_heatmap_figure` lays out the same elements as the on-screen
+//! heatmap (position numbers, template base track, length labels, cells and
+//! legend) as a flat list of rectangles and text. The figure is then written
+//! either as SVG or rasterized to PNG at any scale. Nothing here depends on
+//! the GUI, so figures can be produced headless.
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+
+use super::types::ScreeningResults;
+
+/// An RGB color
+pub type Rgb = [u8; 3];
+
+const BACKGROUND: Rgb = [255, 255, 255];
+const TEXT_COLOR: Rgb = [30, 30, 30];
+const POSITION_TEXT_COLOR: Rgb = [110, 110, 110];
+/// Skipped cells (same as on screen)
+pub const SKIPPED_RGB: Rgb = [40, 40, 40];
+/// Cells without data for a length
+pub const MISSING_RGB: Rgb = [30, 30, 30];
+
+const MARGIN: f32 = 10.0;
+const LABEL_WIDTH: f32 = 50.0;
+const POS_LABEL_HEIGHT: f32 = 14.0;
+const BASE_TRACK_HEIGHT: f32 = 20.0;
+const LEGEND_HEIGHT: f32 = 30.0;
+const SWATCH: f32 = 12.0;
+/// Largest PNG rendered, in pixels (3 bytes each while drawing)
+pub const MAX_PNG_PIXELS: usize = 200_000_000;
+
+/// Layout and color settings for a heatmap figure
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatmapImageSettings {
+    /// Cell size in figure units (SVG user units, PNG pixels at scale 1)
+    pub cell_width: f32,
+    pub cell_height: f32,
+    pub color_green_at: usize,
+    pub color_red_at: usize,
+    /// No-match darkening thresholds, in percent
+    pub nomatch_ok_percent: f64,
+    pub nomatch_bad_percent: f64,
+    pub show_base_track: bool,
+    pub show_legend: bool,
+}
+
+impl Default for HeatmapImageSettings {
+    fn default() -> Self {
+        Self {
+            cell_width: 14.0,
+            cell_height: 30.0,
+            color_green_at: 1,
+            color_red_at: 10,
+            nomatch_ok_percent: 5.0,
+            nomatch_bad_percent: 50.0,
+            show_base_track: true,
+            show_legend: true,
+        }
+    }
+}
+
+/// Horizontal text anchor; text is always vertically centered on `y`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    Rect {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        color: Rgb,
+    },
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        anchor: TextAnchor,
+        monospace: bool,
+        color: Rgb,
+        text: String,
+    },
+}
+
+/// A laid-out heatmap figure
+#[derive(Debug, Clone)]
+pub struct HeatmapFigure {
+    pub width: f32,
+    pub height: f32,
+    shapes: Vec<Shape>,
+}
+
+/// Color for a cell based on variant count and no-match fraction.
+///
+/// Variant count gradient: green → yellow → red (configurable thresholds).
+/// No-match darkening (takes priority): original color → dark red,
+/// ramping between `nomatch_ok` and `nomatch_bad` fractions.
+pub fn position_rgb(
+    variant_count: usize,
+    no_match_fraction: f64,
+    green_at: usize,
+    red_at: usize,
+    nomatch_ok: f64,
+    nomatch_bad: f64,
+) -> Rgb {
+    if variant_count == 0 {
+        return SKIPPED_RGB;
+    }
+
+    // 3-stop gradient: green (0,180,0) → yellow (220,200,0) → red (220,50,50)
+    let green = (0.0f64, 180.0f64, 0.0f64);
+    let yellow = (220.0f64, 200.0f64, 0.0f64);
+    let red = (220.0f64, 50.0f64, 50.0f64);
+
+    let t = if red_at <= green_at {
+        if variant_count <= green_at { 0.0 } else { 1.0 }
+    } else if variant_count <= green_at {
+        0.0
+    } else if variant_count >= red_at {
+        1.0
+    } else {
+        (variant_count - green_at) as f64 / (red_at - green_at) as f64
+    };
+
+    // Interpolate through green → yellow (t=0..0.5) → red (t=0.5..1.0)
+    let (base_r, base_g, base_b) = if t <= 0.5 {
+        let s = t * 2.0; // 0..1 within the first half
+        (
+            green.0 + (yellow.0 - green.0) * s,
+            green.1 + (yellow.1 - green.1) * s,
+            green.2 + (yellow.2 - green.2) * s,
+        )
+    } else {
+        let s = (t - 0.5) * 2.0; // 0..1 within the second half
+        (
+            yellow.0 + (red.0 - yellow.0) * s,
+            yellow.1 + (red.1 - yellow.1) * s,
+            yellow.2 + (red.2 - yellow.2) * s,
+        )
+    };
+
+    // No-match darkening: blend from base color toward dark red (100, 20, 20)
+    // nm_t=0 at nomatch_ok, nm_t=1 at nomatch_bad
+    let dark_red = (100.0f64, 20.0f64, 20.0f64);
+    let nm_frac = no_match_fraction.clamp(0.0, 1.0);
+    let nm_ok = nomatch_ok.clamp(0.0, 1.0);
+    let nm_bad = nomatch_bad.clamp(0.0, 1.0);
+
+    let nm_t = if nm_bad <= nm_ok {
+        if nm_frac <= nm_ok { 0.0 } else { 1.0 }
+    } else if nm_frac <= nm_ok {
+        0.0
+    } else if nm_frac >= nm_bad {
+        1.0
+    } else {
+        (nm_frac - nm_ok) / (nm_bad - nm_ok)
+    };
+
+    let r = (base_r * (1.0 - nm_t) + dark_red.0 * nm_t).clamp(0.0, 255.0) as u8;
+    let g = (base_g * (1.0 - nm_t) + dark_red.1 * nm_t).clamp(0.0, 255.0) as u8;
+    let b = (base_b * (1.0 - nm_t) + dark_red.2 * nm_t).clamp(0.0, 255.0) as u8;
+
+    [r, g, b]
+}
+
+/// Color for DNA base letters in the template track
+pub fn base_rgb(base: char) -> Rgb {
+    match base {
+        'A' => [100, 200, 100], // Green
+        'T' => [220, 80, 80],   // Red
+        'G' => [255, 200, 60],  // Yellow/gold
+        'C' => [100, 150, 255], // Blue
+        _ => [160, 160, 160],
+    }
+}
+
+struct Fonts {
+    sans: FontRef<'static>,
+    mono: FontRef<'static>,
+}
+
+impl Fonts {
+    fn load() -> Self {
+        Self {
+            sans: FontRef::try_from_slice(epaint_default_fonts::UBUNTU_LIGHT)
+                .expect("bundled font is valid"),
+            mono: FontRef::try_from_slice(epaint_default_fonts::HACK_REGULAR)
+                .expect("bundled font is valid"),
+        }
+    }
+
+    fn get(&self, monospace: bool) -> &FontRef<'static> {
+        if monospace {
+            &self.mono
+        } else {
+            &self.sans
+        }
+    }
+
+    fn text_width(&self, text: &str, size: f32, monospace: bool) -> f32 {
+        let font = self.get(monospace).as_scaled(PxScale::from(size));
+        let mut width = 0.0;
+        let mut prev = None;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(prev) = prev {
+                width += font.kern(prev, id);
+            }
+            width += font.h_advance(id);
+            prev = Some(id);
+        }
+        width
+    }
+}
+
+/// Lay out the heatmap for all oligo lengths of `results`
+pub fn render_heatmap_figure(
+    results: &ScreeningResults,
+    settings: &HeatmapImageSettings,
+) -> HeatmapFigure {
+    let fonts = Fonts::load();
+    let mut lengths: Vec<u32> = results.results_by_length.keys().copied().collect();
+    lengths.sort();
+    let positions: Vec<usize> = lengths
+        .first()
+        .and_then(|l| results.results_by_length.get(l))
+        .map(|lr| lr.positions.iter().map(|p| p.position).collect())
+        .unwrap_or_default();
+
+    let cell_w = settings.cell_width.max(1.0);
+    let cell_h = settings.cell_height.max(1.0);
+    let mut shapes = Vec::new();
+
+    // --- Position numbers row ---
+    let show_every_n = if cell_w < 12.0 {
+        (12.0 / cell_w).ceil() as usize
+    } else {
+        1
+    };
+    let grid_x = MARGIN + LABEL_WIDTH;
+    for (col, &pos) in positions.iter().enumerate() {
+        if col % show_every_n != 0 {
+            continue;
+        }
+        shapes.push(Shape::Text {
+            x: grid_x + col as f32 * cell_w + cell_w / 2.0,
+            y: MARGIN + POS_LABEL_HEIGHT / 2.0,
+            size: 9.0,
+            anchor: TextAnchor::Middle,
+            monospace: false,
+            color: POSITION_TEXT_COLOR,
+            text: (pos + 1).to_string(),
+        });
+    }
+
+    // --- Template base track ---
+    let mut y = MARGIN + POS_LABEL_HEIGHT;
+    if settings.show_base_track {
+        let template = results.template_sequence.as_bytes();
+        for (col, &pos) in positions.iter().enumerate() {
+            let Some(&base) = template.get(pos) else {
+                continue;
+            };
+            let mut base = base as char;
+            let mut color = base_rgb(base);
+            // Soft-masked bases: lowercase, faded toward the background
+            if results.is_masked(pos) {
+                base = base.to_ascii_lowercase();
+                for (c, bg) in color.iter_mut().zip(BACKGROUND) {
+                    *c = ((*c as u16 + bg as u16 * 2) / 3) as u8;
+                }
+            }
+            let x = grid_x + col as f32 * cell_w;
+            if cell_w >= 8.0 {
+                shapes.push(Shape::Text {
+                    x: x + cell_w / 2.0,
+                    y: y + BASE_TRACK_HEIGHT / 2.0,
+                    size: 11.0,
+                    anchor: TextAnchor::Middle,
+                    monospace: true,
+                    color,
+                    text: base.to_string(),
+                });
+            } else {
+                shapes.push(Shape::Rect {
+                    x,
+                    y: y + 2.0,
+                    w: (cell_w - 1.0).max(1.0),
+                    h: BASE_TRACK_HEIGHT - 4.0,
+                    color,
+                });
+            }
+        }
+        y += BASE_TRACK_HEIGHT;
+    }
+
+    // --- Length labels and cells ---
+    let nomatch_ok = settings.nomatch_ok_percent / 100.0;
+    let nomatch_bad = settings.nomatch_bad_percent / 100.0;
+    for (row, length) in lengths.iter().enumerate() {
+        let row_y = y + row as f32 * cell_h;
+        shapes.push(Shape::Text {
+            x: grid_x - 5.0,
+            y: row_y + cell_h / 2.0,
+            size: 11.0,
+            anchor: TextAnchor::End,
+            monospace: false,
+            color: TEXT_COLOR,
+            text: format!("{} bp", length),
+        });
+
+        let cells = &results.results_by_length[length].positions;
+        for (col, &pos) in positions.iter().enumerate() {
+            let color = match cells.binary_search_by_key(&pos, |p| p.position) {
+                Ok(i) if cells[i].analysis.skipped => SKIPPED_RGB,
+                Ok(i) => {
+                    let a = &cells[i].analysis;
+                    let no_match_frac = if a.total_sequences > 0 {
+                        a.no_match_count as f64 / a.total_sequences as f64
+                    } else {
+                        0.0
+                    };
+                    position_rgb(
+                        cells[i].variants_needed,
+                        no_match_frac,
+                        settings.color_green_at,
+                        settings.color_red_at,
+                        nomatch_ok,
+                        nomatch_bad,
+                    )
+                }
+                Err(_) => MISSING_RGB,
+            };
+            shapes.push(Shape::Rect {
+                x: grid_x + col as f32 * cell_w,
+                y: row_y,
+                w: (cell_w - 1.0).max(0.5),
+                h: (cell_h - 1.0).max(0.5),
+                color,
+            });
+        }
+    }
+    y += lengths.len() as f32 * cell_h;
+
+    let mut width = grid_x + positions.len() as f32 * cell_w;
+
+    // --- Legend ---
+    if settings.show_legend {
+        let legend_y = y + LEGEND_HEIGHT / 2.0;
+        let mut x = MARGIN;
+        let label = |shapes: &mut Vec<Shape>, x: &mut f32, text: String| {
+            let w = fonts.text_width(&text, 11.0, false);
+            shapes.push(Shape::Text {
+                x: *x,
+                y: legend_y,
+                size: 11.0,
+                anchor: TextAnchor::Start,
+                monospace: false,
+                color: TEXT_COLOR,
+                text,
+            });
+            *x += w + 8.0;
+        };
+        let swatch = |shapes: &mut Vec<Shape>, x: &mut f32, color: Rgb| {
+            shapes.push(Shape::Rect {
+                x: *x,
+                y: legend_y - SWATCH / 2.0,
+                w: SWATCH,
+                h: SWATCH,
+                color,
+            });
+            *x += SWATCH + 4.0;
+        };
+
+        let g = settings.color_green_at;
+        let r = settings.color_red_at;
+        let mut samples = vec![(g, format!("<={}", g))];
+        if r <= g {
+            samples.push((g + 1, format!(">{}", g)));
+        } else {
+            let mid = (g + r) / 2;
+            if mid > g && mid < r {
+                samples.push((mid, mid.to_string()));
+            }
+            samples.push((r, format!(">={}", r)));
+        }
+
+        label(&mut shapes, &mut x, "Variants needed:".to_string());
+        for (count, text) in samples {
+            swatch(
+                &mut shapes,
+                &mut x,
+                position_rgb(count, 0.0, g, r, nomatch_ok, nomatch_bad),
+            );
+            label(&mut shapes, &mut x, text);
+        }
+
+        x += 8.0;
+        label(&mut shapes, &mut x, "No match:".to_string());
+        let mid_count = ((g + r) / 2).max(1);
+        for (frac, percent) in [
+            (nomatch_ok, settings.nomatch_ok_percent),
+            (nomatch_bad, settings.nomatch_bad_percent),
+        ] {
+            swatch(
+                &mut shapes,
+                &mut x,
+                position_rgb(mid_count, frac, g, r, nomatch_ok, nomatch_bad),
+            );
+            label(&mut shapes, &mut x, format!("{}%", percent as u32));
+        }
+
+        x += 8.0;
+        swatch(&mut shapes, &mut x, SKIPPED_RGB);
+        label(&mut shapes, &mut x, "skipped/no data".to_string());
+
+        width = width.max(x);
+        y += LEGEND_HEIGHT;
+    }
+
+    HeatmapFigure {
+        width: width + MARGIN,
+        height: y + MARGIN,
+        shapes,
+    }
+}
+
+fn hex(color: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl HeatmapFigure {
+    /// Write the figure as a standalone SVG document
+    pub fn to_svg(&self) -> String {
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = self.width,
+            h = self.height
+        );
+        out.push_str(&format!(
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+            hex(BACKGROUND)
+        ));
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect { x, y, w, h, color } => out.push_str(&format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    x,
+                    y,
+                    w,
+                    h,
+                    hex(*color)
+                )),
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    anchor,
+                    monospace,
+                    color,
+                    text,
+                } => {
+                    let anchor = match anchor {
+                        TextAnchor::Start => "start",
+                        TextAnchor::Middle => "middle",
+                        TextAnchor::End => "end",
+                    };
+                    let family = if *monospace {
+                        "Hack, monospace"
+                    } else {
+                        "Ubuntu, sans-serif"
+                    };
+                    out.push_str(&format!(
+                        "<text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"{}\" fill=\"{}\" text-anchor=\"{}\" dominant-baseline=\"central\">{}</text>\n",
+                        x,
+                        y,
+                        family,
+                        size,
+                        hex(*color),
+                        anchor,
+                        escape_xml(text)
+                    ));
+                }
+            }
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Rasterize the figure and encode it as PNG. `scale` is the number of
+    /// pixels per figure unit (e.g. 3.0 for a 300 dpi print of a 100 dpi
+    /// layout).
+    pub fn to_png(&self, scale: f32) -> Result<Vec<u8>, String> {
+        let scale = scale.max(0.1);
+        let width = (self.width * scale).ceil() as usize;
+        let height = (self.height * scale).ceil() as usize;
+        if width == 0 || height == 0 {
+            return Err("Figure is empty".to_string());
+        }
+        let (Ok(png_width), Ok(png_height)) = (u32::try_from(width), u32::try_from(height)) else {
+            return Err(format!("Image of {} × {} pixels is too large for PNG", width, height));
+        };
+        if width.checked_mul(height).is_none_or(|pixels| pixels > MAX_PNG_PIXELS) {
+            return Err(format!(
+                "Image of {} × {} pixels exceeds {} million pixels; lower the resolution \
+                 or the cell size, or export as SVG",
+                width,
+                height,
+                MAX_PNG_PIXELS / 1_000_000
+            ));
+        }
+
+        let mut canvas = Canvas {
+            width,
+            height,
+            pixels: BACKGROUND.repeat(width * height),
+        };
+        let fonts = Fonts::load();
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect { x, y, w, h, color } => {
+                    canvas.fill_rect(x * scale, y * scale, w * scale, h * scale, *color)
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    anchor,
+                    monospace,
+                    color,
+                    text,
+                } => canvas.draw_text(
+                    fonts.get(*monospace),
+                    text,
+                    x * scale,
+                    y * scale,
+                    size * scale,
+                    *anchor,
+                    *color,
+                ),
+            }
+        }
+
+        let mut png_data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png_data, png_width, png_height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let png_err = |e: png::EncodingError| format!("Failed to encode PNG: {}", e);
+        let mut writer = encoder.write_header().map_err(png_err)?;
+        writer.write_image_data(&canvas.pixels).map_err(png_err)?;
+        writer.finish().map_err(png_err)?;
+        Ok(png_data)
+    }
+}
+
+/// RGB pixel buffer used for PNG output
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn blend(&mut self, x: i64, y: i64, color: Rgb, alpha: f32) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let alpha = alpha.clamp(0.0, 1.0);
+        let i = (y as usize * self.width + x as usize) * 3;
+        for (pixel, &target) in self.pixels[i..i + 3].iter_mut().zip(&color) {
+            let old = *pixel as f32;
+            *pixel = (old + (target as f32 - old) * alpha).round() as u8;
+        }
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: Rgb) {
+        let x0 = x.round() as i64;
+        let y0 = y.round() as i64;
+        // Keep at least one pixel so thin cells stay visible when scaled down
+        let x1 = (x + w).round().max(x0 as f32 + 1.0) as i64;
+        let y1 = (y + h).round().max(y0 as f32 + 1.0) as i64;
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.blend(px, py, color, 1.0);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_text(
+        &mut self,
+        font: &FontRef<'static>,
+        text: &str,
+        x: f32,
+        y: f32,
+        size: f32,
+        anchor: TextAnchor,
+        color: Rgb,
+    ) {
+        let scaled = font.as_scaled(PxScale::from(size));
+        let mut glyphs = Vec::new();
+        let mut caret = 0.0;
+        let mut prev = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(prev) = prev {
+                caret += scaled.kern(prev, id);
+            }
+            glyphs.push((id, caret));
+            caret += scaled.h_advance(id);
+            prev = Some(id);
+        }
+
+        let start_x = match anchor {
+            TextAnchor::Start => x,
+            TextAnchor::Middle => x - caret / 2.0,
+            TextAnchor::End => x - caret,
+        };
+        // Center the ascent..descent box on y
+        let baseline = y + (scaled.ascent() + scaled.descent()) / 2.0;
+
+        for (id, offset) in glyphs {
+            let glyph = id.with_scale_and_position(scaled.scale(), point(start_x + offset, baseline));
+            if let Some(outlined) = font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|gx, gy, coverage| {
+                    self.blend(
+                        bounds.min.x as i64 + gx as i64,
+                        bounds.min.y as i64 + gy as i64,
+                        color,
+                        coverage,
+                    );
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::{
+        AnalysisParams, LengthResult, PositionResult, WindowAnalysisResult,
+    };
+
+    fn sample_results() -> ScreeningResults {
+        let mut results =
+            ScreeningResults::new(AnalysisParams::default(), 6, 4, "ACGTAC".to_string());
+        for length in [4, 5] {
+            let positions = (0..3)
+                .map(|position| PositionResult {
+                    position,
+                    variants_needed: position + 1,
+                    source: None,
+                    analysis: WindowAnalysisResult {
+                        total_sequences: 4,
+                        skipped: position == 2 && length == 5,
+                        ..Default::default()
+                    },
+                })
+                .collect();
+            results.results_by_length.insert(
+                length,
+                LengthResult {
+                    oligo_length: length,
+                    positions,
+                    column_profile: Vec::new(),
+                },
+            );
+        }
+        results
+    }
+
+    #[test]
+    fn test_position_rgb_thresholds() {
+        assert_eq!(position_rgb(1, 0.0, 1, 10, 0.05, 0.5), [0, 180, 0]);
+        assert_eq!(position_rgb(10, 0.0, 1, 10, 0.05, 0.5), [220, 50, 50]);
+        assert_eq!(position_rgb(1, 0.5, 1, 10, 0.05, 0.5), [100, 20, 20]);
+        assert_eq!(position_rgb(0, 0.0, 1, 10, 0.05, 0.5), SKIPPED_RGB);
+    }
+
+    #[test]
+    fn test_figure_svg_and_png() {
+        let settings = HeatmapImageSettings::default();
+        let figure = render_heatmap_figure(&sample_results(), &settings);
+        let svg = figure.to_svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(">4 bp</text>"));
+        assert!(svg.contains(">5 bp</text>"));
+        // 6 cells, 1 skipped cell and the skipped legend swatch
+        assert_eq!(svg.matches(&format!("fill=\"{}\"", hex(SKIPPED_RGB))).count(), 2);
+
+        let png = figure.to_png(2.0).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+        assert_eq!(width, (figure.width * 2.0).ceil() as u32);
+
+        // Too large to rasterize: rejected before allocating
+        let huge = HeatmapFigure {
+            width: 1.0e6,
+            height: 1.0e6,
+            shapes: Vec::new(),
+        };
+        assert!(huge.to_png(1.0).is_err());
+        assert!(huge.to_png(1.0e6).is_err());
+    }
+}
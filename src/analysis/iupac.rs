@@ -175,7 +175,7 @@ pub fn count_ambiguities(seq: &str) -> usize {
 
 // ── Bitmask-based IUPAC operations (zero heap allocation) ──────────────────
 
-/// Bitmask representation: bit 0 = A, bit 1 = C, bit 2 = G, bit 3 = T
+// Bitmask representation: bit 0 = A, bit 1 = C, bit 2 = G, bit 3 = T
 
 /// Lookup table: 4-bit bitmask index -> IUPAC code byte.
 /// Index 0 (no bases) maps to b'?' and should not occur with valid DNA data.
@@ -0,0 +1,181 @@
+//! Sequence logo data for one window
+//!
+//! Columns are built from the stored per-reference matches when available,
+//! otherwise from the variant list weighted by count. Ambiguity codes in
+//! variants are split evenly over the bases they stand for. Information
+//! content uses the small-sample correction of Schneider et al. (1986):
+//! `R = 2 − (H + 3 / (2 ln 2 · n))`, clamped at 0.
+
+use super::iupac::iupac_to_mask;
+use super::types::WindowAnalysisResult;
+
+/// Logo bases in mask bit order
+pub const LOGO_BASES: [char; 4] = ['A', 'C', 'G', 'T'];
+
+/// One stacked column of a sequence logo
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogoColumn {
+    /// (base, letter height in bits), smallest first (bottom of the stack)
+    pub letters: Vec<(char, f64)>,
+    /// Information content in bits (0..=2)
+    pub information: f64,
+    /// Number of sequences contributing to the column
+    pub weight: f64,
+}
+
+/// Where the logo's sequences came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogoSource {
+    Matches,
+    Variants,
+}
+
+/// Build logo columns for a window, optionally for the reverse complement.
+/// Returns `None` if the window has neither match data nor variants.
+pub fn sequence_logo(
+    analysis: &WindowAnalysisResult,
+    reverse_complement: bool,
+) -> Option<(Vec<LogoColumn>, LogoSource)> {
+    let (weighted, source): (Vec<(&str, f64)>, LogoSource) = if !analysis.matches.is_empty() {
+        (
+            analysis
+                .matches
+                .iter()
+                .map(|g| (g.sequence.as_str(), g.references.len() as f64))
+                .collect(),
+            LogoSource::Matches,
+        )
+    } else if !analysis.variants.is_empty() {
+        (
+            analysis
+                .variants
+                .iter()
+                .map(|v| (v.sequence.as_str(), v.count as f64))
+                .collect(),
+            LogoSource::Variants,
+        )
+    } else {
+        return None;
+    };
+
+    let length = weighted.iter().map(|(s, _)| s.len()).max().unwrap_or(0);
+    let mut counts = vec![[0.0f64; 4]; length];
+    for (seq, weight) in weighted {
+        for (column, &b) in counts.iter_mut().zip(seq.as_bytes()) {
+            let mask = iupac_to_mask(b.to_ascii_uppercase());
+            if mask == 0 {
+                continue;
+            }
+            let share = weight / mask.count_ones() as f64;
+            for (bit, count) in column.iter_mut().enumerate() {
+                if mask & (1 << bit) != 0 {
+                    *count += share;
+                }
+            }
+        }
+    }
+
+    if reverse_complement {
+        counts.reverse();
+        // A,C,G,T complemented is T,G,C,A: the reversed base order
+        for column in &mut counts {
+            column.reverse();
+        }
+    }
+
+    Some((counts.iter().map(logo_column).collect(), source))
+}
+
+fn logo_column(counts: &[f64; 4]) -> LogoColumn {
+    let n: f64 = counts.iter().sum();
+    if n <= 0.0 {
+        return LogoColumn {
+            letters: Vec::new(),
+            information: 0.0,
+            weight: 0.0,
+        };
+    }
+
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&c| c > 0.0)
+        .map(|&c| {
+            let p = c / n;
+            -p * p.log2()
+        })
+        .sum();
+    let correction = 3.0 / (2.0 * std::f64::consts::LN_2 * n);
+    let information = (2.0 - (entropy + correction)).max(0.0);
+
+    let mut letters: Vec<(char, f64)> = LOGO_BASES
+        .iter()
+        .zip(counts)
+        .filter(|(_, &c)| c > 0.0)
+        .map(|(&base, &c)| (base, c / n * information))
+        .collect();
+    letters.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    LogoColumn {
+        letters,
+        information,
+        weight: n,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::{MatchGroup, Variant};
+
+    #[test]
+    fn test_logo_from_matches_and_reverse_complement() {
+        let analysis = WindowAnalysisResult {
+            matches: vec![
+                MatchGroup {
+                    sequence: "AC".to_string(),
+                    mismatches: 0,
+                    references: (0..30).collect(),
+                },
+                MatchGroup {
+                    sequence: "AG".to_string(),
+                    mismatches: 1,
+                    references: (30..60).collect(),
+                },
+            ],
+            ..Default::default()
+        };
+        let (columns, source) = sequence_logo(&analysis, false).unwrap();
+        assert_eq!(source, LogoSource::Matches);
+        let correction = 3.0 / (2.0 * std::f64::consts::LN_2 * 60.0);
+        assert!((columns[0].information - (2.0 - correction)).abs() < 1e-9);
+        assert_eq!(columns[0].letters.len(), 1);
+        assert!((columns[1].information - (1.0 - correction)).abs() < 1e-9);
+        assert_eq!(columns[1].letters.len(), 2);
+
+        // Reverse complement: first column is the complement of the last
+        let (rc, _) = sequence_logo(&analysis, true).unwrap();
+        let bases: Vec<char> = rc[0].letters.iter().map(|(b, _)| *b).collect();
+        assert!(bases.contains(&'G') && bases.contains(&'C'));
+        assert_eq!(rc[1].letters[0].0, 'T');
+    }
+
+    #[test]
+    fn test_logo_from_variants_splits_ambiguity_codes() {
+        let analysis = WindowAnalysisResult {
+            variants: vec![Variant {
+                sequence: "R".to_string(),
+                count: 10,
+                percentage: 100.0,
+            }],
+            ..Default::default()
+        };
+        let (columns, source) = sequence_logo(&analysis, false).unwrap();
+        assert_eq!(source, LogoSource::Variants);
+        assert_eq!(columns[0].weight, 10.0);
+        let bases: Vec<char> = columns[0].letters.iter().map(|(b, _)| *b).collect();
+        assert_eq!(bases.len(), 2);
+        assert!(bases.contains(&'A') && bases.contains(&'G'));
+
+        assert!(sequence_logo(&WindowAnalysisResult::default(), false).is_none());
+    }
+}
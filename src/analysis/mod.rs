@@ -9,6 +9,30 @@ mod fasta;
 mod analyzer;
 mod pairwise;
 mod screener;
+mod compare;
+mod project;
+mod binary_results;
+mod thermo;
+mod export;
+mod candidates;
+mod heatmap_image;
+mod tracks;
+mod logo;
+mod alignment_view;
+mod ranking;
+mod regions;
+mod overview;
+mod motif;
+mod oligo_check;
+mod specificity;
+mod groups;
+mod multiplex;
+mod template_builder;
+mod multi_template;
+mod template_ambiguity;
+mod sequence_file;
+mod reference_filter;
+mod reference_metadata;
 
 pub use types::*;
 pub use iupac::*;
@@ -16,3 +40,27 @@ pub use fasta::*;
 pub use analyzer::*;
 pub use pairwise::*;
 pub use screener::*;
+pub use compare::*;
+pub use project::*;
+pub use binary_results::*;
+pub use thermo::*;
+pub use export::*;
+pub use candidates::*;
+pub use heatmap_image::*;
+pub use tracks::*;
+pub use logo::*;
+pub use alignment_view::*;
+pub use ranking::*;
+pub use regions::*;
+pub use overview::*;
+pub use motif::*;
+pub use oligo_check::*;
+pub use specificity::*;
+pub use groups::*;
+pub use multiplex::*;
+pub use template_builder::*;
+pub use multi_template::*;
+pub use template_ambiguity::*;
+pub use sequence_file::*;
+pub use reference_filter::*;
+pub use reference_metadata::*;
@@ -0,0 +1,150 @@
+//! Motif search in the template sequence
+//!
+//! A motif (for example a published primer, possibly with IUPAC codes) is
+//! compared to every template window of its length, on both strands. A
+//! motif base matches a template base when their IUPAC base sets overlap.
+//! If the motif occurs nowhere without mismatches, the closest windows are
+//! returned instead.
+
+use super::iupac::{iupac_to_mask, reverse_complement};
+
+/// A template window compared to the motif
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MotifHit {
+    /// 0-based template start of the window
+    pub position: usize,
+    /// The motif matched the reverse strand (its reverse complement matched)
+    pub reverse_complement: bool,
+    pub mismatches: usize,
+}
+
+/// Result of a motif search
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotifSearch {
+    /// Normalized motif (uppercase, whitespace removed, U read as T)
+    pub motif: String,
+    /// Exact hits, or the closest windows when `exact` is false
+    pub hits: Vec<MotifHit>,
+    pub exact: bool,
+}
+
+impl MotifSearch {
+    pub fn len(&self) -> usize {
+        self.motif.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.motif.is_empty()
+    }
+}
+
+/// Search `template` for `motif` on both strands. Returns all exact hits, or
+/// up to `max_closest` windows with the fewest mismatches if there are none.
+pub fn search_motif(
+    template: &str,
+    motif: &str,
+    max_closest: usize,
+) -> Result<MotifSearch, String> {
+    let motif: String = motif
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c.to_ascii_uppercase() {
+            'U' => 'T',
+            c => c,
+        })
+        .collect();
+    if motif.is_empty() {
+        return Err("Enter a motif to search for".to_string());
+    }
+    if let Some(c) = motif.chars().find(|&c| !c.is_ascii() || iupac_to_mask(c as u8) == 0) {
+        return Err(format!("Invalid character '{}' in motif", c));
+    }
+    if motif.len() > template.len() {
+        return Err(format!(
+            "Motif ({} bp) is longer than the template ({} bp)",
+            motif.len(),
+            template.len()
+        ));
+    }
+
+    let template: Vec<u8> = template
+        .bytes()
+        .map(|b| iupac_to_mask(b.to_ascii_uppercase()))
+        .collect();
+    let strands = [
+        (false, motif.bytes().map(iupac_to_mask).collect::<Vec<u8>>()),
+        (
+            true,
+            reverse_complement(&motif).bytes().map(iupac_to_mask).collect(),
+        ),
+    ];
+
+    let mut hits = Vec::new();
+    for window_start in 0..=template.len() - motif.len() {
+        let window = &template[window_start..window_start + motif.len()];
+        for (reverse, masks) in &strands {
+            let mismatches = window
+                .iter()
+                .zip(masks)
+                .filter(|(t, m)| *t & *m == 0)
+                .count();
+            hits.push(MotifHit {
+                position: window_start,
+                reverse_complement: *reverse,
+                mismatches,
+            });
+        }
+    }
+
+    let exact = hits.iter().any(|h| h.mismatches == 0);
+    if exact {
+        hits.retain(|h| h.mismatches == 0);
+    } else {
+        // Stable sort keeps template order among equally close windows
+        hits.sort_by_key(|h| h.mismatches);
+        hits.truncate(max_closest);
+    }
+    Ok(MotifSearch { motif, hits, exact })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_hits_on_both_strands() {
+        // ACGTTG at 2, its reverse complement CAACGT at 10
+        let template = "TTACGTTGAACAACGTAA";
+        let search = search_motif(template, "acgttg", 5).unwrap();
+        assert!(search.exact);
+        assert_eq!(
+            search.hits,
+            vec![
+                MotifHit {
+                    position: 2,
+                    reverse_complement: false,
+                    mismatches: 0
+                },
+                MotifHit {
+                    position: 10,
+                    reverse_complement: true,
+                    mismatches: 0
+                },
+            ]
+        );
+        // IUPAC: R = A/G
+        assert!(search_motif(template, "RCGTTG", 5).unwrap().exact);
+        assert!(search_motif(template, "ACG-TG", 5).is_err());
+    }
+
+    #[test]
+    fn test_closest_windows_when_absent() {
+        let template = "AAAAACCCCCGGGGG";
+        let search = search_motif(template, "CCCTC", 3).unwrap();
+        assert!(!search.exact);
+        assert_eq!(search.hits.len(), 3);
+        assert_eq!(search.hits[0].mismatches, 1);
+        assert_eq!(search.hits[0].position, 5);
+        assert!(search.hits.windows(2).all(|w| w[0].mismatches <= w[1].mismatches));
+    }
+}
@@ -0,0 +1,500 @@
+//! Screening with several templates merged into one coordinate system
+//!
+//! A window that is missing from the template (for example a deletion
+//! relative to other strains) is never screened. To reduce this bias, a few
+//! representative references are screened as additional templates. Each
+//! additional template is aligned globally to the template, and each of its
+//! windows is placed at the template coordinate of its first base. A heatmap
+//! cell keeps the best window placed on it: not skipped, then fewest variants
+//! needed, then highest coverage. Cells taken from an additional template
+//! record which one in `PositionResult::source`. Windows that start in an
+//! insertion relative to the template have no column of their own; the
+//! screened ones are kept in `ScreeningResults::insertion_windows`.
+//!
+//! The global alignment is anchored: the longest chain of shared 12-mers
+//! (LCSk++) is mapped directly, and only the stretches between anchors are
+//! aligned base by base. A stretch larger than `MAX_UNANCHORED_CELLS`
+//! alignment matrix cells is an error, which keeps the memory bounded for
+//! long templates.
+
+use bio::alignment::pairwise::{Aligner, MatchParams};
+use bio::alignment::sparse::{find_kmer_matches, lcskpp};
+use bio::alignment::AlignmentOperation;
+use std::sync::mpsc::{channel, Sender};
+
+use super::fasta::{ReferenceData, TemplateData};
+use super::screener::run_screening;
+use super::template_builder::kmer_distance_matrix;
+use super::types::{
+    AnalysisParams, CellSource, ExtraTemplate, InsertionWindow, PairwiseParams, PositionResult,
+    ProgressUpdate, ScreeningResults,
+};
+
+/// K-mer length of the anchors between two templates
+const ANCHOR_LENGTH: usize = 12;
+
+/// Largest stretch between anchors that is aligned base by base, in
+/// alignment matrix cells (about 2 bytes each)
+pub const MAX_UNANCHORED_CELLS: usize = 16_000_000;
+
+/// Pick up to `count` references as additional templates, farthest-first by
+/// k-mer distance from the template and the ones already picked. Only
+/// gap-free A/C/G/T references are eligible, and references identical in
+/// k-mer content to a picked one are skipped.
+pub fn pick_extra_templates(
+    template: &str,
+    references: &ReferenceData,
+    count: usize,
+) -> Result<Vec<usize>, String> {
+    let mut sequences = vec![template.as_bytes().to_vec()];
+    sequences.extend(references.sequences.iter().map(|s| s.as_bytes().to_vec()));
+    let eligible: Vec<bool> = sequences
+        .iter()
+        .map(|s| !s.is_empty() && s.iter().all(|b| b"ACGT".contains(b)))
+        .collect();
+    let distances = kmer_distance_matrix(&sequences);
+
+    // Distance of each sequence to the closest picked one
+    let mut closest = distances[0].clone();
+    let mut picked = Vec::new();
+    while picked.len() < count {
+        let next = (1..sequences.len())
+            .filter(|&i| eligible[i] && closest[i] > 0.0)
+            .max_by(|&a, &b| closest[a].total_cmp(&closest[b]));
+        let Some(next) = next else {
+            break;
+        };
+        picked.push(next - 1);
+        for (c, d) in closest.iter_mut().zip(&distances[next]) {
+            *c = c.min(*d);
+        }
+    }
+    Ok(picked)
+}
+
+/// Pick `params.extra_templates` references as additional templates and map
+/// each to `template` (none when the setting is 0)
+pub fn align_extra_templates(
+    template: &TemplateData,
+    references: &ReferenceData,
+    params: &AnalysisParams,
+) -> Result<Vec<(TemplateData, Vec<MappedBase>)>, String> {
+    if params.extra_templates == 0 {
+        return Ok(Vec::new());
+    }
+    let picked = pick_extra_templates(&template.sequence, references, params.extra_templates)?;
+    picked
+        .into_iter()
+        .map(|i| {
+            let extra = TemplateData {
+                name: references.names[i].clone(),
+                sequence: references.sequences[i].clone(),
+                masked: Vec::new(),
+            };
+            let map = map_to_template(
+                template.sequence.as_bytes(),
+                extra.sequence.as_bytes(),
+                &params.pairwise,
+            )
+            .map_err(|e| format!("Cannot align {} to the template: {}", extra.name, e))?;
+            Ok((extra, map))
+        })
+        .collect()
+}
+
+/// Template coordinate of a base of an additional template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MappedBase {
+    /// Aligned template position; for an inserted base the next template
+    /// position (the last one after the end of the template)
+    pub position: usize,
+    /// Whether the base is in an insertion relative to the template
+    pub inserted: bool,
+}
+
+/// Globally align `other` to `template`, a stretch starting at template
+/// position `offset`, and append the coordinates of `other` to `map`
+fn map_stretch(
+    template: &[u8],
+    other: &[u8],
+    offset: usize,
+    last: usize,
+    pairwise: &PairwiseParams,
+    map: &mut Vec<MappedBase>,
+) -> Result<(), String> {
+    let inserted = MappedBase {
+        position: offset.min(last),
+        inserted: true,
+    };
+    if other.is_empty() || template.is_empty() {
+        map.extend(std::iter::repeat_n(inserted, other.len()));
+        return Ok(());
+    }
+    if (other.len() + 1) * (template.len() + 1) > MAX_UNANCHORED_CELLS {
+        return Err(format!(
+            "{} bp share no {}-mer with the {} bp of the template they align to",
+            other.len(),
+            ANCHOR_LENGTH,
+            template.len()
+        ));
+    }
+    let mut aligner = Aligner::with_capacity(
+        other.len(),
+        template.len(),
+        pairwise.gap_open_penalty,
+        pairwise.gap_extend_penalty,
+        MatchParams::new(pairwise.match_score, pairwise.mismatch_score),
+    );
+    let alignment = aligner.global(other, template);
+
+    let mut yi = offset;
+    for op in &alignment.operations {
+        match op {
+            AlignmentOperation::Match | AlignmentOperation::Subst => {
+                map.push(MappedBase {
+                    position: yi.min(last),
+                    inserted: false,
+                });
+                yi += 1;
+            }
+            AlignmentOperation::Del => yi += 1,
+            AlignmentOperation::Ins => map.push(MappedBase {
+                position: yi.min(last),
+                inserted: true,
+            }),
+            AlignmentOperation::Xclip(_) | AlignmentOperation::Yclip(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Template coordinate of every base of `other`, from a global alignment
+/// anchored on shared k-mers
+pub fn map_to_template(
+    template: &[u8],
+    other: &[u8],
+    pairwise: &PairwiseParams,
+) -> Result<Vec<MappedBase>, String> {
+    let matches = find_kmer_matches(other, template, ANCHOR_LENGTH);
+    let chain = lcskpp(&matches, ANCHOR_LENGTH);
+    let last = template.len().saturating_sub(1);
+
+    let mut map = Vec::with_capacity(other.len());
+    // First unmapped base of each sequence
+    let (mut x, mut y) = (0usize, 0usize);
+    for &i in &chain.path {
+        let (kx, ky) = (matches[i].0 as usize, matches[i].1 as usize);
+        // Consecutive k-mers of the chain overlap on the same diagonal
+        let mapped = x.saturating_sub(kx).max(y.saturating_sub(ky));
+        if mapped >= ANCHOR_LENGTH {
+            continue;
+        }
+        let (kx, ky) = (kx + mapped, ky + mapped);
+        map_stretch(&template[y..ky], &other[x..kx], y, last, pairwise, &mut map)?;
+        map.extend((ky..ky + ANCHOR_LENGTH - mapped).map(|position| MappedBase {
+            position,
+            inserted: false,
+        }));
+        x = kx + ANCHOR_LENGTH - mapped;
+        y = ky + ANCHOR_LENGTH - mapped;
+    }
+    map_stretch(&template[y..], &other[x..], y, last, pairwise, &mut map)?;
+    Ok(map)
+}
+
+/// Whether `a` is a better cell than `b`
+fn is_better(a: &PositionResult, b: &PositionResult) -> bool {
+    if a.analysis.skipped != b.analysis.skipped {
+        return !a.analysis.skipped;
+    }
+    if a.analysis.skipped {
+        return false;
+    }
+    if a.variants_needed != b.variants_needed {
+        return a.variants_needed < b.variants_needed;
+    }
+    a.analysis.coverage_at_threshold > b.analysis.coverage_at_threshold
+}
+
+/// Merge the results of additional templates into the template's results.
+/// Each entry holds the template, its results and its `map_to_template`
+/// coordinates. Cells are only replaced, so the heatmap columns stay those of
+/// the template; screened windows starting in an insertion are kept in
+/// `insertion_windows` instead.
+pub fn merge_template_results(
+    mut merged: ScreeningResults,
+    extras: Vec<(ExtraTemplate, ScreeningResults, Vec<MappedBase>)>,
+) -> ScreeningResults {
+    for (template_index, (template, extra, map)) in extras.into_iter().enumerate() {
+        for (length, extra_length) in extra.results_by_length {
+            let Some(length_result) = merged.results_by_length.get_mut(&length) else {
+                continue;
+            };
+            for cell in extra_length.positions {
+                let Some(&mapped) = map.get(cell.position) else {
+                    continue;
+                };
+                // Column at or before the mapped start
+                let slot = length_result
+                    .positions
+                    .partition_point(|p| p.position <= mapped.position);
+                let Some(current) = slot.checked_sub(1).map(|i| &mut length_result.positions[i])
+                else {
+                    continue;
+                };
+                let source = Some(CellSource {
+                    template: template_index,
+                    position: cell.position,
+                });
+                if mapped.inserted {
+                    if !cell.analysis.skipped {
+                        merged.insertion_windows.push(InsertionWindow {
+                            oligo_length: length,
+                            next_template_base: mapped.position,
+                            cell: PositionResult {
+                                position: current.position,
+                                source,
+                                ..cell
+                            },
+                        });
+                    }
+                } else if is_better(&cell, current) {
+                    *current = PositionResult {
+                        position: current.position,
+                        source,
+                        ..cell
+                    };
+                }
+            }
+        }
+        merged.extra_templates.push(template);
+    }
+    merged.insertion_windows.sort_by_key(|w| {
+        let source = w.cell.source.unwrap_or(CellSource {
+            template: 0,
+            position: 0,
+        });
+        (w.cell.position, w.oligo_length, source.template, source.position)
+    });
+    merged
+}
+
+/// `run_screening` with the progress messages prefixed by `label`
+fn screen_labeled(
+    template: &TemplateData,
+    references: &ReferenceData,
+    params: &AnalysisParams,
+    progress_tx: &Option<Sender<ProgressUpdate>>,
+    label: String,
+) -> ScreeningResults {
+    let Some(outer) = progress_tx.clone() else {
+        return run_screening(template, references, params, None);
+    };
+    let (tx, rx) = channel::<ProgressUpdate>();
+    let forward = std::thread::spawn(move || {
+        for mut update in rx {
+            update.message = format!("{}: {}", label, update.message);
+            if outer.send(update).is_err() {
+                break;
+            }
+        }
+    });
+    let results = run_screening(template, references, params, Some(tx));
+    let _ = forward.join();
+    results
+}
+
+/// Screen the template and every additional template, and merge the results
+/// into the template's coordinates. Each additional template comes with its
+/// `map_to_template` coordinates.
+pub fn run_multi_template_screening(
+    template: &TemplateData,
+    extra_templates: &[(TemplateData, Vec<MappedBase>)],
+    references: &ReferenceData,
+    params: &AnalysisParams,
+    progress_tx: Option<Sender<ProgressUpdate>>,
+) -> ScreeningResults {
+    let total = extra_templates.len() + 1;
+    let primary = screen_labeled(
+        template,
+        references,
+        params,
+        &progress_tx,
+        format!("Template 1/{}", total),
+    );
+
+    let extras = extra_templates
+        .iter()
+        .enumerate()
+        .map(|(i, (extra, map))| {
+            let results = screen_labeled(
+                extra,
+                references,
+                params,
+                &progress_tx,
+                format!("Template {}/{} ({})", i + 2, total, extra.name),
+            );
+            let extra = ExtraTemplate {
+                name: extra.name.clone(),
+                sequence: extra.sequence.clone(),
+            };
+            (extra, results, map.clone())
+        })
+        .collect();
+
+    merge_template_results(primary, extras)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::{LengthResult, WindowAnalysisResult};
+
+    #[test]
+    fn test_map_to_template_across_insertion() {
+        let template = b"AAAACCCCGGGGTTTT";
+        let other = b"AAAACCCCATATGGGGTTTT";
+        let map = map_to_template(template, other, &PairwiseParams::default()).unwrap();
+        assert_eq!(map.len(), other.len());
+        let positions: Vec<usize> = map.iter().map(|m| m.position).collect();
+        assert_eq!(&positions[..8], &[0, 1, 2, 3, 4, 5, 6, 7]);
+        // Inserted ATAT sits before template position 8
+        assert_eq!(&positions[8..12], &[8, 8, 8, 8]);
+        assert_eq!(positions[12], 8);
+        assert_eq!(positions[19], 15);
+        let inserted: Vec<usize> = (0..map.len()).filter(|&i| map[i].inserted).collect();
+        assert_eq!(inserted, vec![8, 9, 10, 11]);
+    }
+
+    #[test]
+    fn test_map_to_template_through_anchors() {
+        // Pseudo-random template with a deletion and an insertion in `other`
+        let mut state = 12345u32;
+        let template: Vec<u8> = (0..3000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                b"ACGT"[(state >> 16) as usize % 4]
+            })
+            .collect();
+        let mut other = template[..1000].to_vec();
+        other.extend_from_slice(&template[1100..2000]);
+        other.extend_from_slice(b"CCCCCGGGGG");
+        other.extend_from_slice(&template[2000..]);
+
+        let map = map_to_template(&template, &other, &PairwiseParams::default()).unwrap();
+        assert_eq!(map.len(), other.len());
+        assert_eq!(map[999].position, 999);
+        assert_eq!(map[1000].position, 1100);
+        assert!(map[1900..1910].iter().all(|m| m.inserted && m.position == 2000));
+        assert_eq!(map[1910].position, 2000);
+        assert_eq!(map.iter().filter(|m| m.inserted).count(), 10);
+
+        // Without shared k-mers the stretch is too large to align
+        let (a, c) = (vec![b'A'; 5000], vec![b'C'; 5000]);
+        assert!(map_to_template(&a, &c, &PairwiseParams::default()).is_err());
+    }
+
+    fn results(cells: &[(usize, usize, bool)]) -> ScreeningResults {
+        let mut results = ScreeningResults::new(AnalysisParams::default(), 10, 4, "A".repeat(10));
+        results.results_by_length.insert(
+            4,
+            LengthResult {
+                oligo_length: 4,
+                positions: cells
+                    .iter()
+                    .map(|&(position, variants_needed, skipped)| PositionResult {
+                        position,
+                        variants_needed,
+                        source: None,
+                        analysis: WindowAnalysisResult {
+                            skipped,
+                            coverage_at_threshold: 100.0,
+                            ..Default::default()
+                        },
+                    })
+                    .collect(),
+                column_profile: Vec::new(),
+            },
+        );
+        results
+    }
+
+    #[test]
+    fn test_merge_keeps_best_cell_per_column() {
+        let primary = results(&[(0, 1, false), (2, 0, true), (4, 3, false), (6, 2, false)]);
+        // Extra windows 0..4 map to template columns 0, 2, 2, 4 and 6;
+        // window 1 starts in an insertion before template position 2
+        let extra = results(&[
+            (0, 2, false),
+            (1, 5, false),
+            (2, 1, false),
+            (3, 2, false),
+            (4, 2, false),
+        ]);
+        let map: Vec<MappedBase> = [0, 2, 3, 4, 6, 7]
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| MappedBase {
+                position,
+                inserted: i == 1,
+            })
+            .collect();
+        let template = ExtraTemplate {
+            name: "strain2".to_string(),
+            sequence: "C".repeat(10),
+        };
+
+        let merged = merge_template_results(primary, vec![(template.clone(), extra, map)]);
+        assert_eq!(merged.extra_templates, vec![template]);
+        let cells = &merged.results_by_length[&4].positions;
+        let summary: Vec<(usize, usize, Option<CellSource>)> = cells
+            .iter()
+            .map(|c| (c.position, c.variants_needed, c.source))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, 1, None),
+                // Skipped column replaced by the best window placed on it
+                (
+                    2,
+                    1,
+                    Some(CellSource {
+                        template: 0,
+                        position: 2
+                    })
+                ),
+                (
+                    4,
+                    2,
+                    Some(CellSource {
+                        template: 0,
+                        position: 3
+                    })
+                ),
+                // Tie keeps the template's own window
+                (6, 2, None),
+            ]
+        );
+        assert_eq!(merged.template_oligo(&cells[1], 4), "CCCC");
+        assert_eq!(merged.template_oligo(&cells[0], 4), "AAAA");
+
+        let insertions: Vec<(u32, usize, usize, Option<CellSource>)> = merged
+            .insertion_windows
+            .iter()
+            .map(|w| (w.oligo_length, w.next_template_base, w.cell.position, w.cell.source))
+            .collect();
+        assert_eq!(
+            insertions,
+            vec![(
+                4,
+                2,
+                2,
+                Some(CellSource {
+                    template: 0,
+                    position: 1
+                })
+            )]
+        );
+    }
+}
@@ -0,0 +1,568 @@
+//! Multiplex panel design across several targets
+//!
+//! Each target has its own template and reference set and is screened on its
+//! own. From the ranked sites of a target, assay options are formed: a
+//! forward site and a reverse site spanning an amplicon within the size
+//! range, optionally with a probe site between them. The panel builder then
+//! picks one option per target, best-first with backtracking, so that
+//! - amplicon sizes differ by a minimum spacing (size discrimination), or
+//!   each target gets its own probe dye (color discrimination), and
+//! - no two oligos of the panel (including each oligo with itself) form a
+//!   dimer: a complementary run touching a 3' end, or a long run anywhere.
+//!
+//! The chosen panel, with the target inputs and settings, is saved as JSON.
+
+use serde::{Deserialize, Serialize};
+
+use super::candidates::Candidate;
+use super::iupac::iupac_to_mask;
+use super::ranking::{rank_sites, RankingWeights};
+use super::types::ScreeningResults;
+
+/// Current panel file format version
+pub const PANEL_FORMAT_VERSION: u32 = 1;
+
+/// Assay options kept per target (best-scoring first)
+const OPTIONS_PER_TARGET: usize = 25;
+
+/// Search steps before the panel builder gives up
+const MAX_SEARCH_STEPS: usize = 200_000;
+
+/// How the assays of a panel are told apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PanelDiscrimination {
+    /// Amplicon sizes must differ by `min_size_difference`
+    #[default]
+    AmpliconSize,
+    /// Each assay has a probe with its own dye
+    ProbeColor,
+}
+
+/// Panel builder settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelParams {
+    pub min_amplicon: usize,
+    pub max_amplicon: usize,
+    pub discrimination: PanelDiscrimination,
+    /// Minimum amplicon size difference between any two assays (bp)
+    pub min_size_difference: usize,
+    /// Add a probe between the primers (always on for color discrimination)
+    pub include_probe: bool,
+    /// Dye channels, assigned to the assays in target order
+    pub dyes: Vec<String>,
+    /// Complementary run touching a 3' end that counts as a dimer
+    pub three_prime_dimer_run: usize,
+    /// Complementary run anywhere that counts as a dimer
+    pub dimer_run: usize,
+    /// Ranked sites per target that primers and probes are picked from
+    pub sites_per_target: usize,
+}
+
+impl Default for PanelParams {
+    fn default() -> Self {
+        Self {
+            min_amplicon: 80,
+            max_amplicon: 300,
+            discrimination: PanelDiscrimination::AmpliconSize,
+            min_size_difference: 20,
+            include_probe: false,
+            dyes: ["FAM", "HEX", "ROX", "Cy5"]
+                .iter()
+                .map(|d| d.to_string())
+                .collect(),
+            three_prime_dimer_run: 4,
+            dimer_run: 8,
+            sites_per_target: 60,
+        }
+    }
+}
+
+impl PanelParams {
+    fn needs_probe(&self) -> bool {
+        self.include_probe || self.discrimination == PanelDiscrimination::ProbeColor
+    }
+}
+
+/// Inputs of one panel target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PanelTarget {
+    pub name: String,
+    #[serde(default)]
+    pub template_fasta: String,
+    #[serde(default)]
+    pub reference_fasta: String,
+}
+
+/// Primers (and probe) chosen for one target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PanelAssay {
+    pub target: String,
+    pub forward: Candidate,
+    /// Reverse primer; its variants are in template orientation and it is
+    /// exported reverse complemented
+    pub reverse: Candidate,
+    #[serde(default)]
+    pub probe: Option<Candidate>,
+    pub amplicon_size: usize,
+    #[serde(default)]
+    pub dye: Option<String>,
+    /// Mean ranking score of the sites
+    pub score: f64,
+}
+
+impl PanelAssay {
+    pub fn candidates(&self) -> impl Iterator<Item = &Candidate> {
+        [&self.forward, &self.reverse]
+            .into_iter()
+            .chain(self.probe.as_ref())
+    }
+
+    /// Named oligo sequences in order orientation
+    pub fn oligos(&self) -> Vec<(String, String)> {
+        self.candidates().flat_map(|c| c.oligos()).collect()
+    }
+}
+
+/// A pair of panel oligos that can form a dimer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PanelDimer {
+    pub first: String,
+    pub second: String,
+    /// Longest complementary run
+    pub run: usize,
+    /// Longest complementary run touching a 3' end
+    pub three_prime_run: usize,
+}
+
+/// A saved multiplex panel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiplexPanel {
+    pub version: u32,
+    #[serde(default)]
+    pub targets: Vec<PanelTarget>,
+    #[serde(default)]
+    pub params: PanelParams,
+    #[serde(default)]
+    pub assays: Vec<PanelAssay>,
+    /// Oligo pairs with complementary runs, worst first
+    #[serde(default)]
+    pub dimers: Vec<PanelDimer>,
+}
+
+impl MultiplexPanel {
+    pub fn new(
+        targets: Vec<PanelTarget>,
+        params: PanelParams,
+        assays: Vec<PanelAssay>,
+        dimers: Vec<PanelDimer>,
+    ) -> Self {
+        Self {
+            version: PANEL_FORMAT_VERSION,
+            targets,
+            params,
+            assays,
+            dimers,
+        }
+    }
+
+    /// All panel oligos as candidates, e.g. for the order sheet
+    pub fn candidates(&self) -> Vec<Candidate> {
+        self.assays
+            .iter()
+            .flat_map(|a| a.candidates().cloned())
+            .collect()
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize: {}", e))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let panel: Self =
+            serde_json::from_str(json).map_err(|e| format!("Failed to parse panel: {}", e))?;
+        if panel.version == 0 {
+            return Err("Invalid panel file: version must be at least 1".to_string());
+        }
+        Ok(panel)
+    }
+}
+
+/// Bitmask of the complementary bases (A<->T, C<->G)
+fn complement_mask(mask: u8) -> u8 {
+    ((mask & 0b0001) << 3)
+        | ((mask & 0b0010) << 1)
+        | ((mask & 0b0100) >> 1)
+        | ((mask & 0b1000) >> 3)
+}
+
+/// Longest complementary run of two oligos (both 5'->3') paired
+/// antiparallel, and the longest run that includes the 3' base of either.
+/// Degenerate bases pair when any of their bases could.
+pub fn complementary_run(a: &str, b: &str) -> (usize, usize) {
+    let a: Vec<u8> = a
+        .bytes()
+        .map(|c| iupac_to_mask(c.to_ascii_uppercase()))
+        .collect();
+    let b: Vec<u8> = b
+        .bytes()
+        .map(|c| complement_mask(iupac_to_mask(c.to_ascii_uppercase())))
+        .collect();
+    if a.is_empty() || b.is_empty() {
+        return (0, 0);
+    }
+
+    let (mut longest, mut three_prime) = (0, 0);
+    // a[i] pairs with b[j] on the diagonal i + j = k
+    for k in 0..a.len() + b.len() - 1 {
+        let i_start = k.saturating_sub(b.len() - 1);
+        let i_end = k.min(a.len() - 1);
+        let mut run = 0;
+        let mut run_has_three_prime = false;
+        for i in i_start..=i_end {
+            let j = k - i;
+            if a[i] & b[j] != 0 {
+                if run == 0 {
+                    // The run starts at b's 3' end (largest j)
+                    run_has_three_prime = j == b.len() - 1;
+                }
+                run += 1;
+                if i == a.len() - 1 {
+                    run_has_three_prime = true;
+                }
+                longest = longest.max(run);
+                if run_has_three_prime {
+                    three_prime = three_prime.max(run);
+                }
+            } else {
+                run = 0;
+            }
+        }
+    }
+    (longest, three_prime)
+}
+
+fn is_dimer(run: usize, three_prime_run: usize, params: &PanelParams) -> bool {
+    run >= params.dimer_run || three_prime_run >= params.three_prime_dimer_run
+}
+
+/// Worst complementary runs over all variant pairs of two candidates
+fn candidate_runs(a: &Candidate, b: &Candidate) -> (usize, usize) {
+    let (a, b) = (a.oligos(), b.oligos());
+    let mut worst = (0, 0);
+    for (_, x) in &a {
+        for (_, y) in &b {
+            let (run, three_prime) = complementary_run(x, y);
+            worst = (worst.0.max(run), worst.1.max(three_prime));
+        }
+    }
+    worst
+}
+
+/// Whether any oligo of `a` forms a dimer with any oligo of `b` (with
+/// itself when `a` and `b` are the same assay)
+fn assays_form_dimer(a: &PanelAssay, b: &PanelAssay, params: &PanelParams) -> bool {
+    let same = std::ptr::eq(a, b);
+    let a_candidates: Vec<&Candidate> = a.candidates().collect();
+    let b_candidates: Vec<&Candidate> = b.candidates().collect();
+    for (i, x) in a_candidates.iter().enumerate() {
+        let others = if same {
+            &b_candidates[i..]
+        } else {
+            &b_candidates[..]
+        };
+        for y in others {
+            let (run, three_prime) = candidate_runs(x, y);
+            if is_dimer(run, three_prime, params) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Candidate for a ranked site of `results`, with the cell's variants needed
+fn site_candidate(
+    results: &ScreeningResults,
+    oligo_length: u32,
+    position: usize,
+    label: String,
+    reverse_complement: bool,
+) -> Option<Candidate> {
+    let lr = results.results_by_length.get(&oligo_length)?;
+    let i = lr
+        .positions
+        .binary_search_by_key(&position, |p| p.position)
+        .ok()?;
+    let p = &lr.positions[i];
+    let variants = p
+        .analysis
+        .variants
+        .iter()
+        .take(p.variants_needed)
+        .map(|v| v.sequence.clone())
+        .collect();
+    let mut candidate = Candidate::new(oligo_length, position, variants, reverse_complement);
+    candidate.label = label;
+    Some(candidate)
+}
+
+/// Assay options for one target, best first. Options whose own oligos form
+/// a dimer are dropped.
+pub fn assay_options(
+    results: &ScreeningResults,
+    target: &str,
+    params: &PanelParams,
+    weights: &RankingWeights,
+) -> Vec<PanelAssay> {
+    let sites = rank_sites(results, weights, params.sites_per_target);
+    let end = |s: &super::ranking::RankedSite| s.position + s.oligo_length as usize;
+
+    let mut options = Vec::new();
+    for fwd in &sites {
+        for rev in &sites {
+            if rev.position < end(fwd) {
+                continue;
+            }
+            let amplicon_size = end(rev) - fwd.position;
+            if amplicon_size < params.min_amplicon || amplicon_size > params.max_amplicon {
+                continue;
+            }
+            let probe = if params.needs_probe() {
+                // Best site strictly between the primers
+                match sites
+                    .iter()
+                    .find(|p| p.position >= end(fwd) && end(p) <= rev.position)
+                {
+                    Some(p) => Some(p),
+                    None => continue,
+                }
+            } else {
+                None
+            };
+
+            let score_sum = fwd.score + rev.score + probe.map_or(0.0, |p| p.score);
+            let count = if probe.is_some() { 3.0 } else { 2.0 };
+            let (Some(forward), Some(reverse)) = (
+                site_candidate(
+                    results,
+                    fwd.oligo_length,
+                    fwd.position,
+                    format!("{}_Fwd", target),
+                    false,
+                ),
+                site_candidate(
+                    results,
+                    rev.oligo_length,
+                    rev.position,
+                    format!("{}_Rev", target),
+                    true,
+                ),
+            ) else {
+                continue;
+            };
+            let probe = match probe {
+                Some(p) => match site_candidate(
+                    results,
+                    p.oligo_length,
+                    p.position,
+                    format!("{}_Probe", target),
+                    false,
+                ) {
+                    Some(c) => Some(c),
+                    None => continue,
+                },
+                None => None,
+            };
+            options.push(PanelAssay {
+                target: target.to_string(),
+                forward,
+                reverse,
+                probe,
+                amplicon_size,
+                dye: None,
+                score: score_sum / count,
+            });
+        }
+    }
+
+    options.sort_by(|a, b| b.score.total_cmp(&a.score));
+    options.retain(|o| !assays_form_dimer(o, o, params));
+    options.truncate(OPTIONS_PER_TARGET);
+    options
+}
+
+/// Pick one assay option per target (in `options` order). Returns the first
+/// compatible combination in best-first order.
+pub fn design_panel(
+    options: &[Vec<PanelAssay>],
+    params: &PanelParams,
+) -> Result<Vec<PanelAssay>, String> {
+    if let Some(i) = options.iter().position(|o| o.is_empty()) {
+        return Err(format!(
+            "Target {} has no assay option; widen the amplicon range or rank more sites",
+            i + 1
+        ));
+    }
+    if params.discrimination == PanelDiscrimination::ProbeColor && options.len() > params.dyes.len()
+    {
+        return Err(format!(
+            "{} targets but only {} dyes",
+            options.len(),
+            params.dyes.len()
+        ));
+    }
+
+    fn compatible(a: &PanelAssay, b: &PanelAssay, params: &PanelParams) -> bool {
+        if params.discrimination == PanelDiscrimination::AmpliconSize
+            && a.amplicon_size.abs_diff(b.amplicon_size) < params.min_size_difference
+        {
+            return false;
+        }
+        !assays_form_dimer(a, b, params)
+    }
+
+    fn search<'a>(
+        options: &'a [Vec<PanelAssay>],
+        params: &PanelParams,
+        chosen: &mut Vec<&'a PanelAssay>,
+        steps: &mut usize,
+    ) -> bool {
+        let Some(target_options) = options.get(chosen.len()) else {
+            return true;
+        };
+        for option in target_options {
+            *steps += 1;
+            if *steps > MAX_SEARCH_STEPS {
+                return false;
+            }
+            if chosen.iter().all(|c| compatible(c, option, params)) {
+                chosen.push(option);
+                if search(options, params, chosen, steps) {
+                    return true;
+                }
+                chosen.pop();
+            }
+        }
+        false
+    }
+
+    let mut chosen = Vec::new();
+    let mut steps = 0;
+    if !search(options, params, &mut chosen, &mut steps) {
+        return Err(if steps > MAX_SEARCH_STEPS {
+            "No compatible panel found within the search limit".to_string()
+        } else {
+            "No compatible panel: every combination has overlapping sizes or dimers".to_string()
+        });
+    }
+
+    let color = params.discrimination == PanelDiscrimination::ProbeColor;
+    Ok(chosen
+        .into_iter()
+        .enumerate()
+        .map(|(i, assay)| PanelAssay {
+            dye: color.then(|| params.dyes[i].clone()),
+            ..assay.clone()
+        })
+        .collect())
+}
+
+/// Complementary runs between all oligos of a panel (each with itself too),
+/// worst first. Pairs without any run of 3 or more are left out.
+pub fn panel_dimers(assays: &[PanelAssay]) -> Vec<PanelDimer> {
+    let oligos: Vec<(String, String)> = assays.iter().flat_map(|a| a.oligos()).collect();
+    let mut dimers = Vec::new();
+    for (i, (first, x)) in oligos.iter().enumerate() {
+        for (second, y) in &oligos[i..] {
+            let (run, three_prime_run) = complementary_run(x, y);
+            if run >= 3 {
+                dimers.push(PanelDimer {
+                    first: first.clone(),
+                    second: second.clone(),
+                    run,
+                    three_prime_run,
+                });
+            }
+        }
+    }
+    dimers.sort_by_key(|d| std::cmp::Reverse((d.three_prime_run, d.run)));
+    dimers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::iupac::reverse_complement;
+
+    #[test]
+    fn test_complementary_run() {
+        let oligo = "ACGTTGCAAG";
+        assert_eq!(
+            complementary_run(oligo, &reverse_complement(oligo)),
+            (10, 10)
+        );
+        // GGGGG pairs with CCCCC in the middle, away from both 3' ends
+        assert_eq!(complementary_run("AAGGGGGAA", "AACCCCCAA"), (5, 0));
+        // 3' ends overlap by 4 bases (ACGT is its own reverse complement)
+        let (run, three_prime) = complementary_run("AAAAAAACGT", "CCCCCCACGT");
+        assert_eq!(three_prime, 4);
+        assert!(run >= 4);
+        // N could pair with anything
+        assert_eq!(complementary_run("NNN", "AAA"), (3, 3));
+    }
+
+    fn assay(target: &str, size: usize, fwd: &str, rev: &str) -> PanelAssay {
+        PanelAssay {
+            target: target.to_string(),
+            forward: Candidate::new(fwd.len() as u32, 0, vec![fwd.to_string()], false),
+            reverse: Candidate::new(rev.len() as u32, 100, vec![rev.to_string()], true),
+            probe: None,
+            amplicon_size: size,
+            dye: None,
+            score: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_design_panel_backtracks_and_roundtrips() {
+        let params = PanelParams::default();
+        let options = vec![
+            // Exported oligos only use A and C, which never pair, except
+            // the forward primer of B's second option
+            vec![assay("A", 100, "AAAACAAAAC", "GTGGTTGTGG")],
+            vec![
+                // Too close in size to A's assay
+                assay("B", 110, "CACACACACA", "GTTGGTTGGT"),
+                // Forward primer 3' end TTTT pairs with A's forward primer
+                assay("B", 150, "CCCCCGTTTT", "GTTGGTTGGT"),
+                assay("B", 160, "CACACACACA", "TGTGTGGTTG"),
+            ],
+        ];
+        let panel = design_panel(&options, &params).unwrap();
+        assert_eq!(panel.len(), 2);
+        assert_eq!(panel[1].amplicon_size, 160);
+
+        let color = PanelParams {
+            discrimination: PanelDiscrimination::ProbeColor,
+            dyes: vec!["FAM".to_string()],
+            ..Default::default()
+        };
+        assert!(design_panel(&options, &color).is_err());
+
+        let saved = MultiplexPanel::new(
+            vec![PanelTarget {
+                name: "A".to_string(),
+                template_fasta: ">A\nACGT\n".to_string(),
+                reference_fasta: String::new(),
+            }],
+            params.clone(),
+            panel.clone(),
+            panel_dimers(&panel),
+        );
+        let loaded = MultiplexPanel::from_json(&saved.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.assays, panel);
+        assert_eq!(loaded.params, params);
+        assert_eq!(loaded.candidates().len(), 4);
+    }
+}
@@ -0,0 +1,390 @@
+//! Oligo check: evaluate existing (possibly degenerate) oligos against the
+//! reference set, without a template
+//!
+//! Each oligo and its reverse complement are aligned semi-globally against
+//! every reference (`align_degenerate_with_aligner`). A reference counts as
+//! detected when either strand aligns without gaps and within the allowed
+//! mismatches; the strand with fewer mismatches is kept. Mismatch positions
+//! are reported in oligo orientation, so the last `three_prime_window` bases
+//! are the oligo's 3' end whichever strand it bound.
+
+use rayon::prelude::*;
+use std::sync::mpsc::Sender;
+
+use super::export::{Table, TableValue};
+use super::fasta::ReferenceData;
+use super::iupac::{iupac_to_mask, reverse_complement};
+use super::pairwise::{align_degenerate_with_aligner, create_iupac_aligner, IupacAligner};
+use super::types::{PairwiseParams, ThreadCount};
+
+/// A named oligo to check
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckOligo {
+    pub name: String,
+    pub sequence: String,
+}
+
+/// Settings for an oligo check run
+#[derive(Debug, Clone)]
+pub struct OligoCheckParams {
+    pub pairwise: PairwiseParams,
+    /// Number of bases at the 3' end counted as "3' mismatches"
+    pub three_prime_window: usize,
+    pub thread_count: ThreadCount,
+}
+
+impl Default for OligoCheckParams {
+    fn default() -> Self {
+        Self {
+            pairwise: PairwiseParams::default(),
+            three_prime_window: 5,
+            thread_count: ThreadCount::Auto,
+        }
+    }
+}
+
+/// Best accepted alignment of an oligo to one reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct OligoReferenceHit {
+    /// The oligo bound the reverse strand of the reference
+    pub reverse_strand: bool,
+    pub mismatches: usize,
+    /// Mismatches within the 3' window
+    pub three_prime_mismatches: usize,
+    /// Matched reference bases, in oligo orientation
+    pub matched_sequence: String,
+}
+
+/// Check result for one oligo
+#[derive(Debug, Clone, PartialEq)]
+pub struct OligoCheckResult {
+    pub name: String,
+    pub sequence: String,
+    /// One entry per reference, `None` where the oligo was not accepted
+    pub hits: Vec<Option<OligoReferenceHit>>,
+}
+
+impl OligoCheckResult {
+    pub fn total_references(&self) -> usize {
+        self.hits.len()
+    }
+
+    pub fn detected(&self) -> usize {
+        self.hits.iter().flatten().count()
+    }
+
+    /// Percent of references detected within the allowed mismatches
+    pub fn coverage_percent(&self) -> f64 {
+        percent(self.detected(), self.total_references())
+    }
+
+    /// Percent of references matched without any mismatch
+    pub fn perfect_percent(&self) -> f64 {
+        let perfect = self.hits.iter().flatten().filter(|h| h.mismatches == 0).count();
+        percent(perfect, self.total_references())
+    }
+
+    /// Detected references by mismatch count (index = mismatches)
+    pub fn mismatch_distribution(&self) -> Vec<usize> {
+        let mut counts = Vec::new();
+        for hit in self.hits.iter().flatten() {
+            if counts.len() <= hit.mismatches {
+                counts.resize(hit.mismatches + 1, 0);
+            }
+            counts[hit.mismatches] += 1;
+        }
+        counts
+    }
+
+    /// Detected references with at least one mismatch in the 3' window
+    pub fn three_prime_mismatch_count(&self) -> usize {
+        self.hits
+            .iter()
+            .flatten()
+            .filter(|h| h.three_prime_mismatches > 0)
+            .count()
+    }
+
+    /// Indices of the references the oligo missed
+    pub fn missed(&self) -> Vec<usize> {
+        self.hits
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| h.is_none())
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+    if total > 0 {
+        count as f64 / total as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Parse an oligo list. Accepts FASTA, or one oligo per line as
+/// `name<TAB, comma or spaces>sequence` or a bare sequence (named
+/// `Oligo1`, `Oligo2`, ...). Lines starting with `#` are ignored.
+pub fn parse_oligo_list(text: &str) -> Result<Vec<CheckOligo>, String> {
+    let mut entries: Vec<(String, String)> = Vec::new();
+    if text.trim_start().starts_with('>') {
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if let Some(name) = line.strip_prefix('>') {
+                entries.push((name.trim().to_string(), String::new()));
+            } else if let Some((_, seq)) = entries.last_mut() {
+                seq.push_str(line);
+            }
+        }
+    } else {
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line
+                .split(|c: char| c == '\t' || c == ',' || c.is_whitespace())
+                .filter(|f| !f.is_empty())
+                .collect();
+            let (name, seq) = match fields.as_slice() {
+                [seq] => (format!("Oligo{}", entries.len() + 1), seq.to_string()),
+                [name, .., seq] => (name.to_string(), seq.to_string()),
+                [] => continue,
+            };
+            entries.push((name, seq));
+        }
+    }
+
+    let mut oligos = Vec::new();
+    for (name, seq) in entries {
+        let sequence: String = seq
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match c.to_ascii_uppercase() {
+                'U' => 'T',
+                c => c,
+            })
+            .collect();
+        if sequence.is_empty() {
+            return Err(format!("Oligo '{}' has no sequence", name));
+        }
+        if let Some(c) = sequence
+            .chars()
+            .find(|&c| !c.is_ascii() || iupac_to_mask(c as u8) == 0)
+        {
+            return Err(format!("Invalid character '{}' in oligo '{}'", c, name));
+        }
+        oligos.push(CheckOligo { name, sequence });
+    }
+
+    if oligos.is_empty() {
+        return Err("No oligos found in input".to_string());
+    }
+    Ok(oligos)
+}
+
+/// Check every oligo against every reference on both strands. Sends
+/// (oligos done, total oligos) after each oligo.
+pub fn check_oligos(
+    oligos: &[CheckOligo],
+    references: &ReferenceData,
+    params: &OligoCheckParams,
+    progress_tx: Option<Sender<(usize, usize)>>,
+) -> Vec<OligoCheckResult> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(params.thread_count.get_count())
+        .build()
+        .unwrap_or_else(|_| rayon::ThreadPoolBuilder::new().build().unwrap());
+    let ref_bytes: Vec<&[u8]> = references.sequences.iter().map(|s| s.as_bytes()).collect();
+    let max_ref_len = ref_bytes.iter().map(|r| r.len()).max().unwrap_or(0);
+
+    let mut results = Vec::with_capacity(oligos.len());
+    for (done, oligo) in oligos.iter().enumerate() {
+        let hits = pool.install(|| {
+            ref_bytes
+                .par_iter()
+                .map_init(
+                    || create_iupac_aligner(oligo.sequence.len(), max_ref_len, &params.pairwise),
+                    |aligner, reference| check_reference(aligner, oligo, reference, params),
+                )
+                .collect()
+        });
+        results.push(OligoCheckResult {
+            name: oligo.name.clone(),
+            sequence: oligo.sequence.clone(),
+            hits,
+        });
+        if let Some(tx) = &progress_tx {
+            let _ = tx.send((done + 1, oligos.len()));
+        }
+    }
+    results
+}
+
+fn check_reference(
+    aligner: &mut IupacAligner,
+    oligo: &CheckOligo,
+    reference: &[u8],
+    params: &OligoCheckParams,
+) -> Option<OligoReferenceHit> {
+    let length = oligo.sequence.len();
+    let three_prime_start = length.saturating_sub(params.three_prime_window);
+    let reverse = reverse_complement(&oligo.sequence);
+
+    [(false, oligo.sequence.as_str()), (true, reverse.as_str())]
+        .into_iter()
+        .filter_map(|(reverse_strand, query)| {
+            let (result, positions) =
+                align_degenerate_with_aligner(aligner, query.as_bytes(), reference);
+            if result.has_gaps
+                || !result.full_coverage
+                || result.mismatches > params.pairwise.max_mismatches as usize
+            {
+                return None;
+            }
+            // Report positions and the matched bases in oligo orientation
+            let (three_prime_mismatches, matched_sequence) = if reverse_strand {
+                (
+                    positions.iter().filter(|&&i| length - 1 - i >= three_prime_start).count(),
+                    reverse_complement(&result.matched_sequence),
+                )
+            } else {
+                (
+                    positions.iter().filter(|&&i| i >= three_prime_start).count(),
+                    result.matched_sequence,
+                )
+            };
+            Some(OligoReferenceHit {
+                reverse_strand,
+                mismatches: result.mismatches,
+                three_prime_mismatches,
+                matched_sequence,
+            })
+        })
+        .min_by_key(|hit| hit.mismatches)
+}
+
+/// Summary table with one row per oligo. Missed references are listed by
+/// name, separated by semicolons.
+pub fn oligo_check_table(results: &[OligoCheckResult], reference_names: &[String]) -> Table {
+    let max_mismatches = results
+        .iter()
+        .map(|r| r.mismatch_distribution().len())
+        .max()
+        .unwrap_or(0);
+    let mut headers: Vec<String> = [
+        "name",
+        "sequence",
+        "references",
+        "detected",
+        "coverage_percent",
+        "perfect_percent",
+        "three_prime_mismatch_refs",
+    ]
+    .iter()
+    .map(|h| h.to_string())
+    .collect();
+    headers.extend((0..max_mismatches).map(|m| format!("mm{}", m)));
+    headers.push("missed".to_string());
+
+    let rows = results
+        .iter()
+        .map(|r| {
+            let mut row = vec![
+                TableValue::Text(r.name.clone()),
+                TableValue::Text(r.sequence.clone()),
+                TableValue::Int(r.total_references() as i64),
+                TableValue::Int(r.detected() as i64),
+                TableValue::Float(r.coverage_percent()),
+                TableValue::Float(r.perfect_percent()),
+                TableValue::Int(r.three_prime_mismatch_count() as i64),
+            ];
+            let distribution = r.mismatch_distribution();
+            row.extend(
+                (0..max_mismatches)
+                    .map(|m| TableValue::Int(distribution.get(m).copied().unwrap_or(0) as i64)),
+            );
+            let missed: Vec<String> = r
+                .missed()
+                .iter()
+                .map(|&i| {
+                    reference_names
+                        .get(i)
+                        .cloned()
+                        .unwrap_or_else(|| format!("#{}", i + 1))
+                })
+                .collect();
+            row.push(TableValue::Text(missed.join("; ")));
+            row
+        })
+        .collect();
+
+    Table {
+        name: "Oligo check".to_string(),
+        headers,
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_oligo_list() {
+        let oligos = parse_oligo_list("# assays\nFwd1\tACGTRY\nacgu\nRev1, GGN\n").unwrap();
+        assert_eq!(oligos.len(), 3);
+        assert_eq!(oligos[0].name, "Fwd1");
+        assert_eq!(oligos[1].name, "Oligo2");
+        assert_eq!(oligos[1].sequence, "ACGT");
+        assert_eq!(oligos[2].sequence, "GGN");
+
+        let fasta = parse_oligo_list(">P1 probe\nACGT\nTT\n>P2\nGG\n").unwrap();
+        assert_eq!(fasta[0].name, "P1 probe");
+        assert_eq!(fasta[0].sequence, "ACGTTT");
+        assert!(parse_oligo_list("Bad ACGTX").is_err());
+    }
+
+    #[test]
+    fn test_check_both_strands_and_three_prime() {
+        let references = ReferenceData {
+            names: vec!["fwd".to_string(), "rev".to_string(), "3p".to_string(), "none".to_string()],
+            sequences: vec![
+                "GGGTATGGTACGTCATGGG".to_string(),
+                // Reverse complement of the above
+                "CCCATGACGTACCATACCC".to_string(),
+                // Last oligo base (T) changed to A
+                "GGGTATGGTACGACATGGG".to_string(),
+                "CCCCCCCCCCCCCCCCCCC".to_string(),
+            ],
+        };
+        let oligos = vec![CheckOligo {
+            name: "Fwd".to_string(),
+            sequence: "TATGGYACGT".to_string(),
+        }];
+        let params = OligoCheckParams {
+            thread_count: ThreadCount::Fixed(1),
+            ..Default::default()
+        };
+        let result = &check_oligos(&oligos, &references, &params, None)[0];
+
+        let fwd = result.hits[0].as_ref().unwrap();
+        assert!(!fwd.reverse_strand);
+        assert_eq!(fwd.mismatches, 0);
+        let rev = result.hits[1].as_ref().unwrap();
+        assert!(rev.reverse_strand);
+        assert_eq!(rev.mismatches, 0);
+        assert_eq!(rev.matched_sequence, "TATGGTACGT");
+        let three_prime = result.hits[2].as_ref().unwrap();
+        assert_eq!(three_prime.mismatches, 1);
+        assert_eq!(three_prime.three_prime_mismatches, 1);
+
+        assert_eq!(result.missed(), vec![3]);
+        assert_eq!(result.mismatch_distribution(), vec![2, 1]);
+        assert_eq!(result.coverage_percent(), 75.0);
+        assert_eq!(result.three_prime_mismatch_count(), 1);
+
+        let table = oligo_check_table(std::slice::from_ref(result), &references.names);
+        assert_eq!(table.rows[0].last(), Some(&TableValue::Text("none".to_string())));
+    }
+}
@@ -0,0 +1,114 @@
+//! Whole-template overview of the heatmap for the navigation minimap
+//!
+//! The heatmap columns are split into a fixed number of bins and each
+//! (length, bin) pair is reduced to the mean variants needed and mean
+//! no-match fraction of its analyzed cells, so a template of any size fits
+//! in a strip a few hundred pixels wide.
+
+use super::types::ScreeningResults;
+
+/// Summary of the analyzed cells in one bin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverviewCell {
+    /// Mean variants needed, rounded
+    pub variants_needed: usize,
+    pub no_match_fraction: f64,
+}
+
+/// Bin the cells of each length (rows, in `lengths` order) over `positions`
+/// (the heatmap columns) into at most `bins` columns. A bin is `None` when
+/// none of its cells were analyzed.
+pub fn heatmap_overview(
+    results: &ScreeningResults,
+    lengths: &[u32],
+    positions: &[usize],
+    bins: usize,
+) -> Vec<Vec<Option<OverviewCell>>> {
+    let bins = bins.min(positions.len()).max(1);
+    lengths
+        .iter()
+        .map(|length| {
+            let cells = results
+                .results_by_length
+                .get(length)
+                .map(|lr| lr.positions.as_slice())
+                .unwrap_or(&[]);
+            (0..bins)
+                .map(|bin| {
+                    let cols = &positions
+                        [bin * positions.len() / bins..(bin + 1) * positions.len() / bins];
+                    let (mut variants, mut no_match, mut count) = (0usize, 0.0f64, 0usize);
+                    for pos in cols {
+                        let Ok(i) = cells.binary_search_by_key(pos, |p| p.position) else {
+                            continue;
+                        };
+                        let a = &cells[i].analysis;
+                        if a.skipped {
+                            continue;
+                        }
+                        variants += cells[i].variants_needed;
+                        if a.total_sequences > 0 {
+                            no_match += a.no_match_count as f64 / a.total_sequences as f64;
+                        }
+                        count += 1;
+                    }
+                    (count > 0).then(|| OverviewCell {
+                        variants_needed: (variants as f64 / count as f64).round() as usize,
+                        no_match_fraction: no_match / count as f64,
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::{
+        AnalysisParams, LengthResult, PositionResult, WindowAnalysisResult,
+    };
+
+    #[test]
+    fn test_overview_bins() {
+        let mut results =
+            ScreeningResults::new(AnalysisParams::default(), 10, 4, "A".repeat(10));
+        let positions: Vec<PositionResult> = (0..6)
+            .map(|position| PositionResult {
+                position,
+                variants_needed: position + 1,
+                source: None,
+                analysis: WindowAnalysisResult {
+                    total_sequences: 4,
+                    no_match_count: if position < 3 { 2 } else { 0 },
+                    skipped: position == 5,
+                    ..Default::default()
+                },
+            })
+            .collect();
+        results.results_by_length.insert(
+            5,
+            LengthResult {
+                oligo_length: 5,
+                positions,
+                column_profile: Vec::new(),
+            },
+        );
+
+        let columns: Vec<usize> = (0..6).collect();
+        let overview = heatmap_overview(&results, &[5, 6], &columns, 3);
+        assert_eq!(overview.len(), 2);
+        assert_eq!(
+            overview[0][0],
+            Some(OverviewCell {
+                variants_needed: 2,
+                no_match_fraction: 0.5
+            })
+        );
+        // Bin of positions 4 and 5: only 4 was analyzed
+        assert_eq!(overview[0][2].unwrap().variants_needed, 5);
+        assert_eq!(overview[1], vec![None, None, None]);
+        // More bins than columns
+        assert_eq!(heatmap_overview(&results, &[5], &columns, 100)[0].len(), 6);
+    }
+}
@@ -1,11 +1,14 @@
 //! Pairwise alignment logic for matching oligos against reference sequences
 //!
 //! Uses Smith-Waterman local alignment from the bio crate to find the best
-//! match for each template oligo in each reference sequence.
+//! match for each template oligo in each reference sequence. Degenerate
+//! (IUPAC) oligos use a separate aligner whose match function accepts any
+//! base an ambiguity code stands for.
 
 use bio::alignment::pairwise::{Aligner, MatchFunc, MatchParams};
-use bio::alignment::AlignmentOperation;
+use bio::alignment::{Alignment, AlignmentOperation};
 
+use super::iupac::{base_to_bit, iupac_to_mask, sequence_matches_consensus_bytes};
 use super::types::PairwiseParams;
 
 /// Concrete Aligner type using MatchParams (nameable, unlike closure-based Aligners).
@@ -27,6 +30,48 @@ pub fn create_aligner(
     )
 }
 
+/// Match function for degenerate oligos: an oligo code matches a reference
+/// base when their base sets overlap (same rule as
+/// `sequence_matches_consensus_bytes`).
+#[derive(Debug, Clone, Copy)]
+pub struct IupacMatchParams {
+    match_score: i32,
+    mismatch_score: i32,
+}
+
+impl MatchFunc for IupacMatchParams {
+    #[inline]
+    fn score(&self, a: u8, b: u8) -> i32 {
+        if iupac_to_mask(a) & base_to_bit(b) != 0 {
+            self.match_score
+        } else {
+            self.mismatch_score
+        }
+    }
+}
+
+/// Aligner for degenerate oligos
+pub type IupacAligner = Aligner<IupacMatchParams>;
+
+/// Create an aligner for degenerate oligos sized for the given dimensions.
+pub fn create_iupac_aligner(
+    oligo_len: usize,
+    max_ref_len: usize,
+    params: &PairwiseParams,
+) -> IupacAligner {
+    let match_fn = IupacMatchParams {
+        match_score: params.match_score,
+        mismatch_score: params.mismatch_score,
+    };
+    Aligner::with_capacity(
+        oligo_len,
+        max_ref_len,
+        params.gap_open_penalty,
+        params.gap_extend_penalty,
+        match_fn,
+    )
+}
+
 /// Result of aligning an oligo against a single reference sequence
 #[derive(Debug, Clone)]
 pub struct PairwiseMatch {
@@ -50,7 +95,10 @@ fn process_alignment<F: MatchFunc>(
     reference: &[u8],
 ) -> PairwiseMatch {
     let alignment = aligner.local(oligo, reference);
+    summarize_alignment(&alignment, oligo, reference)
+}
 
+fn summarize_alignment(alignment: &Alignment, oligo: &[u8], reference: &[u8]) -> PairwiseMatch {
     let mut has_gaps = false;
     let mut mismatches = 0;
 
@@ -85,6 +133,38 @@ fn process_alignment<F: MatchFunc>(
     }
 }
 
+/// Align a degenerate oligo against one reference.
+///
+/// Unlike the template screen this uses semi-global alignment (the whole
+/// oligo, any part of the reference), so mismatches at the oligo ends are
+/// reported instead of being clipped off. The aligner reports
+/// IUPAC-compatible positions as substitutions, so for gap-free alignments
+/// the mismatches are recounted from the matched sequence. Also returns the
+/// 0-based oligo positions that mismatch (empty if the alignment has gaps).
+pub fn align_degenerate_with_aligner(
+    aligner: &mut IupacAligner,
+    oligo: &[u8],
+    reference: &[u8],
+) -> (PairwiseMatch, Vec<usize>) {
+    let alignment = aligner.semiglobal(oligo, reference);
+    let mut result = summarize_alignment(&alignment, oligo, reference);
+    if result.has_gaps || !result.full_coverage {
+        return (result, Vec::new());
+    }
+
+    let positions: Vec<usize> = oligo
+        .iter()
+        .zip(result.matched_sequence.as_bytes())
+        .enumerate()
+        .filter(|(_, (o, r))| {
+            !sequence_matches_consensus_bytes(std::slice::from_ref(r), std::slice::from_ref(o))
+        })
+        .map(|(i, _)| i)
+        .collect();
+    result.mismatches = positions.len();
+    (result, positions)
+}
+
 /// Align an oligo against a single reference sequence using local alignment.
 /// Creates its own aligner — use `collect_matches` for batch alignment.
 pub fn align_oligo_to_reference(
@@ -185,6 +265,33 @@ pub fn collect_matches_with_aligner(
     (matched, no_match_count)
 }
 
+/// Align an oligo against all references using a pre-existing aligner,
+/// keeping track of which reference produced which match.
+///
+/// Returns one entry per reference, in order: `Some(match)` if the alignment
+/// passes the same acceptance rules as `collect_matches_with_aligner`.
+pub fn align_references_with_aligner(
+    aligner: &mut DnaAligner,
+    oligo: &[u8],
+    references: &[Vec<u8>],
+    params: &PairwiseParams,
+) -> Vec<Option<PairwiseMatch>> {
+    references
+        .iter()
+        .map(|reference| {
+            let result = process_alignment(aligner, oligo, reference);
+            if !result.full_coverage
+                || result.has_gaps
+                || result.mismatches > params.max_mismatches as usize
+            {
+                None
+            } else {
+                Some(result)
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -248,6 +355,27 @@ mod tests {
         assert_eq!(matched.iter().filter(|s| *s == "TATGGTTCGT").count(), 1);
     }
 
+    #[test]
+    fn test_degenerate_oligo_alignment() {
+        let params = default_params();
+        let reference = b"GGTATGGTACGTCATGTTCTAG";
+        let mut aligner = create_iupac_aligner(10, reference.len(), &params);
+
+        // R (A/G) and Y (C/T) cover the reference bases: no mismatches
+        let (result, positions) =
+            align_degenerate_with_aligner(&mut aligner, b"TRTGGTAYGT", reference);
+        assert!(result.full_coverage && !result.has_gaps);
+        assert_eq!(result.mismatches, 0);
+        assert!(positions.is_empty());
+        assert_eq!(result.matched_sequence, "TATGGTACGT");
+
+        // S (C/G) does not cover A at index 1; last base mismatches too
+        let (result, positions) =
+            align_degenerate_with_aligner(&mut aligner, b"TSTGGTACGA", reference);
+        assert_eq!(result.mismatches, 2);
+        assert_eq!(positions, vec![1, 9]);
+    }
+
     #[test]
     fn test_max_mismatches_filter() {
         let oligo = b"TATGGTACGT";
@@ -0,0 +1,246 @@
+//! Project files: inputs, settings and (optionally) results in one document
+//!
+//! A project bundles the raw template and reference FASTA text, the analysis
+//! parameters, results-viewer settings and UI selections so that reopening it
+//! restores the whole application state. Every field except `version` has a
+//! serde default and unknown fields are ignored, so files written by older or
+//! newer versions of the program still load. Candidate oligos picked from the
+//! results are saved with the project as well, and so is the reference import
+//! filter with its report, which is applied again to the reopened references.
+
+use serde::{Deserialize, Serialize};
+
+use super::candidates::Candidate;
+use super::reference_filter::{FilterReport, ReferenceFilter};
+use super::sequence_file::ReadOptions;
+use super::types::{AnalysisParams, ScreeningResults, TemplateOrigin};
+
+/// Current project file format version
+pub const PROJECT_FORMAT_VERSION: u32 = 1;
+
+/// Results viewer settings (adjustable without re-running analysis)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewerSettings {
+    pub coverage_threshold: f64,
+    pub color_green_at: usize,
+    pub color_red_at: usize,
+    pub nomatch_ok_percent: f64,
+    pub nomatch_bad_percent: f64,
+    pub zoom_level: f32,
+    /// Heatmap row height in pixels
+    pub row_height: f32,
+    pub show_reverse_complement: bool,
+    pub show_codon_spacing: bool,
+    pub show_entropy_track: bool,
+    pub show_matched_track: bool,
+    pub show_mismatch_track: bool,
+}
+
+impl Default for ViewerSettings {
+    fn default() -> Self {
+        Self {
+            coverage_threshold: 95.0,
+            color_green_at: 1,
+            color_red_at: 10,
+            nomatch_ok_percent: 5.0,
+            nomatch_bad_percent: 50.0,
+            zoom_level: 1.0,
+            row_height: 54.0,
+            show_reverse_complement: false,
+            show_codon_spacing: true,
+            show_entropy_track: true,
+            show_matched_track: true,
+            show_mismatch_track: true,
+        }
+    }
+}
+
+/// Analysis method radio selection in the setup tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MethodChoice {
+    #[default]
+    NoAmbiguities,
+    FixedAmbiguities,
+    Incremental,
+}
+
+/// Analysis setup selections that are not part of `AnalysisParams`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSelections {
+    pub method: MethodChoice,
+    pub incremental_limit_ambiguities: bool,
+    pub incremental_max_ambiguities: u32,
+    /// `None` = automatic thread count
+    pub manual_thread_count: Option<usize>,
+}
+
+impl Default for UiSelections {
+    fn default() -> Self {
+        Self {
+            method: MethodChoice::NoAmbiguities,
+            incremental_limit_ambiguities: false,
+            incremental_max_ambiguities: 3,
+            manual_thread_count: None,
+        }
+    }
+}
+
+/// A saved project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectFile {
+    pub version: u32,
+    #[serde(default)]
+    pub template_fasta: String,
+    #[serde(default)]
+    pub reference_fasta: String,
+    #[serde(default)]
+    pub params: AnalysisParams,
+    #[serde(default)]
+    pub viewer: ViewerSettings,
+    #[serde(default)]
+    pub ui: UiSelections,
+    #[serde(default)]
+    pub results: Option<ScreeningResults>,
+    #[serde(default)]
+    pub candidates: Vec<Candidate>,
+    /// How the template FASTA was obtained
+    #[serde(default)]
+    pub template_origin: TemplateOrigin,
+    /// Reference file that was streamed instead of pasted; `reference_fasta`
+    /// is empty then and the file is read again on open
+    #[serde(default)]
+    pub reference_file: Option<String>,
+    /// Options the references were read with
+    #[serde(default)]
+    pub read_options: ReadOptions,
+    #[serde(default)]
+    pub reference_filter: ReferenceFilter,
+    /// Report of the applied reference filter (`None` if the references are
+    /// unfiltered)
+    #[serde(default)]
+    pub reference_filter_report: Option<FilterReport>,
+}
+
+impl ProjectFile {
+    pub fn new(
+        template_fasta: String,
+        reference_fasta: String,
+        params: AnalysisParams,
+        viewer: ViewerSettings,
+        ui: UiSelections,
+        results: Option<ScreeningResults>,
+        candidates: Vec<Candidate>,
+    ) -> Self {
+        Self {
+            version: PROJECT_FORMAT_VERSION,
+            template_fasta,
+            reference_fasta,
+            params,
+            viewer,
+            ui,
+            results,
+            candidates,
+            template_origin: TemplateOrigin::Provided,
+            reference_file: None,
+            read_options: ReadOptions::default(),
+            reference_filter: ReferenceFilter::default(),
+            reference_filter_report: None,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize: {}", e))
+    }
+
+    /// Parse a project file. Files from newer program versions are accepted;
+    /// fields this version does not know about are ignored.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let project: Self =
+            serde_json::from_str(json).map_err(|e| format!("Failed to parse project: {}", e))?;
+        if project.version == 0 {
+            return Err("Invalid project file: version must be at least 1".to_string());
+        }
+        Ok(project)
+    }
+
+    /// True if the file was written by a newer program version, meaning some
+    /// of its settings may have been dropped on load.
+    pub fn is_newer_than_supported(&self) -> bool {
+        self.version > PROJECT_FORMAT_VERSION
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::reference_filter::{RemovalReason, RemovedReference};
+    use crate::analysis::types::AnalysisMethod;
+
+    #[test]
+    fn test_project_roundtrip() {
+        let params = AnalysisParams {
+            method: AnalysisMethod::FixedAmbiguities(2),
+            min_oligo_length: 20,
+            ..Default::default()
+        };
+        let viewer = ViewerSettings {
+            color_red_at: 6,
+            zoom_level: 2.5,
+            ..Default::default()
+        };
+        let ui = UiSelections {
+            method: MethodChoice::FixedAmbiguities,
+            manual_thread_count: Some(4),
+            ..Default::default()
+        };
+        let mut project = ProjectFile::new(
+            ">T\nACGT\n".to_string(),
+            ">R1\nACGT\n".to_string(),
+            params,
+            viewer.clone(),
+            ui.clone(),
+            None,
+            vec![Candidate::new(4, 0, vec!["ACGT".to_string()], true)],
+        );
+        let report = FilterReport {
+            total: 2,
+            removed: vec![RemovedReference {
+                index: 1,
+                name: "R2".to_string(),
+                reason: RemovalReason::TooShort { length: 4 },
+            }],
+        };
+        project.reference_filter_report = Some(report.clone());
+
+        let loaded = ProjectFile::from_json(&project.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.version, PROJECT_FORMAT_VERSION);
+        assert_eq!(loaded.template_fasta, ">T\nACGT\n");
+        assert_eq!(loaded.params.method, AnalysisMethod::FixedAmbiguities(2));
+        assert_eq!(loaded.params.min_oligo_length, 20);
+        assert_eq!(loaded.viewer, viewer);
+        assert_eq!(loaded.ui, ui);
+        assert!(loaded.results.is_none());
+        assert_eq!(loaded.candidates.len(), 1);
+        assert!(loaded.candidates[0].reverse_complement);
+        assert_eq!(loaded.reference_filter_report, Some(report));
+    }
+
+    #[test]
+    fn test_project_tolerates_missing_and_unknown_fields() {
+        let json = r#"{
+            "version": 2,
+            "template_fasta": ">T\nACGT",
+            "viewer": { "color_green_at": 3, "some_future_setting": true },
+            "future_section": { "x": 1 }
+        }"#;
+        let project = ProjectFile::from_json(json).unwrap();
+        assert!(project.is_newer_than_supported());
+        assert_eq!(project.viewer.color_green_at, 3);
+        assert_eq!(project.viewer.color_red_at, 10);
+        assert!(project.reference_fasta.is_empty());
+        assert_eq!(project.ui, UiSelections::default());
+        assert!(project.candidates.is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+//! Ranking of the best oligo sites across all lengths and positions
+//!
+//! Every non-skipped window gets a score in 0..=1 from four components, each
+//! also in 0..=1 (higher is better):
+//! - variants: `1 / variants_needed`,
+//! - coverage: coverage at threshold / 100,
+//! - no-match: 1 − no-match fraction,
+//! - thermo: Tm closeness to a target and GC within a range, computed on the
+//!   template oligo.
+//!
+//! The score is the weighted mean of the components. Windows are then picked
+//! best-first, skipping any that overlap an already picked window, so shifted
+//! copies of one site are not listed twice.
+
+use super::thermo::{gc_content, melting_temperature};
+use super::types::ScreeningResults;
+
+/// User-adjustable ranking weights and thermodynamic targets
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankingWeights {
+    pub variants: f64,
+    pub coverage: f64,
+    pub no_match: f64,
+    pub thermo: f64,
+    /// Tm target and the distance at which the Tm score reaches 0 (°C)
+    pub target_tm: f64,
+    pub tm_tolerance: f64,
+    /// Acceptable GC range in percent
+    pub gc_min: f64,
+    pub gc_max: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            variants: 1.0,
+            coverage: 1.0,
+            no_match: 1.0,
+            thermo: 0.5,
+            target_tm: 60.0,
+            tm_tolerance: 10.0,
+            gc_min: 40.0,
+            gc_max: 60.0,
+        }
+    }
+}
+
+/// A scored window
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedSite {
+    pub oligo_length: u32,
+    pub position: usize,
+    pub score: f64,
+    pub variants_needed: usize,
+    pub coverage: f64,
+    pub no_match_fraction: f64,
+    pub tm: f64,
+    pub gc: f64,
+}
+
+/// GC outside the range loses score linearly, reaching 0 at 10 points away
+const GC_FALLOFF: f64 = 10.0;
+
+fn thermo_score(tm: f64, gc: f64, weights: &RankingWeights) -> f64 {
+    let tm_score = if weights.tm_tolerance > 0.0 {
+        (1.0 - (tm - weights.target_tm).abs() / weights.tm_tolerance).max(0.0)
+    } else if tm == weights.target_tm {
+        1.0
+    } else {
+        0.0
+    };
+    let gc_distance = if gc < weights.gc_min {
+        weights.gc_min - gc
+    } else if gc > weights.gc_max {
+        gc - weights.gc_max
+    } else {
+        0.0
+    };
+    let gc_score = (1.0 - gc_distance / GC_FALLOFF).max(0.0);
+    (tm_score + gc_score) / 2.0
+}
+
+/// Score all windows and return the best `top_n` non-overlapping ones
+pub fn rank_sites(
+    results: &ScreeningResults,
+    weights: &RankingWeights,
+    top_n: usize,
+) -> Vec<RankedSite> {
+    let weight_sum = weights.variants + weights.coverage + weights.no_match + weights.thermo;
+
+    let mut sites = Vec::new();
+    for (&oligo_length, length_result) in &results.results_by_length {
+        for p in &length_result.positions {
+            let a = &p.analysis;
+            let oligo = results.template_oligo(p, oligo_length);
+            if a.skipped || p.variants_needed == 0 || oligo.is_empty() {
+                continue;
+            }
+            let tm = melting_temperature(oligo);
+            let gc = gc_content(oligo);
+            let no_match_fraction = if a.total_sequences > 0 {
+                a.no_match_count as f64 / a.total_sequences as f64
+            } else {
+                0.0
+            };
+
+            let weighted = weights.variants / p.variants_needed as f64
+                + weights.coverage * (a.coverage_at_threshold / 100.0).clamp(0.0, 1.0)
+                + weights.no_match * (1.0 - no_match_fraction)
+                + weights.thermo * thermo_score(tm, gc, weights);
+            let score = if weight_sum > 0.0 {
+                weighted / weight_sum
+            } else {
+                0.0
+            };
+
+            sites.push(RankedSite {
+                oligo_length,
+                position: p.position,
+                score,
+                variants_needed: p.variants_needed,
+                coverage: a.coverage_at_threshold,
+                no_match_fraction,
+                tm,
+                gc,
+            });
+        }
+    }
+
+    // Best first; ties go to the shorter oligo, then the earlier position
+    sites.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.oligo_length.cmp(&b.oligo_length))
+            .then(a.position.cmp(&b.position))
+    });
+
+    let mut picked: Vec<RankedSite> = Vec::new();
+    for site in sites {
+        if picked.len() >= top_n {
+            break;
+        }
+        let start = site.position;
+        let end = start + site.oligo_length as usize;
+        let overlaps = picked.iter().any(|s| {
+            let s_end = s.position + s.oligo_length as usize;
+            start < s_end && s.position < end
+        });
+        if !overlaps {
+            picked.push(site);
+        }
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::{
+        AnalysisParams, LengthResult, PositionResult, WindowAnalysisResult,
+    };
+
+    fn results_with(cells: &[(usize, usize)]) -> ScreeningResults {
+        let mut results = ScreeningResults::new(
+            AnalysisParams::default(),
+            40,
+            10,
+            "ACGTACGTACGTACGTACGTACGTACGTACGTACGTACGT".to_string(),
+        );
+        let positions = cells
+            .iter()
+            .map(|&(position, variants_needed)| PositionResult {
+                position,
+                variants_needed,
+                source: None,
+                analysis: WindowAnalysisResult {
+                    total_sequences: 10,
+                    sequences_analyzed: 10,
+                    coverage_at_threshold: 100.0,
+                    variants_for_threshold: variants_needed,
+                    ..Default::default()
+                },
+            })
+            .collect();
+        results.results_by_length.insert(
+            10,
+            LengthResult {
+                oligo_length: 10,
+                positions,
+                column_profile: Vec::new(),
+            },
+        );
+        results
+    }
+
+    #[test]
+    fn test_ranking_prefers_fewer_variants_and_deduplicates() {
+        let results = results_with(&[(0, 3), (1, 1), (2, 1), (5, 2), (20, 2), (25, 4)]);
+        let weights = RankingWeights {
+            thermo: 0.0,
+            ..Default::default()
+        };
+        let ranked = rank_sites(&results, &weights, 10);
+        let picked: Vec<usize> = ranked.iter().map(|s| s.position).collect();
+        // 1 and 2 overlap (1 wins the tie), 0 and 5 overlap 1, 25 overlaps 20
+        assert_eq!(picked, vec![1, 20]);
+        assert!(ranked[0].score > ranked[1].score);
+
+        assert_eq!(rank_sites(&results, &weights, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_thermo_score() {
+        let weights = RankingWeights::default();
+        assert_eq!(thermo_score(60.0, 50.0, &weights), 1.0);
+        assert_eq!(thermo_score(55.0, 30.0, &weights), 0.25);
+        assert_eq!(thermo_score(80.0, 75.0, &weights), 0.0);
+    }
+}
@@ -0,0 +1,515 @@
+//! Quality filtering of imported reference sets
+//!
+//! Downloaded reference sets contain truncated partial sequences, sequences
+//! full of `N`s and mislabeled entries, which show up as no-matches in every
+//! window. References are checked in this order and removed at the first
+//! failed check: minimum length, maximum fraction of ambiguous bases (among
+//! non-gap characters), exact duplicate of an earlier kept reference, and
+//! template coverage. The coverage is the template span of the best local
+//! alignment of the template to the reference, on the better scoring strand,
+//! as a percentage of the template length. Mismatches and gaps inside the
+//! alignment count as covered, so a divergent but complete reference is not
+//! removed. Only the alignment score and span are computed, which needs
+//! memory linear in the template length.
+//!
+//! As for additional templates (`multi_template`), the alignment is anchored
+//! on the longest chain of shared 12-mers (LCSk++): only cells within
+//! `BAND_WIDTH` diagonals of the chain, extended far enough to reach both
+//! template ends, are computed. A reference sharing no 12-mer with the
+//! template strand is aligned in full only up to `MAX_UNANCHORED_CELLS`
+//! cells, and otherwise counts as not covering it.
+//!
+//! The filter settings and report are saved with a project. Reopening it
+//! removes the reported references again by index (`apply_filter_report`)
+//! instead of realigning them.
+
+use bio::alignment::sparse::{find_kmer_matches, lcskpp};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::export::{Table, TableValue};
+use super::fasta::ReferenceData;
+use super::iupac::reverse_complement;
+use super::types::PairwiseParams;
+
+/// K-mer length of the anchors between the template and a reference
+const ANCHOR_LENGTH: usize = 12;
+
+/// Diagonals on either side of the anchor chain that are aligned, which
+/// bounds the indels an alignment can contain away from the anchors
+const BAND_WIDTH: isize = 64;
+
+/// Largest template × reference comparison without any shared k-mer that is
+/// aligned in full, in alignment matrix cells
+const MAX_UNANCHORED_CELLS: usize = 4_000_000;
+
+/// Import filter settings. Each check can be switched off.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReferenceFilter {
+    /// Minimum length in bases, gaps excluded (0 = off)
+    pub min_length: usize,
+    /// Maximum percentage of ambiguous bases (100 = off)
+    pub max_ambiguous_percent: f64,
+    pub remove_duplicates: bool,
+    /// Minimum template coverage in percent (0 = off)
+    pub min_template_coverage: f64,
+}
+
+impl Default for ReferenceFilter {
+    fn default() -> Self {
+        Self {
+            min_length: 0,
+            max_ambiguous_percent: 5.0,
+            remove_duplicates: true,
+            min_template_coverage: 0.0,
+        }
+    }
+}
+
+/// Why a reference was removed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RemovalReason {
+    TooShort { length: usize },
+    TooAmbiguous { percent: f64 },
+    DuplicateOf { name: String },
+    LowTemplateCoverage { percent: f64 },
+}
+
+impl RemovalReason {
+    pub fn description(&self) -> String {
+        match self {
+            Self::TooShort { length } => format!("too short ({} bp)", length),
+            Self::TooAmbiguous { percent } => format!("{:.1}% ambiguous bases", percent),
+            Self::DuplicateOf { name } => format!("duplicate of {}", name),
+            Self::LowTemplateCoverage { percent } => {
+                format!("aligns to {:.1}% of the template", percent)
+            }
+        }
+    }
+}
+
+/// A removed reference
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemovedReference {
+    /// Index in the unfiltered set
+    pub index: usize,
+    pub name: String,
+    pub reason: RemovalReason,
+}
+
+/// Outcome of filtering a reference set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterReport {
+    pub total: usize,
+    pub removed: Vec<RemovedReference>,
+}
+
+impl FilterReport {
+    pub fn kept(&self) -> usize {
+        self.total - self.removed.len()
+    }
+
+    /// Removed references, one row each
+    pub fn table(&self) -> Table {
+        let headers = ["index", "name", "reason"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        let rows = self
+            .removed
+            .iter()
+            .map(|r| {
+                vec![
+                    TableValue::Int(r.index as i64 + 1),
+                    TableValue::Text(r.name.clone()),
+                    TableValue::Text(r.reason.description()),
+                ]
+            })
+            .collect();
+        Table {
+            name: "Removed references".to_string(),
+            headers,
+            rows,
+        }
+    }
+}
+
+fn ungapped_length(sequence: &str) -> usize {
+    sequence.bytes().filter(|&b| b != b'-').count()
+}
+
+fn ambiguous_percent(sequence: &str) -> f64 {
+    let length = ungapped_length(sequence);
+    if length == 0 {
+        return 0.0;
+    }
+    let ambiguous = sequence.bytes().filter(|b| !b"ACGT-".contains(b)).count();
+    ambiguous as f64 / length as f64 * 100.0
+}
+
+/// Higher score, then later template start
+fn better(a: (i32, usize), b: (i32, usize)) -> (i32, usize) {
+    if b.0 > a.0 || (b.0 == a.0 && b.1 > a.1) {
+        b
+    } else {
+        a
+    }
+}
+
+/// Score of the best local alignment of `template` to `reference` and the
+/// number of template bases it spans (affine gaps, scored as in
+/// `create_aligner`). `band` gives, for each reference base, the range of
+/// diagonals (template index minus reference index) to compute; cells
+/// outside it score 0.
+///
+/// Smith-Waterman over one reference base at a time, keeping the template
+/// start of every cell instead of a traceback. Among equally scoring
+/// alignments the shortest span is taken, so flanks that add nothing to the
+/// score are not counted.
+fn local_alignment_span(
+    template: &[u8],
+    reference: &[u8],
+    pairwise: &PairwiseParams,
+    band: impl Fn(usize) -> (isize, isize),
+) -> (i32, usize) {
+    let m = template.len();
+    let open = pairwise.gap_open_penalty + pairwise.gap_extend_penalty;
+    let extend = pairwise.gap_extend_penalty;
+    // Score and template start of the previous reference base's column, and
+    // of its alignments ending in a gap in the template
+    let mut h = vec![0i32; m + 1];
+    let mut h_start: Vec<usize> = (0..=m).collect();
+    let mut e = vec![i32::MIN / 2; m + 1];
+    let mut e_start = vec![0usize; m + 1];
+    let mut best = (0, 0);
+    // Template rows computed in the previous column
+    let mut previous_rows = 1..1;
+
+    for (j, &base) in reference.iter().enumerate() {
+        let (low, high) = band(j);
+        let first = (j as isize + low + 1).clamp(1, m as isize + 1) as usize;
+        let last = (j as isize + high + 1).clamp(0, m as isize) as usize;
+        let rows = first..last.max(first - 1) + 1;
+        // Cells left behind by the band start over
+        for i in previous_rows.filter(|i| !rows.contains(i)) {
+            (h[i], h_start[i], e[i]) = (0, i, i32::MIN / 2);
+        }
+        previous_rows = rows.clone();
+
+        let (mut diagonal, mut diagonal_start) = (h[first - 1], h_start[first - 1]);
+        let (mut f, mut f_start) = (i32::MIN / 2, 0);
+        for i in rows {
+            // Gap in the template: from the same template base, previous column
+            (e[i], e_start[i]) = better((h[i] + open, h_start[i]), (e[i] + extend, e_start[i]));
+            // Gap in the reference: from the previous template base, this column
+            (f, f_start) = better((h[i - 1] + open, h_start[i - 1]), (f + extend, f_start));
+            let score = if template[i - 1] == base {
+                pairwise.match_score
+            } else {
+                pairwise.mismatch_score
+            };
+            let (previous, previous_start) = (h[i], h_start[i]);
+            (h[i], h_start[i]) = [(e[i], e_start[i]), (f, f_start), (0, i)]
+                .into_iter()
+                .fold((diagonal + score, diagonal_start), better);
+            if h[i] > best.0 {
+                best = (h[i], i - h_start[i]);
+            }
+            (diagonal, diagonal_start) = (previous, previous_start);
+        }
+    }
+    best
+}
+
+/// `local_alignment_span` in the band around the shared k-mers of
+/// `template` and `reference`
+fn anchored_alignment_span(
+    template: &[u8],
+    reference: &[u8],
+    pairwise: &PairwiseParams,
+) -> (i32, usize) {
+    let matches = find_kmer_matches(template, reference, ANCHOR_LENGTH);
+    let chain = lcskpp(&matches, ANCHOR_LENGTH);
+    if chain.path.is_empty() {
+        if template.len().saturating_mul(reference.len()) > MAX_UNANCHORED_CELLS {
+            return (0, 0);
+        }
+        return local_alignment_span(template, reference, pairwise, |_| {
+            (isize::MIN / 4, isize::MAX / 4)
+        });
+    }
+
+    // (template position, reference position) of each anchor
+    let chained: Vec<(isize, isize)> = chain
+        .path
+        .iter()
+        .map(|&i| (matches[i].0 as isize, matches[i].1 as isize))
+        .collect();
+    // Chance k-mer hits far along the reference can join the chain. Split it
+    // where consecutive anchors are further apart on the reference than the
+    // template is long, and keep the part with the most anchors.
+    let anchors = chained
+        .chunk_by(|a, b| b.1 - a.1 <= b.0 - a.0 + template.len() as isize)
+        .max_by_key(|part| part.len())
+        .unwrap_or_default();
+    let (first, last) = (anchors[0], anchors[anchors.len() - 1]);
+    // Reference stretch the template reaches along the chain's outer diagonals
+    let start = (first.1 - first.0 - BAND_WIDTH).max(0);
+    let end = (last.1 + (template.len() as isize - last.0) + BAND_WIDTH)
+        .min(reference.len() as isize);
+    // Between two anchors both diagonals are in the band; before the first
+    // and after the last anchor, that anchor's diagonal
+    let band = |j: usize| {
+        let position = start + j as isize;
+        let next = anchors.partition_point(|a| a.1 <= position);
+        let (a, b) = (
+            anchors[next.saturating_sub(1)],
+            anchors[next.min(anchors.len() - 1)],
+        );
+        let (da, db) = (a.0 - a.1 + start, b.0 - b.1 + start);
+        (da.min(db) - BAND_WIDTH, da.max(db) + BAND_WIDTH)
+    };
+    local_alignment_span(
+        template,
+        &reference[start as usize..end as usize],
+        pairwise,
+        band,
+    )
+}
+
+/// Template coverage (percent) of every reference
+fn template_coverage(template: &str, sequences: &[&str], pairwise: &PairwiseParams) -> Vec<f64> {
+    let forward = template.as_bytes();
+    let reverse = reverse_complement(template);
+    sequences
+        .par_iter()
+        .map(|sequence| {
+            let sequence: Vec<u8> = sequence.bytes().filter(|&b| b != b'-').collect();
+            let (_, span) = [forward, reverse.as_bytes()]
+                .iter()
+                .map(|t| anchored_alignment_span(t, &sequence, pairwise))
+                .fold((0, 0), |best, strand| {
+                    if strand.0 > best.0 {
+                        strand
+                    } else {
+                        best
+                    }
+                });
+            span as f64 / template.len() as f64 * 100.0
+        })
+        .collect()
+}
+
+/// Apply `filter` to `references`. The coverage check needs the template.
+pub fn filter_references(
+    references: &ReferenceData,
+    template: Option<&str>,
+    filter: &ReferenceFilter,
+    pairwise: &PairwiseParams,
+) -> Result<(ReferenceData, FilterReport), String> {
+    let mut reasons: Vec<Option<RemovalReason>> = vec![None; references.len()];
+    let mut first_copy: HashMap<&str, usize> = HashMap::new();
+    for (i, sequence) in references.sequences.iter().enumerate() {
+        let length = ungapped_length(sequence);
+        let ambiguous = ambiguous_percent(sequence);
+        reasons[i] = if length < filter.min_length {
+            Some(RemovalReason::TooShort { length })
+        } else if ambiguous > filter.max_ambiguous_percent {
+            Some(RemovalReason::TooAmbiguous { percent: ambiguous })
+        } else if filter.remove_duplicates {
+            match first_copy.get(sequence.as_str()) {
+                Some(&first) => Some(RemovalReason::DuplicateOf {
+                    name: references.names[first].clone(),
+                }),
+                None => {
+                    first_copy.insert(sequence, i);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+    }
+
+    if filter.min_template_coverage > 0.0 {
+        let template = template.ok_or_else(|| {
+            "Load a template to filter references by template coverage".to_string()
+        })?;
+        let candidates: Vec<usize> = (0..references.len())
+            .filter(|&i| reasons[i].is_none())
+            .collect();
+        let sequences: Vec<&str> = candidates
+            .iter()
+            .map(|&i| references.sequences[i].as_str())
+            .collect();
+        let coverage = template_coverage(template, &sequences, pairwise);
+        for (&i, percent) in candidates.iter().zip(coverage) {
+            if percent < filter.min_template_coverage {
+                reasons[i] = Some(RemovalReason::LowTemplateCoverage { percent });
+            }
+        }
+    }
+
+    let mut kept = ReferenceData::new();
+    let mut removed = Vec::new();
+    for (index, reason) in reasons.into_iter().enumerate() {
+        let name = &references.names[index];
+        match reason {
+            Some(reason) => removed.push(RemovedReference {
+                index,
+                name: name.clone(),
+                reason,
+            }),
+            None => {
+                kept.names.push(name.clone());
+                kept.sequences.push(references.sequences[index].clone());
+            }
+        }
+    }
+    Ok((
+        kept,
+        FilterReport {
+            total: references.len(),
+            removed,
+        },
+    ))
+}
+
+/// The references `report` kept, for a set read again from the same input
+/// (a reopened project). Fails if the set no longer matches the report.
+pub fn apply_filter_report(
+    references: &ReferenceData,
+    report: &FilterReport,
+) -> Result<ReferenceData, String> {
+    let matches = references.len() == report.total
+        && report
+            .removed
+            .iter()
+            .all(|r| references.names.get(r.index) == Some(&r.name));
+    if !matches {
+        return Err(
+            "The references differ from the ones the saved filter report was made for"
+                .to_string(),
+        );
+    }
+    let mut removed = vec![false; references.len()];
+    for r in &report.removed {
+        removed[r.index] = true;
+    }
+    let mut kept = ReferenceData::new();
+    for (i, (name, sequence)) in references.names.iter().zip(&references.sequences).enumerate() {
+        if !removed[i] {
+            kept.names.push(name.clone());
+            kept.sequences.push(sequence.clone());
+        }
+    }
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "TATGGTACGTCATGTTCTAGAAATGGGCTGTACCGATTGCA";
+    // TEMPLATE with every sixth base changed
+    const DIVERGENT: &str = "TATGGAACGTCTTGTTCAAGAAAAGGGCTCTACCGTTTGCA";
+
+    #[test]
+    fn test_filter_reasons_in_order() {
+        let references = ReferenceData {
+            names: ["good", "short", "ns", "copy", "partial", "reverse", "divergent"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            sequences: vec![
+                format!("GG{}CC", TEMPLATE),
+                TEMPLATE[..10].to_string(),
+                format!("{}{}", &TEMPLATE[..20], "N".repeat(21)),
+                format!("GG{}CC", TEMPLATE),
+                format!("{}{}", &TEMPLATE[..22], "ACACACACACACACACACAC"),
+                reverse_complement(TEMPLATE),
+                DIVERGENT.to_string(),
+            ],
+        };
+        let filter = ReferenceFilter {
+            min_length: 20,
+            max_ambiguous_percent: 10.0,
+            remove_duplicates: true,
+            min_template_coverage: 90.0,
+        };
+        let (kept, report) = filter_references(
+            &references,
+            Some(TEMPLATE),
+            &filter,
+            &PairwiseParams::default(),
+        )
+        .unwrap();
+
+        // Mismatches inside the alignment count as covered
+        assert_eq!(kept.names, vec!["good", "reverse", "divergent"]);
+        assert_eq!(report.kept(), 3);
+        let reasons: Vec<(usize, String)> = report
+            .removed
+            .iter()
+            .map(|r| (r.index, r.reason.description()))
+            .collect();
+        assert_eq!(reasons[0], (1, "too short (10 bp)".to_string()));
+        assert_eq!(reasons[1], (2, "51.2% ambiguous bases".to_string()));
+        assert_eq!(reasons[2], (3, "duplicate of good".to_string()));
+        assert_eq!(reasons[3].0, 4);
+        assert!(matches!(
+            report.removed[3].reason,
+            RemovalReason::LowTemplateCoverage { percent } if percent < 90.0
+        ));
+        assert_eq!(report.table().rows.len(), 4);
+
+        // Reapplied to the same set (a reopened project), but not to another
+        assert_eq!(apply_filter_report(&references, &report).unwrap().names, kept.names);
+        assert!(apply_filter_report(&kept, &report).is_err());
+
+        assert!(filter_references(&references, None, &filter, &PairwiseParams::default()).is_err());
+    }
+
+    fn random_sequence(length: usize, seed: u64) -> String {
+        let mut state = seed;
+        (0..length)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                b"ACGT"[(state >> 62) as usize] as char
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_anchored_coverage_of_long_references() {
+        let template = random_sequence(400, 1);
+        // A deletion and two mismatches inside the covered stretch
+        let mut copy = format!("{}{}", &template[..150], &template[153..]);
+        copy.replace_range(60..61, if &copy[60..61] == "A" { "C" } else { "A" });
+        copy.replace_range(300..301, if &copy[300..301] == "A" { "C" } else { "A" });
+        let pairwise = PairwiseParams::default();
+
+        // Same result as the full alignment
+        let short = format!("{}{}{}", random_sequence(50, 2), copy, random_sequence(50, 3));
+        let full = local_alignment_span(template.as_bytes(), short.as_bytes(), &pairwise, |_| {
+            (isize::MIN / 4, isize::MAX / 4)
+        });
+        assert_eq!(
+            anchored_alignment_span(template.as_bytes(), short.as_bytes(), &pairwise),
+            full
+        );
+
+        // Megabase flanks only cost the k-mer search
+        let long = format!(
+            "{}{}{}",
+            random_sequence(1_000_000, 4),
+            reverse_complement(&copy),
+            random_sequence(1_000_000, 5)
+        );
+        assert_eq!(template_coverage(&template, &[&long], &pairwise), vec![100.0]);
+    }
+}
@@ -0,0 +1,378 @@
+//! Per-reference statistics and metadata parsed from FASTA headers
+//!
+//! Headers come in many layouts, so parsing is best effort. `key=value`
+//! pairs (optionally in square brackets, as in NCBI submission headers) are
+//! read first. The remaining fields are the `|`-separated parts of the
+//! header, or its words when there is no `|`: an accession-like field
+//! (`MN908947.3`, `NC_045512`, `EPI_ISL_402124`) is the accession, a date
+//! (`2020`, `2020-03` or `2020-03-15`; plain words only match full dates) is
+//! the collection date, the word after `strain` or `isolate`, or a word
+//! with at least two `/` (`hCoV-19/Wuhan/WIV04/2019`), is the strain, and the
+//! last `|` field made of letters only (at most four words) is the country.
+
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+use super::fasta::ReferenceData;
+use super::types::ExcludedReference;
+
+/// Metadata parsed from a FASTA header
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeaderMetadata {
+    pub accession: Option<String>,
+    pub strain: Option<String>,
+    pub date: Option<String>,
+    pub country: Option<String>,
+}
+
+/// One row of the reference table
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceInfo {
+    /// Length in bases, gaps excluded
+    pub length: usize,
+    /// GC percentage of the unambiguous bases
+    pub gc_percent: f64,
+    pub ambiguous: usize,
+    pub metadata: HeaderMetadata,
+}
+
+fn is_accession(field: &str) -> bool {
+    let base = match field.rsplit_once('.') {
+        Some((base, version))
+            if !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            base
+        }
+        _ => field,
+    };
+    let digits = base
+        .bytes()
+        .rev()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    let prefix = &base[..base.len() - digits];
+    digits >= 5
+        && (1..=8).contains(&prefix.len())
+        && prefix.bytes().any(|b| b.is_ascii_uppercase())
+        && prefix.bytes().all(|b| b.is_ascii_uppercase() || b == b'_')
+}
+
+fn is_date(field: &str, full_only: bool) -> bool {
+    let parts: Vec<&str> = field.split('-').collect();
+    if parts.len() > 3 || (full_only && parts.len() < 3) {
+        return false;
+    }
+    let number = |s: &str, digits: usize, range: std::ops::RangeInclusive<u32>| {
+        s.len() == digits
+            && s.bytes().all(|b| b.is_ascii_digit())
+            && s.parse().is_ok_and(|n| range.contains(&n))
+    };
+    number(parts[0], 4, 1900..=2099)
+        && parts.get(1).is_none_or(|m| number(m, 2, 1..=12))
+        && parts.get(2).is_none_or(|d| number(d, 2, 1..=31))
+}
+
+fn is_country(field: &str) -> bool {
+    let words = field.split_whitespace().count();
+    (1..=4).contains(&words)
+        && field
+            .chars()
+            .all(|c| c.is_alphabetic() || c == ' ' || c == '-' || c == '\'')
+}
+
+/// Word of `field` with at least two `/`, without enclosing parentheses
+fn slash_name(field: &str) -> Option<String> {
+    let word = field
+        .split_whitespace()
+        .find(|w| w.matches('/').count() >= 2)?;
+    let mut word = word.strip_prefix('(').unwrap_or(word);
+    if word.matches(')').count() > word.matches('(').count() {
+        word = word.strip_suffix(')').unwrap_or(word);
+    }
+    Some(word.to_string())
+}
+
+/// Parse the metadata of a FASTA header (without the leading `>`)
+pub fn parse_header(header: &str) -> HeaderMetadata {
+    let mut metadata = HeaderMetadata::default();
+    let mut rest = String::new();
+
+    // key=value pairs, bracketed or as single words
+    let mut remaining = header;
+    while let Some(open) = remaining.find('[') {
+        let Some(close) = remaining[open..].find(']').map(|c| open + c) else {
+            break;
+        };
+        rest.push_str(&remaining[..open]);
+        rest.push(' ');
+        if let Some((key, value)) = remaining[open + 1..close].split_once('=') {
+            set_key_value(&mut metadata, key, value);
+        }
+        remaining = &remaining[close + 1..];
+    }
+    rest.push_str(remaining);
+    let rest: String = rest
+        .split(' ')
+        .filter(|word| match word.split_once('=') {
+            Some((key, value)) => !set_key_value(&mut metadata, key, value),
+            None => true,
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let piped = rest.contains('|');
+    let fields: Vec<&str> = if piped {
+        rest.split('|').map(str::trim).collect()
+    } else {
+        rest.split_whitespace()
+            .map(|w| w.trim_end_matches([',', ';']))
+            .collect()
+    };
+    for (i, &field) in fields.iter().enumerate() {
+        if field.is_empty() {
+            continue;
+        }
+        if metadata.accession.is_none() && is_accession(field) {
+            metadata.accession = Some(field.to_string());
+        } else if metadata.date.is_none() && is_date(field, !piped) {
+            metadata.date = Some(field.to_string());
+        } else if metadata.strain.is_none() && slash_name(field).is_some() {
+            metadata.strain = slash_name(field);
+        } else if metadata.strain.is_none()
+            && !piped
+            && (field.eq_ignore_ascii_case("strain") || field.eq_ignore_ascii_case("isolate"))
+        {
+            metadata.strain = fields.get(i + 1).map(|s| s.to_string());
+        }
+    }
+    if piped && metadata.country.is_none() {
+        metadata.country = fields
+            .iter()
+            .skip(1)
+            .rev()
+            .find(|f| is_country(f))
+            .map(|f| f.to_string());
+    }
+    metadata
+}
+
+/// Store a `key=value` pair in `metadata`; false if the key is not known
+fn set_key_value(metadata: &mut HeaderMetadata, key: &str, value: &str) -> bool {
+    let value = value.trim();
+    let field = match key.trim().to_ascii_lowercase().as_str() {
+        "accession" => &mut metadata.accession,
+        "strain" | "isolate" => &mut metadata.strain,
+        "date" | "collection_date" | "collection-date" => &mut metadata.date,
+        // geo_loc_name is "country: region"
+        "country" | "geo_loc_name" => {
+            metadata.country = Some(value.split(':').next().unwrap_or(value).trim().to_string());
+            return true;
+        }
+        _ => return false,
+    };
+    *field = Some(value.to_string());
+    true
+}
+
+/// Statistics and header metadata of every reference
+pub fn reference_info(references: &ReferenceData) -> Vec<ReferenceInfo> {
+    references
+        .names
+        .par_iter()
+        .zip(&references.sequences)
+        .map(|(name, sequence)| {
+            let (mut length, mut gc, mut unambiguous) = (0, 0, 0);
+            for b in sequence.bytes() {
+                match b.to_ascii_uppercase() {
+                    b'-' => continue,
+                    b'G' | b'C' => {
+                        gc += 1;
+                        unambiguous += 1;
+                    }
+                    b'A' | b'T' => unambiguous += 1,
+                    _ => {}
+                }
+                length += 1;
+            }
+            ReferenceInfo {
+                length,
+                gc_percent: if unambiguous > 0 {
+                    gc as f64 / unambiguous as f64 * 100.0
+                } else {
+                    0.0
+                },
+                ambiguous: length - unambiguous,
+                metadata: parse_header(name),
+            }
+        })
+        .collect()
+}
+
+/// Ordinal of every reference among the references of its name (0 for the
+/// first one)
+pub fn name_ordinals(names: &[String]) -> Vec<usize> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    names
+        .iter()
+        .map(|name| {
+            let count = seen.entry(name).or_default();
+            *count += 1;
+            *count - 1
+        })
+        .collect()
+}
+
+/// Whether each reference is in `excluded`
+pub fn excluded_mask(names: &[String], excluded: &[ExcludedReference]) -> Vec<bool> {
+    if excluded.is_empty() {
+        return vec![false; names.len()];
+    }
+    let mut by_name: HashMap<&str, Vec<&ExcludedReference>> = HashMap::new();
+    for entry in excluded {
+        by_name.entry(&entry.name).or_default().push(entry);
+    }
+    names
+        .iter()
+        .zip(name_ordinals(names))
+        .map(|(name, ordinal)| {
+            by_name
+                .get(name.as_str())
+                .is_some_and(|entries| entries.iter().any(|e| e.matches(name, ordinal)))
+        })
+        .collect()
+}
+
+/// Include or exclude reference `index`. `ordinals` are the `name_ordinals`
+/// of `names`. Including one reference of a name excluded as a whole (a
+/// name-only entry) keeps its other references excluded.
+pub fn set_excluded(
+    excluded: &mut Vec<ExcludedReference>,
+    names: &[String],
+    ordinals: &[usize],
+    index: usize,
+    exclude: bool,
+) {
+    let (name, ordinal) = (&names[index], ordinals[index]);
+    if exclude {
+        if !excluded.iter().any(|e| e.matches(name, ordinal)) {
+            excluded.push(ExcludedReference::new(name, ordinal));
+        }
+        return;
+    }
+    if let Some(whole) = excluded
+        .iter()
+        .position(|e| e.name == *name && e.ordinal.is_none())
+    {
+        excluded.remove(whole);
+        let others = names
+            .iter()
+            .zip(ordinals)
+            .filter(|&(n, &o)| n == name && o != ordinal)
+            .map(|(n, &o)| ExcludedReference::new(n, o));
+        excluded.extend(others);
+    }
+    excluded.retain(|e| !e.matches(name, ordinal));
+}
+
+/// The references not in `excluded`
+pub fn included_references(
+    references: &ReferenceData,
+    excluded: &[ExcludedReference],
+) -> ReferenceData {
+    let mut included = ReferenceData::new();
+    let mask = excluded_mask(&references.names, excluded);
+    for ((name, sequence), excluded) in references.names.iter().zip(&references.sequences).zip(mask)
+    {
+        if !excluded {
+            included.names.push(name.clone());
+            included.sequences.push(sequence.clone());
+        }
+    }
+    included
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(accession: &str, strain: &str, date: &str, country: &str) -> HeaderMetadata {
+        let field = |s: &str| (!s.is_empty()).then(|| s.to_string());
+        HeaderMetadata {
+            accession: field(accession),
+            strain: field(strain),
+            date: field(date),
+            country: field(country),
+        }
+    }
+
+    #[test]
+    fn test_parse_header_layouts() {
+        assert_eq!(
+            parse_header("MN908947.3 Severe acute respiratory syndrome coronavirus 2 isolate Wuhan-Hu-1, complete genome"),
+            metadata("MN908947.3", "Wuhan-Hu-1", "", "")
+        );
+        assert_eq!(
+            parse_header("hCoV-19/Wuhan/WIV04/2019|EPI_ISL_402124|2019-12-30"),
+            metadata(
+                "EPI_ISL_402124",
+                "hCoV-19/Wuhan/WIV04/2019",
+                "2019-12-30",
+                ""
+            )
+        );
+        assert_eq!(
+            parse_header(
+                "OQ123456.1 |Influenza A virus (A/swine/Iowa/A02524856/2020(H1N1))|USA|2020-10"
+            ),
+            metadata(
+                "OQ123456.1",
+                "A/swine/Iowa/A02524856/2020(H1N1)",
+                "2020-10",
+                "USA"
+            )
+        );
+        assert_eq!(
+            parse_header(
+                "seq7 [strain=ST 131] [country=Viet Nam: Hanoi] collection_date=2018-05-02"
+            ),
+            metadata("", "ST 131", "2018-05-02", "Viet Nam")
+        );
+        assert_eq!(parse_header("ref_1 2019 sample"), HeaderMetadata::default());
+    }
+
+    #[test]
+    fn test_reference_info_and_exclusion() {
+        let references = ReferenceData {
+            names: ["a", "b", "c", "b"].iter().map(|s| s.to_string()).collect(),
+            sequences: ["GCAT-NN", "GGGG", "AT", "CC"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        };
+        let info = reference_info(&references);
+        assert_eq!(info[0].length, 6);
+        assert_eq!(info[0].ambiguous, 2);
+        assert_eq!(info[0].gc_percent, 50.0);
+        assert_eq!(info[1].gc_percent, 100.0);
+
+        // References sharing a name are excluded one at a time
+        let ordinals = name_ordinals(&references.names);
+        assert_eq!(ordinals, vec![0, 0, 0, 1]);
+        let mut excluded = Vec::new();
+        set_excluded(&mut excluded, &references.names, &ordinals, 3, true);
+        let included = included_references(&references, &excluded);
+        assert_eq!(included.names, vec!["a", "b", "c"]);
+        assert_eq!(included.sequences, vec!["GCAT-NN", "GGGG", "AT"]);
+
+        // Files that stored names only exclude every reference of the name
+        let mut excluded: Vec<ExcludedReference> = serde_json::from_str(r#"["b"]"#).unwrap();
+        assert_eq!(
+            excluded_mask(&references.names, &excluded),
+            vec![false, true, false, true]
+        );
+        set_excluded(&mut excluded, &references.names, &ordinals, 1, false);
+        assert_eq!(excluded, vec![ExcludedReference::new("b", 1)]);
+        let json = serde_json::to_string(&excluded).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<ExcludedReference>>(&json).unwrap(), excluded);
+    }
+}
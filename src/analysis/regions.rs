@@ -0,0 +1,236 @@
+//! Conserved region detection across all oligo lengths
+//!
+//! A window start position is "good" when, for every screened length, the
+//! window starting there was analyzed, needs at most `max_variants` variants
+//! and has a no-match rate within `max_no_match_percent`. Runs of consecutive
+//! good start positions form a region that spans from the first start to the
+//! end of the longest window at the last start, in 0-based half-open template
+//! coordinates (as in BED).
+
+use super::types::ScreeningResults;
+
+/// Thresholds for conserved region detection
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConservedRegionParams {
+    pub max_variants: usize,
+    pub max_no_match_percent: f64,
+    /// Regions shorter than this (bp) are dropped
+    pub min_length: usize,
+}
+
+impl Default for ConservedRegionParams {
+    fn default() -> Self {
+        Self {
+            max_variants: 2,
+            max_no_match_percent: 5.0,
+            min_length: 30,
+        }
+    }
+}
+
+/// A conserved stretch of the template
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConservedRegion {
+    /// 0-based start (inclusive)
+    pub start: usize,
+    /// 0-based end (exclusive)
+    pub end: usize,
+    /// Highest `variants_needed` of any window in the region
+    pub worst_variants: usize,
+    /// Mean coverage at threshold over all windows in the region
+    pub mean_coverage: f64,
+}
+
+impl ConservedRegion {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end == self.start
+    }
+}
+
+/// Find conserved regions in `results`
+pub fn find_conserved_regions(
+    results: &ScreeningResults,
+    params: &ConservedRegionParams,
+) -> Vec<ConservedRegion> {
+    let mut lengths: Vec<u32> = results.results_by_length.keys().copied().collect();
+    lengths.sort();
+    let (Some(&shortest), Some(&longest)) = (lengths.first(), lengths.last()) else {
+        return Vec::new();
+    };
+    let positions: Vec<usize> = results.results_by_length[&shortest]
+        .positions
+        .iter()
+        .map(|p| p.position)
+        .collect();
+
+    // Per start position: Some((worst variants, coverage sum, window count)) if good
+    let evaluate = |pos: usize| -> Option<(usize, f64, usize)> {
+        let mut worst = 0;
+        let mut coverage = 0.0;
+        for length in &lengths {
+            let cells = &results.results_by_length[length].positions;
+            let i = cells.binary_search_by_key(&pos, |p| p.position).ok()?;
+            let cell = &cells[i];
+            let a = &cell.analysis;
+            if a.skipped || cell.variants_needed > params.max_variants {
+                return None;
+            }
+            let no_match_percent = if a.total_sequences > 0 {
+                a.no_match_count as f64 / a.total_sequences as f64 * 100.0
+            } else {
+                0.0
+            };
+            if no_match_percent > params.max_no_match_percent {
+                return None;
+            }
+            worst = worst.max(cell.variants_needed);
+            coverage += a.coverage_at_threshold;
+        }
+        Some((worst, coverage, lengths.len()))
+    };
+
+    let mut regions = Vec::new();
+    // (first start, last start, worst, coverage sum, window count)
+    let mut current: Option<(usize, usize, usize, f64, usize)> = None;
+    let finish = |run: (usize, usize, usize, f64, usize), regions: &mut Vec<ConservedRegion>| {
+        let (first, last, worst, coverage, count) = run;
+        let end = (last + longest as usize).min(results.template_length);
+        if end.saturating_sub(first) >= params.min_length {
+            regions.push(ConservedRegion {
+                start: first,
+                end,
+                worst_variants: worst,
+                mean_coverage: coverage / count as f64,
+            });
+        }
+    };
+
+    for &pos in &positions {
+        match (evaluate(pos), current.as_mut()) {
+            (Some((worst, coverage, count)), Some(run)) => {
+                run.1 = pos;
+                run.2 = run.2.max(worst);
+                run.3 += coverage;
+                run.4 += count;
+            }
+            (Some((worst, coverage, count)), None) => {
+                current = Some((pos, pos, worst, coverage, count));
+            }
+            (None, _) => {
+                if let Some(run) = current.take() {
+                    finish(run, &mut regions);
+                }
+            }
+        }
+    }
+    if let Some(run) = current {
+        finish(run, &mut regions);
+    }
+    regions
+}
+
+/// BED lines (chrom, start, end, name, score) for the regions. The score is
+/// the mean coverage scaled to BED's 0–1000 range.
+pub fn regions_to_bed(regions: &[ConservedRegion], chrom: &str) -> String {
+    let chrom: String = chrom
+        .split_whitespace()
+        .next()
+        .unwrap_or("template")
+        .to_string();
+    let mut out = String::new();
+    for (i, region) in regions.iter().enumerate() {
+        out.push_str(&format!(
+            "{}\t{}\t{}\tconserved_{}_maxvar{}\t{}\n",
+            chrom,
+            region.start,
+            region.end,
+            i + 1,
+            region.worst_variants,
+            (region.mean_coverage * 10.0).round().clamp(0.0, 1000.0) as u32
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::{
+        AnalysisParams, LengthResult, PositionResult, WindowAnalysisResult,
+    };
+
+    /// Two lengths (4 and 5) over a 20 bp template; `bad` start positions
+    /// need 5 variants at length 5.
+    fn results_with_bad(bad: &[usize]) -> ScreeningResults {
+        let mut results =
+            ScreeningResults::new(AnalysisParams::default(), 20, 10, "A".repeat(20));
+        for length in [4u32, 5] {
+            let positions = (0..=(20 - length as usize))
+                .map(|position| {
+                    let needed = if length == 5 && bad.contains(&position) { 5 } else { 1 };
+                    PositionResult {
+                        position,
+                        variants_needed: needed,
+                        source: None,
+                        analysis: WindowAnalysisResult {
+                            total_sequences: 10,
+                            sequences_analyzed: 10,
+                            coverage_at_threshold: 100.0,
+                            variants_for_threshold: needed,
+                            ..Default::default()
+                        },
+                    }
+                })
+                .collect();
+            results.results_by_length.insert(
+                length,
+                LengthResult {
+                    oligo_length: length,
+                    positions,
+                    column_profile: Vec::new(),
+                },
+            );
+        }
+        results
+    }
+
+    #[test]
+    fn test_regions_split_on_bad_windows() {
+        let params = ConservedRegionParams {
+            max_variants: 2,
+            max_no_match_percent: 5.0,
+            min_length: 5,
+        };
+        let regions = find_conserved_regions(&results_with_bad(&[6]), &params);
+        // Starts 0..=5 -> [0, 10); starts 7..=15 (16 has no 5 bp window) -> [7, 20)
+        assert_eq!(regions.len(), 2);
+        assert_eq!((regions[0].start, regions[0].end), (0, 10));
+        assert_eq!((regions[1].start, regions[1].end), (7, 20));
+        assert_eq!(regions[0].worst_variants, 1);
+        assert_eq!(regions[0].mean_coverage, 100.0);
+
+        let long_only = ConservedRegionParams {
+            min_length: 11,
+            ..params
+        };
+        assert_eq!(find_conserved_regions(&results_with_bad(&[6]), &long_only).len(), 1);
+    }
+
+    #[test]
+    fn test_bed_output() {
+        let regions = vec![ConservedRegion {
+            start: 10,
+            end: 50,
+            worst_variants: 2,
+            mean_coverage: 97.5,
+        }];
+        assert_eq!(
+            regions_to_bed(&regions, "NC_001 some description"),
+            "NC_001\t10\t50\tconserved_1_maxvar2\t975\n"
+        );
+    }
+}
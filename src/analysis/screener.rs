@@ -5,17 +5,21 @@
 
 use super::analyzer::analyze_sequences;
 use super::fasta::{ReferenceData, TemplateData};
-use super::pairwise::{collect_matches_with_aligner, create_aligner, DnaAligner};
+use super::pairwise::{align_references_with_aligner, create_aligner, DnaAligner, PairwiseMatch};
+use super::template_ambiguity::{resolve_template_ambiguities, template_window_issue};
 use super::types::{
-    AnalysisParams, LengthResult, PositionResult, ProgressUpdate, ScreeningResults,
-    WindowAnalysisResult,
+    AnalysisParams, ColumnComposition, LengthResult, MatchGroup, PositionResult, ProgressUpdate,
+    ScreeningResults, TemplateAmbiguity, WindowAnalysisResult,
 };
 use rayon::prelude::*;
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 
 /// Run the complete screening analysis using pairwise alignment.
+/// With `TemplateAmbiguity::MajorityReferenceBase` the results hold the
+/// resolved template.
 pub fn run_screening(
     template: &TemplateData,
     references: &ReferenceData,
@@ -23,11 +27,18 @@ pub fn run_screening(
     progress_tx: Option<Sender<ProgressUpdate>>,
 ) -> ScreeningResults {
     // Configure rayon thread pool
-    let num_threads = params.thread_count.get_count();
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(num_threads)
-        .build()
-        .unwrap_or_else(|_| rayon::ThreadPoolBuilder::new().build().unwrap());
+    let pool = build_thread_pool(params);
+
+    let resolved;
+    let template = match params.template_ambiguity {
+        TemplateAmbiguity::MajorityReferenceBase => {
+            resolved = pool.install(|| {
+                resolve_template_ambiguities(template, references, &params.pairwise).0
+            });
+            &resolved
+        }
+        TemplateAmbiguity::SkipWindows => template,
+    };
 
     let mut results = ScreeningResults::new(
         params.clone(),
@@ -35,6 +46,7 @@ pub fn run_screening(
         references.len(),
         template.sequence.clone(),
     );
+    results.template_masked = template.masked.clone();
 
     // Pre-convert reference sequences to byte vectors for alignment
     let ref_bytes: Vec<Vec<u8>> = references
@@ -86,11 +98,7 @@ fn analyze_length(
     let template_len = template.sequence.len();
 
     // Calculate positions to analyze
-    let max_start = if template_len >= length {
-        template_len - length
-    } else {
-        0
-    };
+    let max_start = template_len.saturating_sub(length);
 
     let positions: Vec<usize> = (0..=max_start).step_by(resolution).collect();
     let total_positions = positions.len();
@@ -103,60 +111,320 @@ fn analyze_length(
     let pw_params = params.pairwise;
 
     // Process positions in parallel, one Aligner per rayon task
-    let mut position_results: Vec<PositionResult> = positions
+    let mut windows: Vec<(PositionResult, Vec<ColumnComposition>)> = positions
         .par_iter()
         .map_init(
             move || create_aligner(length, max_ref_len, &pw_params),
             |aligner, &position| {
-                let analysis = analyze_window(
-                    template_bytes,
-                    ref_bytes,
-                    params,
-                    position,
-                    length,
-                    aligner,
-                );
+                let (analysis, composition) =
+                    match template_window_issue(template, params, position, length) {
+                        Some(reason) => (skipped_window(reason, ref_bytes.len()), Vec::new()),
+                        None => analyze_window(
+                            template_bytes,
+                            ref_bytes,
+                            params,
+                            position,
+                            length,
+                            aligner,
+                        ),
+                    };
 
                 // Update progress
                 let completed = completed_count.fetch_add(1, Ordering::Relaxed) + 1;
-                if let Some(tx) = progress_tx {
-                    if completed % 10 == 0 || completed == total_positions {
-                        let _ = tx.send(ProgressUpdate {
-                            current_length: oligo_length,
-                            current_position: position,
-                            total_positions,
-                            lengths_completed: length_idx,
-                            total_lengths,
-                            message: format!(
-                                "Length {}/{}: Position {}/{}",
-                                length_idx + 1,
-                                total_lengths,
-                                completed,
-                                total_positions
-                            ),
-                        });
-                    }
-                }
+                report_progress(
+                    progress_tx,
+                    oligo_length,
+                    position,
+                    completed,
+                    total_positions,
+                    length_idx,
+                    total_lengths,
+                );
 
-                PositionResult {
+                let result = PositionResult {
                     position,
                     variants_needed: analysis.variants_for_threshold,
+                    source: None,
                     analysis,
-                }
+                };
+                (result, composition)
             },
         )
         .collect();
 
     // Sort results by position
-    position_results.sort_by_key(|r| r.position);
+    windows.sort_by_key(|(r, _)| r.position);
+
+    build_length_result(oligo_length, template_len, windows)
+}
+
+/// Assemble a `LengthResult` from position-sorted windows, summing the window
+/// compositions into the per-template-position profile.
+fn build_length_result(
+    oligo_length: u32,
+    template_len: usize,
+    windows: Vec<(PositionResult, Vec<ColumnComposition>)>,
+) -> LengthResult {
+    let mut column_profile = vec![ColumnComposition::default(); template_len];
+    let mut positions = Vec::with_capacity(windows.len());
+    for (result, composition) in windows {
+        for (column, window_column) in column_profile
+            .iter_mut()
+            .skip(result.position)
+            .zip(&composition)
+        {
+            column.merge(window_column);
+        }
+        positions.push(result);
+    }
 
     LengthResult {
         oligo_length,
-        positions: position_results,
+        positions,
+        column_profile,
+    }
+}
+
+/// Whether `previous` can be extended with `rescreen_with_new_references`:
+/// it needs a single template, `store_match_data`, and a template that was
+/// not resolved from the references (`TemplateAmbiguity::MajorityReferenceBase`).
+pub fn check_rescreen(previous: &ScreeningResults) -> Result<(), String> {
+    if !previous.extra_templates.is_empty() {
+        return Err(
+            "Results screened with additional templates cannot be extended; run a full screen instead"
+                .to_string(),
+        );
+    }
+    if !previous.params.store_match_data {
+        return Err(
+            "Previous results were saved without per-reference match data; run a full screen instead"
+                .to_string(),
+        );
+    }
+    if previous.params.template_ambiguity == TemplateAmbiguity::MajorityReferenceBase {
+        return Err(
+            "Template ambiguities were resolved from the previous references; run a full screen instead"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Extend a previous screen with newly added references.
+///
+/// Only the new references are aligned; their matches are merged into the
+/// stored per-reference match data of each window and the variant analysis is
+/// re-run on the combined set. The new references are numbered after the
+/// previous ones, so the output equals a full `run_screening` over the previous
+/// references followed by `new_references`.
+///
+/// Fails if `check_rescreen` rejects `previous`.
+pub fn rescreen_with_new_references(
+    previous: &ScreeningResults,
+    new_references: &ReferenceData,
+    progress_tx: Option<Sender<ProgressUpdate>>,
+) -> Result<ScreeningResults, String> {
+    check_rescreen(previous)?;
+
+    let params = &previous.params;
+    let pool = build_thread_pool(params);
+    let total_refs = previous.total_sequences + new_references.len();
+
+    let mut results = ScreeningResults::new(
+        params.clone(),
+        previous.template_length,
+        total_refs,
+        previous.template_sequence.clone(),
+    );
+    results.template_origin = previous.template_origin.clone();
+    results.template_masked = previous.template_masked.clone();
+
+    let ref_bytes: Vec<Vec<u8>> = new_references
+        .sequences
+        .iter()
+        .map(|s| s.as_bytes().to_vec())
+        .collect();
+    let max_ref_len = ref_bytes.iter().map(|r| r.len()).max().unwrap_or(0);
+    let template_bytes = previous.template_sequence.as_bytes();
+    let template = TemplateData {
+        name: "Template".to_string(),
+        sequence: previous.template_sequence.clone(),
+        masked: previous.template_masked.clone(),
+    };
+
+    let mut lengths: Vec<u32> = previous.results_by_length.keys().copied().collect();
+    lengths.sort();
+    let total_lengths = lengths.len() as u32;
+
+    for (length_idx, &oligo_length) in lengths.iter().enumerate() {
+        let previous_length = &previous.results_by_length[&oligo_length];
+        let length = oligo_length as usize;
+        let total_positions = previous_length.positions.len();
+        let completed_count = AtomicUsize::new(0);
+        let pw_params = params.pairwise;
+
+        let windows: Vec<(PositionResult, Vec<ColumnComposition>)> = pool.install(|| {
+            previous_length
+                .positions
+                .par_iter()
+                .map_init(
+                    || create_aligner(length, max_ref_len, &pw_params),
+                    |aligner, prev| {
+                        if let Some(reason) =
+                            template_window_issue(&template, params, prev.position, length)
+                        {
+                            let result = PositionResult {
+                                analysis: skipped_window(reason, total_refs),
+                                ..prev.clone()
+                            };
+                            return (result, Vec::new());
+                        }
+                        let oligo = &template_bytes[prev.position..prev.position + length];
+                        let alignments = align_references_with_aligner(
+                            aligner,
+                            oligo,
+                            &ref_bytes,
+                            &params.pairwise,
+                        );
+
+                        let mut groups: BTreeMap<String, MatchGroup> = prev
+                            .analysis
+                            .matches
+                            .iter()
+                            .map(|g| (g.sequence.clone(), g.clone()))
+                            .collect();
+                        let new_no_match =
+                            group_matches(alignments, previous.total_sequences, &mut groups);
+                        let composition = window_composition(&groups, length);
+                        let analysis = analyze_match_groups(
+                            groups,
+                            prev.analysis.no_match_count + new_no_match,
+                            total_refs,
+                            params,
+                        );
+
+                        let completed = completed_count.fetch_add(1, Ordering::Relaxed) + 1;
+                        report_progress(
+                            &progress_tx,
+                            oligo_length,
+                            prev.position,
+                            completed,
+                            total_positions,
+                            length_idx as u32,
+                            total_lengths,
+                        );
+
+                        let result = PositionResult {
+                            position: prev.position,
+                            variants_needed: analysis.variants_for_threshold,
+                            source: None,
+                            analysis,
+                        };
+                        (result, composition)
+                    },
+                )
+                .collect()
+        });
+
+        results.results_by_length.insert(
+            oligo_length,
+            build_length_result(oligo_length, previous.template_length, windows),
+        );
+    }
+
+    Ok(results)
+}
+
+/// Send a progress update every 10 positions and at the end of a length.
+fn report_progress(
+    progress_tx: &Option<Sender<ProgressUpdate>>,
+    oligo_length: u32,
+    position: usize,
+    completed: usize,
+    total_positions: usize,
+    length_idx: u32,
+    total_lengths: u32,
+) {
+    if let Some(tx) = progress_tx {
+        if completed.is_multiple_of(10) || completed == total_positions {
+            let _ = tx.send(ProgressUpdate {
+                current_length: oligo_length,
+                current_position: position,
+                total_positions,
+                lengths_completed: length_idx,
+                total_lengths,
+                message: format!(
+                    "Length {}/{}: Position {}/{}",
+                    length_idx + 1,
+                    total_lengths,
+                    completed,
+                    total_positions
+                ),
+            });
+        }
+    }
+}
+
+/// Build the rayon pool for the configured thread count.
+fn build_thread_pool(params: &AnalysisParams) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(params.thread_count.get_count())
+        .build()
+        .unwrap_or_else(|_| rayon::ThreadPoolBuilder::new().build().unwrap())
+}
+
+/// Re-analyze one (position, length) window on its own, outside of a full screen.
+/// Creates a dedicated aligner, so it is meant for occasional single-cell use
+/// (e.g. comparing parameter choices from the detail view).
+pub fn analyze_single_window(
+    template: &TemplateData,
+    references: &ReferenceData,
+    params: &AnalysisParams,
+    position: usize,
+    oligo_length: u32,
+) -> WindowAnalysisResult {
+    let length = oligo_length as usize;
+    if position + length > template.sequence.len() {
+        return skipped_window(
+            "Window extends past the end of the template".to_string(),
+            references.len(),
+        );
+    }
+    if let Some(reason) = template_window_issue(template, params, position, length) {
+        return skipped_window(reason, references.len());
+    }
+
+    let ref_bytes: Vec<Vec<u8>> = references
+        .sequences
+        .iter()
+        .map(|s| s.as_bytes().to_vec())
+        .collect();
+    let max_ref_len = ref_bytes.iter().map(|r| r.len()).max().unwrap_or(0);
+    let mut aligner = create_aligner(length, max_ref_len, &params.pairwise);
+
+    let (analysis, _) = analyze_window(
+        template.sequence.as_bytes(),
+        &ref_bytes,
+        params,
+        position,
+        length,
+        &mut aligner,
+    );
+    analysis
+}
+
+/// Result of a window that is not screened
+fn skipped_window(reason: String, total_refs: usize) -> WindowAnalysisResult {
+    WindowAnalysisResult {
+        total_sequences: total_refs,
+        skipped: true,
+        skip_reason: Some(reason),
+        ..Default::default()
     }
 }
 
 /// Analyze a single window at a specific position using a pre-existing aligner.
+/// Also returns the per-column base composition of the matched sequences.
 fn analyze_window(
     template_bytes: &[u8],
     ref_bytes: &[Vec<u8>],
@@ -164,16 +432,78 @@ fn analyze_window(
     position: usize,
     length: usize,
     aligner: &mut DnaAligner,
-) -> WindowAnalysisResult {
+) -> (WindowAnalysisResult, Vec<ColumnComposition>) {
     // Extract oligo from template
     let oligo = &template_bytes[position..position + length];
     let total_refs = ref_bytes.len();
 
     // Pairwise align against all references using the shared aligner
-    let (matched_sequences, no_match_count) =
-        collect_matches_with_aligner(aligner, oligo, ref_bytes, &params.pairwise);
+    let alignments = align_references_with_aligner(aligner, oligo, ref_bytes, &params.pairwise);
+
+    let mut groups = BTreeMap::new();
+    let no_match_count = group_matches(alignments, 0, &mut groups);
+    let composition = window_composition(&groups, length);
+
+    (
+        analyze_match_groups(groups, no_match_count, total_refs, params),
+        composition,
+    )
+}
 
-    if matched_sequences.is_empty() {
+/// Per-column base counts of the matched sequences of one window
+fn window_composition(
+    groups: &BTreeMap<String, MatchGroup>,
+    length: usize,
+) -> Vec<ColumnComposition> {
+    let mut columns = vec![ColumnComposition::default(); length];
+    for group in groups.values() {
+        let n = group.references.len() as u32;
+        for (column, &base) in columns.iter_mut().zip(group.sequence.as_bytes()) {
+            column.add(base, n);
+        }
+    }
+    columns
+}
+
+/// Add accepted alignments to `groups` (keyed by matched sequence), numbering
+/// references from `index_offset`. Returns the number of rejected references.
+fn group_matches(
+    alignments: Vec<Option<PairwiseMatch>>,
+    index_offset: usize,
+    groups: &mut BTreeMap<String, MatchGroup>,
+) -> usize {
+    let mut no_match_count = 0;
+    for (i, alignment) in alignments.into_iter().enumerate() {
+        let Some(m) = alignment else {
+            no_match_count += 1;
+            continue;
+        };
+        groups
+            .entry(m.matched_sequence)
+            .or_insert_with_key(|seq| MatchGroup {
+                sequence: seq.clone(),
+                mismatches: m.mismatches,
+                references: Vec::new(),
+            })
+            .references
+            .push(index_offset + i);
+    }
+    no_match_count
+}
+
+/// Run the variant analysis for one window from its grouped matches.
+/// Shared by the full screen and the incremental re-screen so both produce
+/// identical results for the same set of matches.
+fn analyze_match_groups(
+    groups: BTreeMap<String, MatchGroup>,
+    no_match_count: usize,
+    total_refs: usize,
+    params: &AnalysisParams,
+) -> WindowAnalysisResult {
+    let matches: Vec<MatchGroup> = groups.into_values().collect();
+    let matched_count: usize = matches.iter().map(|g| g.references.len()).sum();
+
+    if matched_count == 0 {
         return WindowAnalysisResult {
             total_sequences: total_refs,
             sequences_analyzed: 0,
@@ -184,8 +514,11 @@ fn analyze_window(
         };
     }
 
-    // Convert to &str for the analyzer
-    let seq_refs: Vec<&str> = matched_sequences.iter().map(|s| s.as_str()).collect();
+    // One &str per matching reference for the analyzer
+    let seq_refs: Vec<&str> = matches
+        .iter()
+        .flat_map(|g| std::iter::repeat_n(g.sequence.as_str(), g.references.len()))
+        .collect();
 
     // Run the variant analysis on matched sequences
     let mut result = analyze_sequences(
@@ -196,12 +529,17 @@ fn analyze_window(
     );
 
     result.total_sequences = total_refs;
-    result.sequences_analyzed = matched_sequences.len();
+    result.sequences_analyzed = matched_count;
     result.no_match_count = no_match_count;
+    result.mean_mismatches = matches
+        .iter()
+        .map(|g| g.mismatches * g.references.len())
+        .sum::<usize>() as f64
+        / matched_count as f64;
 
     // Rescale variant percentages against total references (including no-matches)
     // so that no-match sequences count toward reducing coverage
-    if total_refs > matched_sequences.len() {
+    if total_refs > matched_count {
         let total_f = total_refs as f64;
         for variant in &mut result.variants {
             variant.percentage = (variant.count as f64 / total_f) * 100.0;
@@ -225,6 +563,10 @@ fn analyze_window(
         result.coverage_at_threshold = new_coverage;
     }
 
+    if params.store_match_data {
+        result.matches = matches;
+    }
+
     result
 }
 
@@ -238,6 +580,7 @@ mod tests {
         let template = TemplateData {
             name: "Template".to_string(),
             sequence: "TATGGTACGTCATGTTCTAGAAATGGGCTGT".to_string(),
+            masked: Vec::new(),
         };
 
         let references = ReferenceData {
@@ -271,6 +614,183 @@ mod tests {
         // First position should have variants
         let first_pos = &length_result.positions[0];
         assert!(!first_pos.analysis.skipped);
-        assert!(first_pos.analysis.variants.len() >= 1);
+        assert!(!first_pos.analysis.variants.is_empty());
+    }
+
+    #[test]
+    fn test_single_window_matches_screen() {
+        let template = TemplateData {
+            name: "Template".to_string(),
+            sequence: "TATGGTACGTCATGTTCTAGAAATGGGCTGT".to_string(),
+            masked: Vec::new(),
+        };
+        let references = ReferenceData {
+            names: vec!["Ref1".to_string(), "Ref2".to_string(), "Ref3".to_string()],
+            sequences: vec![
+                "TATGGTACGTCATGTTCTAGAAATGGGCTGT".to_string(),
+                "TATGGTTCGTCATGTTCTAGAAATGGGCTGTTTT".to_string(),
+                "GTATGGTACGTCATGTTCTAGAAATGGGCTGT".to_string(),
+            ],
+        };
+        let params = AnalysisParams {
+            min_oligo_length: 10,
+            max_oligo_length: 10,
+            ..Default::default()
+        };
+
+        let results = run_screening(&template, &references, &params, None);
+        let screened = &results.results_by_length[&10].positions[3].analysis;
+        let single = analyze_single_window(&template, &references, &params, 3, 10);
+
+        assert_eq!(single.variants_for_threshold, screened.variants_for_threshold);
+        assert_eq!(single.no_match_count, screened.no_match_count);
+        assert_eq!(single.variants.len(), screened.variants.len());
+
+        let past_end = analyze_single_window(&template, &references, &params, 25, 10);
+        assert!(past_end.skipped);
+    }
+
+    #[test]
+    fn test_incremental_rescreen_equals_full_run() {
+        let template = TemplateData {
+            name: "Template".to_string(),
+            sequence: "TATGGTACGTCATGTTCTAGAAATGGGCTGT".to_string(),
+            masked: Vec::new(),
+        };
+        let all_sequences = [
+            "TATGGTACGTCATGTTCTAGAAATGGGCTGT",
+            "AATATGGTACGTCATGTTCTAGAAATGGGCTGT",
+            "TATGGTTCGTCATGTTCTAGAAATGGGCTGTTTT",
+            "GTATGGTACGTCATGTTCTAGAAATGGGCTGT",
+            "TATGGTACGTCATGATCTAGAAATGGCCTGT",
+            "CCCCCCCCCCCCATGTTCTAGAAATGGGCTGT",
+        ];
+        let refs = |seqs: &[&str]| ReferenceData {
+            names: (0..seqs.len()).map(|i| format!("Ref{}", i)).collect(),
+            sequences: seqs.iter().map(|s| s.to_string()).collect(),
+        };
+
+        for method in [
+            AnalysisMethod::NoAmbiguities,
+            AnalysisMethod::FixedAmbiguities(1),
+            AnalysisMethod::Incremental(50, Some(2)),
+        ] {
+            let params = AnalysisParams {
+                method,
+                min_oligo_length: 8,
+                max_oligo_length: 10,
+                pairwise: crate::analysis::types::PairwiseParams {
+                    max_mismatches: 2,
+                    ..Default::default()
+                },
+                store_match_data: true,
+                ..Default::default()
+            };
+
+            let full = run_screening(&template, &refs(&all_sequences), &params, None);
+            let previous = run_screening(&template, &refs(&all_sequences[..4]), &params, None);
+            let incremental =
+                rescreen_with_new_references(&previous, &refs(&all_sequences[4..]), None).unwrap();
+
+            assert_eq!(incremental.total_sequences, full.total_sequences);
+            for (length, full_lr) in &full.results_by_length {
+                let inc_lr = &incremental.results_by_length[length];
+                assert_eq!(inc_lr.positions.len(), full_lr.positions.len());
+                assert_eq!(inc_lr.column_profile, full_lr.column_profile);
+                for (f, i) in full_lr.positions.iter().zip(&inc_lr.positions) {
+                    assert_eq!(f.position, i.position);
+                    assert_eq!(f.variants_needed, i.variants_needed);
+                    assert_eq!(f.analysis.no_match_count, i.analysis.no_match_count);
+                    assert_eq!(f.analysis.sequences_analyzed, i.analysis.sequences_analyzed);
+                    assert_eq!(f.analysis.skipped, i.analysis.skipped);
+                    assert_eq!(f.analysis.coverage_at_threshold, i.analysis.coverage_at_threshold);
+                    assert_eq!(f.analysis.matches, i.analysis.matches);
+                    assert_eq!(f.analysis.mean_mismatches, i.analysis.mean_mismatches);
+                    let seqs = |r: &PositionResult| {
+                        r.analysis
+                            .variants
+                            .iter()
+                            .map(|v| (v.sequence.clone(), v.count))
+                            .collect::<Vec<_>>()
+                    };
+                    assert_eq!(seqs(f), seqs(i));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_incremental_rescreen_keeps_masked_and_ambiguous_windows() {
+        let template = TemplateData {
+            name: "Template".to_string(),
+            sequence: "TATGGTACGTCATGTTCTAGAARTGGGCTGT".to_string(),
+            masked: vec![(4, 9)],
+        };
+        let sequences = [
+            "TATGGTACGTCATGTTCTAGAAATGGGCTGT",
+            "TATGGTTCGTCATGTTCTAGAAGTGGGCTGT",
+            "GTATGGTACGTCATGTTCTAGAAATGGGCTGT",
+            "TATGGTACGTCATGATCTAGAAATGGCCTGT",
+        ];
+        let refs = |seqs: &[&str]| ReferenceData {
+            names: (0..seqs.len()).map(|i| format!("Ref{}", i)).collect(),
+            sequences: seqs.iter().map(|s| s.to_string()).collect(),
+        };
+        let mut params = AnalysisParams {
+            min_oligo_length: 8,
+            max_oligo_length: 8,
+            exclude_soft_masked: true,
+            store_match_data: true,
+            ..Default::default()
+        };
+
+        let full = run_screening(&template, &refs(&sequences), &params, None);
+        let previous = run_screening(&template, &refs(&sequences[..2]), &params, None);
+        let incremental =
+            rescreen_with_new_references(&previous, &refs(&sequences[2..]), None).unwrap();
+        assert_eq!(incremental.template_masked, full.template_masked);
+        assert_eq!(incremental.template_sequence, full.template_sequence);
+        let summary = |r: &ScreeningResults| {
+            r.results_by_length[&8]
+                .positions
+                .iter()
+                .map(|p| {
+                    (
+                        p.position,
+                        p.variants_needed,
+                        p.analysis.skip_reason.clone(),
+                        p.analysis.matches.clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summary(&incremental), summary(&full));
+        assert!(summary(&full).iter().any(|p| p.2.is_some()));
+
+        // A template resolved from the previous references is not extended
+        params.template_ambiguity = TemplateAmbiguity::MajorityReferenceBase;
+        let previous = run_screening(&template, &refs(&sequences[..2]), &params, None);
+        assert!(rescreen_with_new_references(&previous, &refs(&sequences[2..]), None).is_err());
+    }
+
+    #[test]
+    fn test_incremental_rescreen_requires_match_data() {
+        let template = TemplateData {
+            name: "Template".to_string(),
+            sequence: "TATGGTACGTCATGTT".to_string(),
+            masked: Vec::new(),
+        };
+        let references = ReferenceData {
+            names: vec!["Ref1".to_string()],
+            sequences: vec!["TATGGTACGTCATGTT".to_string()],
+        };
+        let params = AnalysisParams {
+            min_oligo_length: 10,
+            max_oligo_length: 10,
+            store_match_data: false,
+            ..Default::default()
+        };
+        let previous = run_screening(&template, &references, &params, None);
+        assert!(rescreen_with_new_references(&previous, &references, None).is_err());
     }
 }
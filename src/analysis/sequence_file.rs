@@ -0,0 +1,423 @@
+//! Streaming FASTA/FASTQ reader for plain and compressed files
+//!
+//! Files are read line by line through a decompressor picked from the magic
+//! bytes (gzip, including BGZF, or zstd), so multi-GB inputs are never held
+//! as text. Sequence characters are uppercased, `.` is read as a gap, and
+//! whitespace and digits are ignored; any other character is reported per
+//! record instead of being dropped silently. FASTQ records (consensus reads)
+//! are accepted on request and can be filtered by mean Phred quality. Pasted
+//! reference and background text goes through the same reader
+//! (`read_sequence_text`).
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use flate2::read::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+
+use super::fasta::ReferenceData;
+use super::iupac::{is_ambiguous_base, is_gap, is_standard_base};
+
+/// Compression of a sequence file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// gzip or BGZF (block gzip)
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::None => "uncompressed",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+/// Record format of a sequence file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceFormat {
+    Fasta,
+    Fastq,
+}
+
+/// Settings for reading reference files
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReadOptions {
+    /// Accept FASTQ records (e.g. consensus reads)
+    pub accept_fastq: bool,
+    /// FASTQ records below this mean Phred quality are skipped (0 = keep all)
+    pub min_mean_quality: u8,
+    /// Skip records containing invalid characters instead of removing the
+    /// characters
+    pub skip_invalid_records: bool,
+}
+
+/// Invalid characters found in one record
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidCharacters {
+    /// 0-based record index in the file
+    pub record: usize,
+    pub name: String,
+    pub count: usize,
+    pub first: char,
+    /// 1-based sequence position the first invalid character preceded
+    pub position: usize,
+}
+
+/// What happened while reading a sequence file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadSummary {
+    pub format: SequenceFormat,
+    pub records: usize,
+    pub empty_records: usize,
+    pub low_quality_records: usize,
+    pub invalid: Vec<InvalidCharacters>,
+    pub invalid_records_skipped: usize,
+}
+
+impl ReadSummary {
+    /// One-line description of the skipped records and invalid characters
+    pub fn notes(&self) -> Option<String> {
+        let mut notes = Vec::new();
+        if self.empty_records > 0 {
+            notes.push(format!("{} empty records skipped", self.empty_records));
+        }
+        if self.low_quality_records > 0 {
+            notes.push(format!(
+                "{} low-quality FASTQ records skipped",
+                self.low_quality_records
+            ));
+        }
+        if !self.invalid.is_empty() {
+            let action = if self.invalid_records_skipped > 0 {
+                "skipped"
+            } else {
+                "characters removed"
+            };
+            notes.push(format!(
+                "{} records with invalid characters ({})",
+                self.invalid.len(),
+                action
+            ));
+        }
+        (!notes.is_empty()).then(|| notes.join(", "))
+    }
+}
+
+/// Counts the bytes read from the underlying file, for progress display
+struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// Open a possibly compressed file as a buffered reader. `bytes_read`, if
+/// given, counts the (compressed) bytes read from disk.
+pub fn open_sequence_file(
+    path: &Path,
+    bytes_read: Option<Arc<AtomicU64>>,
+) -> Result<(Box<dyn BufRead + Send>, Compression), String> {
+    let open_err = |e: std::io::Error| format!("Failed to open {}: {}", path.display(), e);
+    let mut magic = [0u8; 4];
+    let mut probe = File::open(path).map_err(open_err)?;
+    let n = probe.read(&mut magic).map_err(open_err)?;
+    let compression = match &magic[..n] {
+        [0x1f, 0x8b, ..] => Compression::Gzip,
+        [0x28, 0xb5, 0x2f, 0xfd] => Compression::Zstd,
+        _ => Compression::None,
+    };
+
+    let file = File::open(path).map_err(open_err)?;
+    let raw = CountingReader {
+        inner: file,
+        count: bytes_read.unwrap_or_default(),
+    };
+    let reader: Box<dyn BufRead + Send> = match compression {
+        Compression::None => Box::new(BufReader::with_capacity(1 << 20, raw)),
+        Compression::Gzip => Box::new(BufReader::with_capacity(
+            1 << 20,
+            MultiGzDecoder::new(BufReader::new(raw)),
+        )),
+        Compression::Zstd => Box::new(BufReader::with_capacity(
+            1 << 20,
+            zstd::stream::read::Decoder::new(raw).map_err(open_err)?,
+        )),
+    };
+    Ok((reader, compression))
+}
+
+/// Read a whole (possibly compressed) text file, e.g. a template FASTA
+pub fn read_text_file(path: &Path) -> Result<String, String> {
+    let (mut reader, _) = open_sequence_file(path, None)?;
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(text)
+}
+
+/// Record being assembled
+struct Record {
+    name: String,
+    sequence: String,
+    invalid: Option<InvalidCharacters>,
+}
+
+impl Record {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            sequence: String::new(),
+            invalid: None,
+        }
+    }
+
+    fn push_line(&mut self, line: &[u8], index: usize) {
+        for raw in String::from_utf8_lossy(line).chars() {
+            let c = raw.to_ascii_uppercase();
+            if is_standard_base(c) || is_ambiguous_base(c) || is_gap(c) {
+                self.sequence.push(if c == '.' { '-' } else { c });
+            } else if !(raw.is_whitespace() || raw.is_ascii_digit()) {
+                let position = self.sequence.len() + 1;
+                let invalid = self.invalid.get_or_insert_with(|| InvalidCharacters {
+                    record: index,
+                    name: self.name.clone(),
+                    count: 0,
+                    first: raw,
+                    position,
+                });
+                invalid.count += 1;
+            }
+        }
+    }
+}
+
+/// Read all FASTA or FASTQ records from `reader`
+pub fn read_sequences(
+    reader: impl BufRead,
+    options: &ReadOptions,
+) -> Result<(ReferenceData, ReadSummary), String> {
+    let mut lines = reader.split(b'\n');
+    let mut next_line = || -> Result<Option<Vec<u8>>, String> {
+        match lines.next() {
+            Some(Ok(mut line)) => {
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                Ok(Some(line))
+            }
+            Some(Err(e)) => Err(format!("Failed to read sequences: {}", e)),
+            None => Ok(None),
+        }
+    };
+
+    let mut data = ReferenceData::new();
+    let mut summary = ReadSummary {
+        format: SequenceFormat::Fasta,
+        records: 0,
+        empty_records: 0,
+        low_quality_records: 0,
+        invalid: Vec::new(),
+        invalid_records_skipped: 0,
+    };
+    let mut current: Option<Record> = None;
+
+    let finish =
+        |record: Record, quality_ok: bool, data: &mut ReferenceData, summary: &mut ReadSummary| {
+            summary.records += 1;
+            if let Some(invalid) = record.invalid {
+                summary.invalid.push(invalid);
+                if options.skip_invalid_records {
+                    summary.invalid_records_skipped += 1;
+                    return;
+                }
+            }
+            if record.sequence.is_empty() {
+                summary.empty_records += 1;
+            } else if !quality_ok {
+                summary.low_quality_records += 1;
+            } else {
+                data.names.push(record.name);
+                data.sequences.push(record.sequence);
+            }
+        };
+
+    let mut first = true;
+    while let Some(line) = next_line()? {
+        let trimmed = line.trim_ascii();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if first {
+            first = false;
+            match trimmed[0] {
+                b'>' => {}
+                b'@' if options.accept_fastq => summary.format = SequenceFormat::Fastq,
+                b'@' => return Err("The file is FASTQ; enable FASTQ input to read it".to_string()),
+                _ => return Err("Not a FASTA or FASTQ file (no '>' or '@' header)".to_string()),
+            }
+        }
+
+        match summary.format {
+            SequenceFormat::Fasta => {
+                if let Some(name) = trimmed.strip_prefix(b">") {
+                    if let Some(record) = current.take() {
+                        finish(record, true, &mut data, &mut summary);
+                    }
+                    current = Some(Record::new(String::from_utf8_lossy(name).into_owned()));
+                } else if let Some(record) = current.as_mut() {
+                    record.push_line(trimmed, summary.records);
+                }
+            }
+            SequenceFormat::Fastq => {
+                let Some(name) = trimmed.strip_prefix(b"@") else {
+                    return Err(format!(
+                        "FASTQ record {} does not start with '@'",
+                        summary.records + 1
+                    ));
+                };
+                let mut record = Record::new(String::from_utf8_lossy(name).into_owned());
+                let sequence = next_line()?.unwrap_or_default();
+                let separator = next_line()?.unwrap_or_default();
+                let quality = next_line()?.unwrap_or_default();
+                if !separator.starts_with(b"+") {
+                    return Err(format!(
+                        "FASTQ record {} ({}) has no '+' line",
+                        summary.records + 1,
+                        record.name
+                    ));
+                }
+                record.push_line(sequence.trim_ascii(), summary.records);
+                let quality = quality.trim_ascii();
+                let mean_quality = if quality.is_empty() {
+                    0.0
+                } else {
+                    quality
+                        .iter()
+                        .map(|&q| q.saturating_sub(33) as f64)
+                        .sum::<f64>()
+                        / quality.len() as f64
+                };
+                let quality_ok = mean_quality >= options.min_mean_quality as f64;
+                finish(record, quality_ok, &mut data, &mut summary);
+            }
+        }
+    }
+    if let Some(record) = current.take() {
+        finish(record, true, &mut data, &mut summary);
+    }
+
+    if data.is_empty() {
+        return Err("No valid sequences found in reference input".to_string());
+    }
+    Ok((data, summary))
+}
+
+/// Read pasted sequence text. Text starting with a FASTA or FASTQ header is
+/// read like a file; otherwise each non-empty line is one sequence, named
+/// `Sequence_<line number>`.
+pub fn read_sequence_text(
+    text: &str,
+    options: &ReadOptions,
+) -> Result<(ReferenceData, ReadSummary), String> {
+    let text = text.trim_start();
+    if text.starts_with('>') || text.starts_with('@') {
+        return read_sequences(text.as_bytes(), options);
+    }
+
+    let fasta: String = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| format!(">Sequence_{}\n{}\n", i + 1, line))
+        .collect();
+    read_sequences(fasta.as_bytes(), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_fasta_reports_invalid_characters() {
+        let text = ">r1 first\nACGT acgt\n>empty\n>r2\nAC*G.T\r\nNN9#\n";
+        let (data, summary) = read_sequences(text.as_bytes(), &ReadOptions::default()).unwrap();
+        assert_eq!(data.names, vec!["r1 first", "r2"]);
+        assert_eq!(data.sequences, vec!["ACGTACGT", "ACG-TNN"]);
+        assert_eq!(summary.records, 3);
+        assert_eq!(summary.empty_records, 1);
+        assert_eq!(
+            summary.invalid,
+            vec![InvalidCharacters {
+                record: 2,
+                name: "r2".to_string(),
+                count: 2,
+                first: '*',
+                position: 3,
+            }]
+        );
+
+        let skip = ReadOptions {
+            skip_invalid_records: true,
+            ..Default::default()
+        };
+        let (data, summary) = read_sequences(text.as_bytes(), &skip).unwrap();
+        assert_eq!(data.names, vec!["r1 first"]);
+        assert_eq!(summary.invalid_records_skipped, 1);
+
+        // Bare lines are one sequence each; non-ASCII is reported as decoded
+        let (data, summary) = read_sequence_text("ACGT\n\nAC\u{3bc}GT\n", &skip).unwrap();
+        assert_eq!(data.names, vec!["Sequence_1"]);
+        assert_eq!(summary.invalid[0].name, "Sequence_3");
+        assert_eq!(summary.invalid[0].first, '\u{3bc}');
+    }
+
+    #[test]
+    fn test_compressed_fastq_with_quality_filter() {
+        let fastq = "@read1\nACGTACGT\n+\nIIIIIIII\n@read2\nACGTTT\n+read2\n!!!!!!\n";
+        assert!(read_sequences(fastq.as_bytes(), &ReadOptions::default()).is_err());
+
+        let dir = std::env::temp_dir();
+        let gz_path = dir.join(format!("oligoscreen_test_{}.fq.gz", std::process::id()));
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&gz_path).unwrap(), Default::default());
+        encoder.write_all(fastq.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+        let zst_path = dir.join(format!("oligoscreen_test_{}.fq.zst", std::process::id()));
+        std::fs::write(&zst_path, zstd::encode_all(fastq.as_bytes(), 3).unwrap()).unwrap();
+
+        let options = ReadOptions {
+            accept_fastq: true,
+            min_mean_quality: 20,
+            ..Default::default()
+        };
+        for (path, expected) in [
+            (&gz_path, Compression::Gzip),
+            (&zst_path, Compression::Zstd),
+        ] {
+            let (reader, compression) = open_sequence_file(path, None).unwrap();
+            assert_eq!(compression, expected);
+            let (data, summary) = read_sequences(reader, &options).unwrap();
+            assert_eq!(summary.format, SequenceFormat::Fastq);
+            assert_eq!(data.sequences, vec!["ACGTACGT"]);
+            assert_eq!(summary.low_quality_records, 1);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
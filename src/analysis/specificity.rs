@@ -0,0 +1,262 @@
+//! Off-target screening of window variants against a background set
+//!
+//! The variants each cell needs to reach the coverage threshold
+//! (`variants_needed`, most frequent first; rarer ones are not part of the
+//! oligo set) are aligned, on both strands, against each background
+//! ("exclusion") sequence with the degenerate-oligo aligner. As in
+//! the screen, only gap-free alignments of the whole variant within
+//! `max_mismatches` count as hits. A cell's specificity is its closest hit:
+//! the fewest mismatches of any of its variants to any background sequence.
+//! Variants shared by several cells are aligned once.
+
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+
+use super::fasta::ReferenceData;
+use super::iupac::reverse_complement;
+use super::pairwise::{align_degenerate_with_aligner, create_iupac_aligner};
+use super::types::{PairwiseParams, PositionResult, ScreeningResults, ThreadCount, Variant};
+
+/// Settings for a specificity screen
+#[derive(Debug, Clone)]
+pub struct SpecificityParams {
+    pub pairwise: PairwiseParams,
+    /// Cells needing more variants than this are not checked
+    pub max_cell_variants: usize,
+    pub thread_count: ThreadCount,
+}
+
+impl Default for SpecificityParams {
+    fn default() -> Self {
+        Self {
+            pairwise: PairwiseParams::default(),
+            max_cell_variants: 5,
+            thread_count: ThreadCount::Auto,
+        }
+    }
+}
+
+/// Closest background hit of a variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffTargetHit {
+    /// Index into the background set
+    pub background: usize,
+    pub mismatches: usize,
+    pub reverse_strand: bool,
+}
+
+/// Closest background hit of a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellSpecificity {
+    /// `None` when no variant has a background hit within the mismatch limit
+    pub closest: Option<OffTargetHit>,
+    /// Index of the variant with the closest hit
+    pub variant: usize,
+}
+
+/// Specificity of every checked cell, keyed by (oligo length, position)
+pub type SpecificityMap = HashMap<(u32, usize), CellSpecificity>;
+
+/// Variants of a cell up to its coverage threshold, most frequent first
+fn needed(p: &PositionResult) -> impl Iterator<Item = &Variant> {
+    p.analysis.variants.iter().take(p.variants_needed)
+}
+
+/// Check the needed variants of all non-skipped cells needing at most
+/// `max_cell_variants` variants. Sends (sequences done, total) while running.
+pub fn screen_specificity(
+    results: &ScreeningResults,
+    background: &ReferenceData,
+    params: &SpecificityParams,
+    progress_tx: Option<Sender<(usize, usize)>>,
+) -> SpecificityMap {
+    let eligible = |p: &&PositionResult| {
+        !p.analysis.skipped && p.variants_needed <= params.max_cell_variants
+    };
+
+    let mut unique: Vec<&str> = results
+        .results_by_length
+        .values()
+        .flat_map(|lr| lr.positions.iter().filter(eligible))
+        .flat_map(|p| needed(p).map(|v| v.sequence.as_str()))
+        .collect();
+    unique.sort_unstable();
+    unique.dedup();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(params.thread_count.get_count())
+        .build()
+        .unwrap_or_else(|_| rayon::ThreadPoolBuilder::new().build().unwrap());
+    let background: Vec<&[u8]> = background.sequences.iter().map(|s| s.as_bytes()).collect();
+    let max_background_len = background.iter().map(|b| b.len()).max().unwrap_or(0);
+    let max_variant_len = unique.iter().map(|v| v.len()).max().unwrap_or(0);
+    let completed = AtomicUsize::new(0);
+    let total = unique.len();
+
+    let closest: HashMap<&str, Option<OffTargetHit>> = pool.install(|| {
+        unique
+            .par_iter()
+            .map_init(
+                || create_iupac_aligner(max_variant_len, max_background_len, &params.pairwise),
+                |aligner, &variant| {
+                    let reverse = reverse_complement(variant);
+                    let mut best: Option<OffTargetHit> = None;
+                    for (index, sequence) in background.iter().enumerate() {
+                        for (reverse_strand, query) in [(false, variant), (true, reverse.as_str())] {
+                            let (result, _) =
+                                align_degenerate_with_aligner(aligner, query.as_bytes(), sequence);
+                            if result.has_gaps
+                                || !result.full_coverage
+                                || result.mismatches > params.pairwise.max_mismatches as usize
+                            {
+                                continue;
+                            }
+                            if best.is_none_or(|b| result.mismatches < b.mismatches) {
+                                best = Some(OffTargetHit {
+                                    background: index,
+                                    mismatches: result.mismatches,
+                                    reverse_strand,
+                                });
+                            }
+                        }
+                    }
+
+                    let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                    if let Some(tx) = &progress_tx {
+                        if done.is_multiple_of(10) || done == total {
+                            let _ = tx.send((done, total));
+                        }
+                    }
+                    (variant, best)
+                },
+            )
+            .collect()
+    });
+
+    let mut map = SpecificityMap::new();
+    for (&length, lr) in &results.results_by_length {
+        for p in lr.positions.iter().filter(eligible) {
+            let mut cell = CellSpecificity {
+                closest: None,
+                variant: 0,
+            };
+            for (i, v) in needed(p).enumerate() {
+                if let Some(hit) = closest[v.sequence.as_str()] {
+                    if cell.closest.is_none_or(|c| hit.mismatches < c.mismatches) {
+                        cell = CellSpecificity {
+                            closest: Some(hit),
+                            variant: i,
+                        };
+                    }
+                }
+            }
+            map.insert((length, p.position), cell);
+        }
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::types::{
+        AnalysisParams, LengthResult, PositionResult, Variant, WindowAnalysisResult,
+    };
+
+    fn cell(position: usize, variants: &[&str]) -> PositionResult {
+        PositionResult {
+            position,
+            variants_needed: variants.len(),
+            source: None,
+            analysis: WindowAnalysisResult {
+                variants: variants
+                    .iter()
+                    .map(|s| Variant {
+                        sequence: s.to_string(),
+                        count: 1,
+                        percentage: 50.0,
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_closest_background_hit_per_cell() {
+        let mut results =
+            ScreeningResults::new(AnalysisParams::default(), 30, 2, "A".repeat(30));
+        results.results_by_length.insert(
+            10,
+            LengthResult {
+                oligo_length: 10,
+                positions: vec![
+                    // Second variant occurs (reverse complemented) with 1 mismatch
+                    cell(0, &["CCCCCCCCCC", "TATGGTACGT"]),
+                    cell(1, &["GAGAGAGAGA"]),
+                    // Too many variants: not checked
+                    cell(2, &["TATGGTACGT"; 6]),
+                ],
+                column_profile: Vec::new(),
+            },
+        );
+        let background = ReferenceData {
+            names: vec!["neighbor".to_string()],
+            // Reverse complement of TATGGTTCGT
+            sequences: vec!["TTTTACGAACCATATTTT".to_string()],
+        };
+        let params = SpecificityParams {
+            pairwise: PairwiseParams {
+                max_mismatches: 2,
+                ..Default::default()
+            },
+            thread_count: ThreadCount::Fixed(1),
+            ..Default::default()
+        };
+
+        let map = screen_specificity(&results, &background, &params, None);
+        assert_eq!(map.len(), 2);
+        let first = map[&(10, 0)];
+        assert_eq!(first.variant, 1);
+        assert_eq!(
+            first.closest,
+            Some(OffTargetHit {
+                background: 0,
+                mismatches: 1,
+                reverse_strand: true
+            })
+        );
+        assert_eq!(map[&(10, 1)].closest, None);
+        assert!(!map.contains_key(&(10, 2)));
+    }
+
+    #[test]
+    fn test_ignores_variants_beyond_those_needed() {
+        let mut results =
+            ScreeningResults::new(AnalysisParams::default(), 30, 2, "A".repeat(30));
+        // The rare second variant is below the coverage threshold
+        let mut rare_hit = cell(0, &["CCCCCCCCCC", "TATGGTACGT"]);
+        rare_hit.variants_needed = 1;
+        results.results_by_length.insert(
+            10,
+            LengthResult {
+                oligo_length: 10,
+                positions: vec![rare_hit],
+                column_profile: Vec::new(),
+            },
+        );
+        let background = ReferenceData {
+            names: vec!["neighbor".to_string()],
+            sequences: vec!["TTTTATGGTACGTTTTT".to_string()],
+        };
+        let params = SpecificityParams {
+            thread_count: ThreadCount::Fixed(1),
+            ..Default::default()
+        };
+
+        let map = screen_specificity(&results, &background, &params, None);
+        assert_eq!(map[&(10, 0)].closest, None);
+    }
+}
@@ -0,0 +1,222 @@
+//! Ambiguous and soft-masked template positions
+//!
+//! Windows overlapping an ambiguity code in the template are skipped, or the
+//! codes are first resolved to the reference majority: each run of ambiguous
+//! bases is aligned with `RESOLVE_FLANK` template bases on either side
+//! (degenerate semi-global alignment, as in the oligo check) against every
+//! reference, and each run position takes the most frequent reference base
+//! compatible with its code. Runs longer than `MAX_RESOLVED_RUN` and
+//! positions without a compatible reference base stay ambiguous. Windows
+//! overlapping soft-masked (lowercase) template regions can be skipped too.
+
+use rayon::prelude::*;
+
+use super::fasta::{ReferenceData, TemplateData};
+use super::iupac::{base_to_bit, iupac_to_mask, reverse_complement};
+use super::pairwise::{align_degenerate_with_aligner, create_iupac_aligner};
+use super::types::{AnalysisParams, PairwiseParams};
+
+/// Template bases aligned on each side of an ambiguous run
+pub const RESOLVE_FLANK: usize = 12;
+
+/// Longest run of ambiguous template bases that is resolved
+pub const MAX_RESOLVED_RUN: usize = 50;
+
+fn is_ambiguous(b: u8) -> bool {
+    !matches!(b, b'A' | b'C' | b'G' | b'T')
+}
+
+/// Reason to skip the window at `position`, or `None` if it can be screened
+pub fn template_window_issue(
+    template: &TemplateData,
+    params: &AnalysisParams,
+    position: usize,
+    length: usize,
+) -> Option<String> {
+    let end = position + length;
+    if params.exclude_soft_masked {
+        if let Some(&(start, stop)) = template
+            .masked
+            .iter()
+            .find(|&&(start, stop)| start < end && position < stop)
+        {
+            return Some(format!(
+                "Window overlaps soft-masked template region {}-{}",
+                start + 1,
+                stop
+            ));
+        }
+    }
+    let window = template.sequence.as_bytes().get(position..end)?;
+    window.iter().position(|&b| is_ambiguous(b)).map(|i| {
+        format!(
+            "Window overlaps ambiguous template base '{}' at position {}",
+            window[i] as char,
+            position + i + 1
+        )
+    })
+}
+
+/// Runs of ambiguous bases as 0-based half-open ranges
+fn ambiguous_runs(sequence: &[u8]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, &b) in sequence.iter().enumerate() {
+        if !is_ambiguous(b) {
+            continue;
+        }
+        match runs.last_mut() {
+            Some(last) if last.1 == i => last.1 += 1,
+            _ => runs.push((i, i + 1)),
+        }
+    }
+    runs
+}
+
+/// Template with its ambiguity codes replaced by the reference majority
+/// where possible. Also returns the number of resolved and of remaining
+/// ambiguous bases.
+pub fn resolve_template_ambiguities(
+    template: &TemplateData,
+    references: &ReferenceData,
+    pairwise: &PairwiseParams,
+) -> (TemplateData, usize, usize) {
+    let sequence = template.sequence.as_bytes();
+    let runs: Vec<(usize, usize)> = ambiguous_runs(sequence)
+        .into_iter()
+        .filter(|(start, end)| end - start <= MAX_RESOLVED_RUN)
+        .collect();
+    let max_ref_len = references
+        .sequences
+        .iter()
+        .map(|s| s.len())
+        .max()
+        .unwrap_or(0);
+
+    let resolved: Vec<(usize, Vec<u8>)> = runs
+        .par_iter()
+        .map_init(
+            || create_iupac_aligner(MAX_RESOLVED_RUN + 2 * RESOLVE_FLANK, max_ref_len, pairwise),
+            |aligner, &(start, end)| {
+                let query_start = start.saturating_sub(RESOLVE_FLANK);
+                let query_end = (end + RESOLVE_FLANK).min(sequence.len());
+                let query = &sequence[query_start..query_end];
+                let reverse = reverse_complement(&String::from_utf8_lossy(query));
+                // Base counts (A, C, G, T) per run position
+                let mut counts = vec![[0u32; 4]; end - start];
+                for reference in &references.sequences {
+                    for (reverse_strand, q) in [(false, query), (true, reverse.as_bytes())] {
+                        let (m, _) =
+                            align_degenerate_with_aligner(aligner, q, reference.as_bytes());
+                        if m.has_gaps
+                            || !m.full_coverage
+                            || m.mismatches > pairwise.max_mismatches as usize
+                        {
+                            continue;
+                        }
+                        let matched = if reverse_strand {
+                            reverse_complement(&m.matched_sequence)
+                        } else {
+                            m.matched_sequence
+                        };
+                        let run = &matched.as_bytes()[start - query_start..end - query_start];
+                        for ((c, &base), &code) in
+                            counts.iter_mut().zip(run).zip(&sequence[start..end])
+                        {
+                            if let Some(i) = b"ACGT".iter().position(|&b| b == base) {
+                                if iupac_to_mask(code) & base_to_bit(base) != 0 {
+                                    c[i] += 1;
+                                }
+                            }
+                        }
+                        break;
+                    }
+                }
+                let bases = counts
+                    .iter()
+                    .zip(&sequence[start..end])
+                    .map(|(c, &code)| {
+                        let (best, &n) = c
+                            .iter()
+                            .enumerate()
+                            .max_by_key(|&(i, n)| (n, std::cmp::Reverse(i)))
+                            .unwrap();
+                        if n > 0 {
+                            b"ACGT"[best]
+                        } else {
+                            code
+                        }
+                    })
+                    .collect();
+                (start, bases)
+            },
+        )
+        .collect();
+
+    let mut bytes = sequence.to_vec();
+    for (start, bases) in resolved {
+        bytes[start..start + bases.len()].copy_from_slice(&bases);
+    }
+    let remaining = bytes.iter().filter(|&&b| is_ambiguous(b)).count();
+    let resolved = sequence.iter().filter(|&&b| is_ambiguous(b)).count() - remaining;
+    (
+        TemplateData {
+            name: template.name.clone(),
+            sequence: String::from_utf8_lossy(&bytes).into_owned(),
+            masked: template.masked.clone(),
+        },
+        resolved,
+        remaining,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(sequence: &str, masked: Vec<(usize, usize)>) -> TemplateData {
+        TemplateData {
+            name: "Template".to_string(),
+            sequence: sequence.to_string(),
+            masked,
+        }
+    }
+
+    #[test]
+    fn test_window_issues() {
+        let t = template("ACGTACGTNNACGTACGT", vec![(14, 18)]);
+        let mut params = AnalysisParams::default();
+        assert_eq!(template_window_issue(&t, &params, 0, 8), None);
+        assert_eq!(
+            template_window_issue(&t, &params, 4, 6).as_deref(),
+            Some("Window overlaps ambiguous template base 'N' at position 9")
+        );
+        assert_eq!(template_window_issue(&t, &params, 10, 8), None);
+        params.exclude_soft_masked = true;
+        assert_eq!(
+            template_window_issue(&t, &params, 10, 8).as_deref(),
+            Some("Window overlaps soft-masked template region 15-18")
+        );
+    }
+
+    #[test]
+    fn test_resolve_to_reference_majority() {
+        let left = "TATGGTACGTCATG";
+        let right = "TTCTAGAAATGGGCTGT";
+        // R = A/G: the T-carrying reference is not counted for it
+        let t = template(&format!("{}NR{}", left, right), Vec::new());
+        let references = ReferenceData {
+            names: (1..=4).map(|i| format!("r{}", i)).collect(),
+            sequences: vec![
+                format!("CC{}CA{}GG", left, right),
+                format!("{}CG{}", left, right),
+                format!("{}GT{}", left, right),
+                // Reverse strand copy
+                reverse_complement(&format!("{}CA{}", left, right)),
+            ],
+        };
+        let (resolved, count, remaining) =
+            resolve_template_ambiguities(&t, &references, &PairwiseParams::default());
+        assert_eq!(resolved.sequence, format!("{}CA{}", left, right));
+        assert_eq!((count, remaining), (2, 0));
+    }
+}
@@ -0,0 +1,314 @@
+//! Template derivation from the reference set
+//!
+//! Two ways to avoid picking one strain as the template by hand:
+//! - the medoid: the reference with the smallest summed k-mer (Jaccard)
+//!   distance to all other references;
+//! - a majority-rule consensus: starting from the medoid, the references are
+//!   aligned one by one (nearest first) to the consensus of a growing column
+//!   profile. Reference bases opposite no column open a new column. A column
+//!   is kept when fewer than half of the references have a gap there and
+//!   gets its most frequent base.
+//!
+//! Ambiguity codes in the references are not counted for any base, and
+//! alignment gaps in the input are removed first.
+
+use bio::alignment::pairwise::{Aligner, MatchParams};
+use bio::alignment::AlignmentOperation;
+use rayon::prelude::*;
+
+use super::fasta::{ReferenceData, TemplateData};
+use super::types::{PairwiseParams, TemplateOrigin};
+
+/// k-mer length of the medoid distance
+const KMER: usize = 8;
+
+/// Longest reference the consensus builder aligns (the alignment matrix is
+/// quadratic in the sequence length)
+pub const MAX_CONSENSUS_REFERENCE_LENGTH: usize = 20_000;
+
+/// Profile column counts: A, C, G, T, gap
+type Column = [u32; 5];
+const GAP: usize = 4;
+
+fn base_index(b: u8) -> Option<usize> {
+    match b {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
+    }
+}
+
+/// Reference bases without alignment gaps
+fn ungapped(seq: &str) -> Vec<u8> {
+    seq.bytes().filter(|&b| b != b'-' && b != b'.').collect()
+}
+
+/// Sorted distinct k-mers made only of A/C/G/T, 2 bits per base
+fn kmer_set(seq: &[u8]) -> Vec<u64> {
+    let mut kmers: Vec<u64> = seq
+        .windows(KMER)
+        .filter_map(|w| {
+            w.iter()
+                .try_fold(0u64, |acc, &b| base_index(b).map(|i| acc << 2 | i as u64))
+        })
+        .collect();
+    kmers.sort_unstable();
+    kmers.dedup();
+    kmers
+}
+
+fn jaccard_distance(a: &[u64], b: &[u64]) -> f64 {
+    let (mut i, mut j, mut shared) = (0, 0, 0usize);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    let union = a.len() + b.len() - shared;
+    if union == 0 {
+        1.0
+    } else {
+        1.0 - shared as f64 / union as f64
+    }
+}
+
+/// k-mer (Jaccard) distance of every sequence to every other, in 0..=1
+pub fn kmer_distance_matrix(sequences: &[Vec<u8>]) -> Vec<Vec<f64>> {
+    let sets: Vec<Vec<u64>> = sequences.iter().map(|s| kmer_set(s)).collect();
+    sets.par_iter()
+        .map(|a| sets.iter().map(|b| jaccard_distance(a, b)).collect())
+        .collect()
+}
+
+/// Index of the reference with the smallest summed distance among those
+/// accepted by `eligible`
+fn medoid_index(distances: &[Vec<f64>], eligible: impl Fn(usize) -> bool) -> Option<usize> {
+    (0..distances.len())
+        .filter(|&i| eligible(i))
+        .map(|i| (i, distances[i].iter().sum::<f64>()))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+/// Use the medoid reference as template. Only references made of A/C/G/T
+/// can be picked.
+pub fn medoid_template(
+    references: &ReferenceData,
+) -> Result<(TemplateData, TemplateOrigin), String> {
+    let sequences: Vec<Vec<u8>> = references.sequences.iter().map(|s| ungapped(s)).collect();
+    if sequences.is_empty() {
+        return Err("No references loaded".to_string());
+    }
+    let distances = kmer_distance_matrix(&sequences);
+    let index = medoid_index(&distances, |i| {
+        !sequences[i].is_empty() && sequences[i].iter().all(|&b| base_index(b).is_some())
+    })
+    .ok_or_else(|| {
+        "Every reference contains ambiguity codes; none can be the template".to_string()
+    })?;
+
+    let name = references.names[index].clone();
+    Ok((
+        TemplateData {
+            name: name.clone(),
+            sequence: String::from_utf8_lossy(&sequences[index]).into_owned(),
+            masked: Vec::new(),
+        },
+        TemplateOrigin::MedoidReference { name },
+    ))
+}
+
+/// Most frequent base of each column (ignoring gaps). With `members`,
+/// columns where at least half of the members have a gap are dropped;
+/// without, every column is kept (`N` where no base was counted) so the
+/// result lines up with the profile.
+fn profile_consensus(columns: &[Column], members: Option<u32>) -> Vec<u8> {
+    columns
+        .iter()
+        .filter_map(|c| {
+            if members.is_some_and(|m| c[GAP] * 2 >= m) {
+                return None;
+            }
+            let (best, count) =
+                c[..GAP].iter().enumerate().fold(
+                    (0, 0),
+                    |best, (i, &n)| if n > best.1 { (i, n) } else { best },
+                );
+            if count == 0 {
+                // Only gaps or ambiguity codes
+                return members.is_none().then_some(b'N');
+            }
+            Some(b"ACGT"[best])
+        })
+        .collect()
+}
+
+/// Majority-rule consensus of a progressive alignment of all references
+pub fn consensus_template(
+    references: &ReferenceData,
+    pairwise: &PairwiseParams,
+) -> Result<(TemplateData, TemplateOrigin), String> {
+    let sequences: Vec<Vec<u8>> = references.sequences.iter().map(|s| ungapped(s)).collect();
+    if sequences.is_empty() {
+        return Err("No references loaded".to_string());
+    }
+    if let Some(i) = sequences
+        .iter()
+        .position(|s| s.len() > MAX_CONSENSUS_REFERENCE_LENGTH)
+    {
+        return Err(format!(
+            "Reference {} is longer than {} bp; the consensus builder is meant for gene-sized references",
+            references.names[i], MAX_CONSENSUS_REFERENCE_LENGTH
+        ));
+    }
+
+    let distances = kmer_distance_matrix(&sequences);
+    let start = medoid_index(&distances, |i| !sequences[i].is_empty())
+        .ok_or_else(|| "All references are empty".to_string())?;
+    let mut order: Vec<usize> = (0..sequences.len())
+        .filter(|&i| i != start && !sequences[i].is_empty())
+        .collect();
+    order.sort_by(|&a, &b| distances[start][a].total_cmp(&distances[start][b]));
+
+    let mut columns: Vec<Column> = sequences[start]
+        .iter()
+        .map(|&b| {
+            let mut c = [0; 5];
+            if let Some(i) = base_index(b) {
+                c[i] = 1;
+            }
+            c
+        })
+        .collect();
+    let add = |c: &mut Column, b: u8| {
+        if let Some(i) = base_index(b) {
+            c[i] += 1;
+        }
+    };
+
+    let max_len = sequences.iter().map(|s| s.len()).max().unwrap_or(0);
+    let mut aligner = Aligner::with_capacity(
+        max_len,
+        max_len * 2,
+        pairwise.gap_open_penalty,
+        pairwise.gap_extend_penalty,
+        MatchParams::new(pairwise.match_score, pairwise.mismatch_score),
+    );
+    let mut members = 1;
+    for &r in &order {
+        let x = &sequences[r];
+        let y = profile_consensus(&columns, None);
+        // Whole reference against any part of the profile
+        let alignment = aligner.semiglobal(x, &y);
+
+        let mut merged = Vec::with_capacity(columns.len() + x.len());
+        let gap_column = |mut c: Column| {
+            c[GAP] += 1;
+            c
+        };
+        merged.extend(columns[..alignment.ystart].iter().copied().map(gap_column));
+        let (mut xi, mut yi) = (alignment.xstart, alignment.ystart);
+        for op in &alignment.operations {
+            match op {
+                AlignmentOperation::Match | AlignmentOperation::Subst => {
+                    let mut c = columns[yi];
+                    add(&mut c, x[xi]);
+                    merged.push(c);
+                    xi += 1;
+                    yi += 1;
+                }
+                AlignmentOperation::Del => {
+                    merged.push(gap_column(columns[yi]));
+                    yi += 1;
+                }
+                AlignmentOperation::Ins => {
+                    let mut c = [0, 0, 0, 0, members];
+                    add(&mut c, x[xi]);
+                    merged.push(c);
+                    xi += 1;
+                }
+                AlignmentOperation::Xclip(_) | AlignmentOperation::Yclip(_) => {}
+            }
+        }
+        merged.extend(columns[yi..].iter().copied().map(gap_column));
+        columns = merged;
+        members += 1;
+    }
+
+    let consensus = profile_consensus(&columns, Some(members));
+    if consensus.is_empty() {
+        return Err("The consensus is empty".to_string());
+    }
+    Ok((
+        TemplateData {
+            name: format!("consensus_of_{}_references", members),
+            sequence: String::from_utf8_lossy(&consensus).into_owned(),
+            masked: Vec::new(),
+        },
+        TemplateOrigin::MajorityConsensus {
+            references: members as usize,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "ACGTTGCAACGTAGCTAGGCTATTCGGATCCAAGTCTGACCTAGT";
+
+    fn references(sequences: &[String]) -> ReferenceData {
+        ReferenceData {
+            names: (1..=sequences.len()).map(|i| format!("r{}", i)).collect(),
+            sequences: sequences.to_vec(),
+        }
+    }
+
+    fn with_change(at: usize, base: &str) -> String {
+        format!("{}{}{}", &BASE[..at], base, &BASE[at + 1..])
+    }
+
+    #[test]
+    fn test_medoid_template() {
+        let refs = references(&[
+            with_change(5, "A"),
+            BASE.to_string(),
+            with_change(30, "T"),
+            format!("{}N", &BASE[..40]),
+        ]);
+        let (template, origin) = medoid_template(&refs).unwrap();
+        assert_eq!(template.sequence, BASE);
+        assert_eq!(
+            origin,
+            TemplateOrigin::MedoidReference {
+                name: "r2".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_majority_consensus_ignores_minority_changes() {
+        let insertion = format!("{}GG{}", &BASE[..20], &BASE[20..]);
+        let deletion = format!("{}{}", &BASE[..12], &BASE[13..]);
+        let partial = format!("-----{}", &BASE[5..35]);
+        let refs = references(&[
+            with_change(5, "A"),
+            insertion,
+            with_change(30, "T"),
+            deletion,
+            partial,
+            BASE.to_string(),
+        ]);
+        let (template, origin) = consensus_template(&refs, &PairwiseParams::default()).unwrap();
+        assert_eq!(template.sequence, BASE);
+        assert_eq!(origin, TemplateOrigin::MajorityConsensus { references: 6 });
+    }
+}
@@ -0,0 +1,73 @@
+//! Basic oligo properties: GC content and melting temperature estimates
+//!
+//! Ambiguity codes contribute fractionally: `S` counts as fully G/C, `N` as
+//! half, `R` as half, etc., so degenerate variants get their average value.
+
+use super::iupac::iupac_to_mask;
+
+/// Bitmask of G and C in the IUPAC mask layout (bit 1 = C, bit 2 = G)
+const GC_MASK: u8 = 0b0110;
+
+/// Expected G+C count of a sequence, with ambiguity codes weighted by the
+/// fraction of their bases that are G or C. Unknown characters count as 0.
+fn expected_gc_count(seq: &str) -> f64 {
+    seq.bytes()
+        .map(|b| {
+            let mask = iupac_to_mask(b);
+            if mask == 0 {
+                0.0
+            } else {
+                (mask & GC_MASK).count_ones() as f64 / mask.count_ones() as f64
+            }
+        })
+        .sum()
+}
+
+/// GC content in percent (0 for an empty sequence)
+pub fn gc_content(seq: &str) -> f64 {
+    if seq.is_empty() {
+        return 0.0;
+    }
+    expected_gc_count(seq) / seq.len() as f64 * 100.0
+}
+
+/// Melting temperature estimate in °C.
+///
+/// Uses the Wallace rule (2·AT + 4·GC) below 14 nt and the basic
+/// GC-content formula `64.9 + 41·(GC − 16.4) / N` from 14 nt on. This is a
+/// screening-level estimate, not a nearest-neighbor calculation.
+pub fn melting_temperature(seq: &str) -> f64 {
+    let n = seq.len() as f64;
+    if n == 0.0 {
+        return 0.0;
+    }
+    let gc = expected_gc_count(seq);
+    if seq.len() < 14 {
+        2.0 * (n - gc) + 4.0 * gc
+    } else {
+        64.9 + 41.0 * (gc - 16.4) / n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gc_content() {
+        assert_eq!(gc_content("GGCC"), 100.0);
+        assert_eq!(gc_content("ATGC"), 50.0);
+        assert_eq!(gc_content("SSWW"), 50.0);
+        assert_eq!(gc_content("NN"), 50.0);
+        assert_eq!(gc_content(""), 0.0);
+    }
+
+    #[test]
+    fn test_melting_temperature() {
+        // Wallace rule for short oligos
+        assert_eq!(melting_temperature("ATGC"), 12.0);
+        // 20-mer with 10 G/C
+        let tm = melting_temperature("ATGCATGCATGCATGCATGC");
+        assert!((tm - (64.9 + 41.0 * (10.0 - 16.4) / 20.0)).abs() < 1e-9);
+    }
+}
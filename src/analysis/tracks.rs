@@ -0,0 +1,100 @@
+//! Per-position signal tracks drawn under the heatmap
+//!
+//! Three continuous signals for one oligo length, aligned to a list of
+//! template positions (the heatmap columns):
+//! - Shannon entropy of the matched bases at the position,
+//! - fraction of references with an accepted match for the window starting
+//!   at the position,
+//! - mean mismatches of those matches against the template oligo.
+
+use super::types::ScreeningResults;
+
+/// Signal values per requested position (`None` where there is no data)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PositionTracks {
+    pub entropy: Vec<Option<f64>>,
+    pub matched_fraction: Vec<Option<f64>>,
+    pub mean_mismatches: Vec<Option<f64>>,
+}
+
+/// Build the tracks of `oligo_length` for the given template positions
+pub fn position_tracks(
+    results: &ScreeningResults,
+    oligo_length: u32,
+    positions: &[usize],
+) -> PositionTracks {
+    let Some(length_result) = results.results_by_length.get(&oligo_length) else {
+        return PositionTracks {
+            entropy: vec![None; positions.len()],
+            matched_fraction: vec![None; positions.len()],
+            mean_mismatches: vec![None; positions.len()],
+        };
+    };
+
+    let mut tracks = PositionTracks::default();
+    for &pos in positions {
+        tracks.entropy.push(
+            length_result
+                .column_profile
+                .get(pos)
+                .and_then(|c| c.entropy()),
+        );
+
+        let cell = length_result
+            .positions
+            .binary_search_by_key(&pos, |p| p.position)
+            .ok()
+            .map(|i| &length_result.positions[i].analysis);
+        tracks.matched_fraction.push(cell.and_then(|a| {
+            (a.total_sequences > 0)
+                .then(|| a.sequences_analyzed as f64 / a.total_sequences as f64)
+        }));
+        tracks
+            .mean_mismatches
+            .push(cell.and_then(|a| (a.sequences_analyzed > 0).then_some(a.mean_mismatches)));
+    }
+    tracks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::fasta::{ReferenceData, TemplateData};
+    use crate::analysis::screener::run_screening;
+    use crate::analysis::types::AnalysisParams;
+
+    #[test]
+    fn test_tracks_from_screen() {
+        let template = TemplateData {
+            name: "Template".to_string(),
+            sequence: "TATGGTACGTCATGTTCTAGAAATGGGCTGT".to_string(),
+            masked: Vec::new(),
+        };
+        let references = ReferenceData {
+            names: vec!["Ref1".to_string(), "Ref2".to_string()],
+            sequences: vec![
+                "TATGGTACGTCATGTTCTAGAAATGGGCTGT".to_string(),
+                "TATGGTTCGTCATGTTCTAGAAATGGGCTGT".to_string(),
+            ],
+        };
+        let params = AnalysisParams {
+            min_oligo_length: 10,
+            max_oligo_length: 10,
+            ..Default::default()
+        };
+        let results = run_screening(&template, &references, &params, None);
+
+        let positions: Vec<usize> = (0..template.sequence.len()).collect();
+        let tracks = position_tracks(&results, 10, &positions);
+
+        // Column 6 differs between the references (A vs T): close to 1 bit
+        assert!(tracks.entropy[6].unwrap() > 0.9);
+        assert_eq!(tracks.entropy[0], Some(0.0));
+        assert_eq!(tracks.matched_fraction[0], Some(1.0));
+        // One of two references has one mismatch in the first window
+        assert_eq!(tracks.mean_mismatches[0], Some(0.5));
+        // No window of length 10 starts at the last position
+        assert_eq!(tracks.matched_fraction[30], None);
+        assert_eq!(tracks.entropy.len(), positions.len());
+    }
+}
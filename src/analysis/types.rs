@@ -4,9 +4,10 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Analysis method selection
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum AnalysisMethod {
     /// Find all unique variants without using ambiguity codes
+    #[default]
     NoAmbiguities,
     /// Find minimum variants using up to N ambiguity codes per variant
     FixedAmbiguities(u32),
@@ -15,12 +16,6 @@ pub enum AnalysisMethod {
     Incremental(u32, Option<u32>),
 }
 
-impl Default for AnalysisMethod {
-    fn default() -> Self {
-        Self::NoAmbiguities
-    }
-}
-
 impl AnalysisMethod {
     pub fn description(&self) -> String {
         match self {
@@ -29,23 +24,42 @@ impl AnalysisMethod {
             Self::Incremental(pct, _) => format!("Incremental ({}% coverage per step)", pct),
         }
     }
+
+    /// Max ambiguities of the fixed method, or the UI default for other methods
+    pub fn get_fixed_ambiguities(&self) -> u32 {
+        match self {
+            AnalysisMethod::FixedAmbiguities(n) => *n,
+            _ => 1,
+        }
+    }
+
+    /// Per-step target percentage of the incremental method, or the UI default
+    pub fn get_incremental_pct(&self) -> u32 {
+        match self {
+            AnalysisMethod::Incremental(pct, _) => *pct,
+            _ => 50,
+        }
+    }
+
+    /// Optional ambiguity limit of the incremental method
+    pub fn get_incremental_max_amb(&self) -> Option<u32> {
+        match self {
+            AnalysisMethod::Incremental(_, max_amb) => *max_amb,
+            _ => None,
+        }
+    }
 }
 
 /// Thread count configuration
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum ThreadCount {
     /// Use all available CPU cores
+    #[default]
     Auto,
     /// Use a specific number of threads
     Fixed(usize),
 }
 
-impl Default for ThreadCount {
-    fn default() -> Self {
-        Self::Auto
-    }
-}
-
 impl ThreadCount {
     /// Get the actual number of threads to use
     pub fn get_count(&self) -> usize {
@@ -58,6 +72,26 @@ impl ThreadCount {
     }
 }
 
+/// Handling of ambiguity codes (e.g. `N` runs) in the template
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TemplateAmbiguity {
+    /// Skip windows overlapping an ambiguous template base
+    #[default]
+    SkipWindows,
+    /// Replace each ambiguous base by the most frequent compatible reference
+    /// base at that position before screening; unresolved bases are skipped
+    MajorityReferenceBase,
+}
+
+impl TemplateAmbiguity {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::SkipWindows => "Skip windows with ambiguous template bases",
+            Self::MajorityReferenceBase => "Resolve to the majority reference base",
+        }
+    }
+}
+
 /// Pairwise alignment parameters
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct PairwiseParams {
@@ -80,6 +114,54 @@ impl Default for PairwiseParams {
     }
 }
 
+/// A reference left out of the screening, identified by its name (FASTA
+/// header) and, among the references of that name, its ordinal (0 for the
+/// first one)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "ExcludedReferenceEntry")]
+pub struct ExcludedReference {
+    pub name: String,
+    /// `None` excludes every reference of the name (files that stored names
+    /// only)
+    pub ordinal: Option<usize>,
+}
+
+impl ExcludedReference {
+    pub fn new(name: &str, ordinal: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            ordinal: Some(ordinal),
+        }
+    }
+
+    pub fn matches(&self, name: &str, ordinal: usize) -> bool {
+        self.name == name && self.ordinal.is_none_or(|o| o == ordinal)
+    }
+}
+
+/// Stored form of `ExcludedReference`: a bare name in older files
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExcludedReferenceEntry {
+    Name(String),
+    Reference {
+        name: String,
+        ordinal: Option<usize>,
+    },
+}
+
+impl From<ExcludedReferenceEntry> for ExcludedReference {
+    fn from(entry: ExcludedReferenceEntry) -> Self {
+        match entry {
+            ExcludedReferenceEntry::Name(name) => Self {
+                name,
+                ordinal: None,
+            },
+            ExcludedReferenceEntry::Reference { name, ordinal } => Self { name, ordinal },
+        }
+    }
+}
+
 /// Global analysis parameters
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisParams {
@@ -91,6 +173,23 @@ pub struct AnalysisParams {
     pub resolution: u32,
     pub coverage_threshold: f64,
     pub thread_count: ThreadCount,
+    /// Keep per-reference match data (`WindowAnalysisResult::matches`) in the
+    /// results. Needed for incremental re-screening; costs memory and file size.
+    /// Files written before this option existed carry no match data.
+    #[serde(default)]
+    pub store_match_data: bool,
+    /// Representative references screened as additional templates and merged
+    /// into the template's coordinates (0 = template only)
+    #[serde(default)]
+    pub extra_templates: usize,
+    #[serde(default)]
+    pub template_ambiguity: TemplateAmbiguity,
+    /// Skip windows overlapping soft-masked (lowercase) template regions
+    #[serde(default)]
+    pub exclude_soft_masked: bool,
+    /// References left out of the screening
+    #[serde(default)]
+    pub excluded_references: Vec<ExcludedReference>,
 }
 
 impl Default for AnalysisParams {
@@ -104,6 +203,11 @@ impl Default for AnalysisParams {
             resolution: 1,
             coverage_threshold: 95.0,
             thread_count: ThreadCount::Auto,
+            store_match_data: false,
+            extra_templates: 0,
+            template_ambiguity: TemplateAmbiguity::SkipWindows,
+            exclude_soft_masked: false,
+            excluded_references: Vec::new(),
         }
     }
 }
@@ -116,6 +220,17 @@ pub struct Variant {
     pub percentage: f64,
 }
 
+/// References that matched a window with the same sequence
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchGroup {
+    /// Matched region in the references (gap-free, oligo length)
+    pub sequence: String,
+    /// Mismatches between the template oligo and `sequence`
+    pub mismatches: usize,
+    /// Indices into the reference set, ascending
+    pub references: Vec<usize>,
+}
+
 /// Result of analyzing a single window position
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindowAnalysisResult {
@@ -127,6 +242,13 @@ pub struct WindowAnalysisResult {
     pub coverage_at_threshold: f64,
     pub skipped: bool,
     pub skip_reason: Option<String>,
+    /// Matching references grouped by matched sequence, sorted by sequence.
+    /// Empty unless `AnalysisParams::store_match_data` was set.
+    #[serde(default)]
+    pub matches: Vec<MatchGroup>,
+    /// Mean mismatches of the matching references against the template oligo
+    #[serde(default)]
+    pub mean_mismatches: f64,
 }
 
 impl Default for WindowAnalysisResult {
@@ -140,7 +262,61 @@ impl Default for WindowAnalysisResult {
             coverage_at_threshold: 0.0,
             skipped: false,
             skip_reason: None,
+            matches: Vec::new(),
+            mean_mismatches: 0.0,
+        }
+    }
+}
+
+/// Base counts of one template column across matched reference sequences
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColumnComposition {
+    /// Counts of A, C, G, T
+    pub counts: [u32; 4],
+    /// Ambiguity codes, N and anything else
+    pub other: u32,
+}
+
+impl ColumnComposition {
+    pub fn add(&mut self, base: u8, n: u32) {
+        match base.to_ascii_uppercase() {
+            b'A' => self.counts[0] += n,
+            b'C' => self.counts[1] += n,
+            b'G' => self.counts[2] += n,
+            b'T' | b'U' => self.counts[3] += n,
+            _ => self.other += n,
+        }
+    }
+
+    pub fn merge(&mut self, other: &ColumnComposition) {
+        for (a, b) in self.counts.iter_mut().zip(other.counts) {
+            *a += b;
+        }
+        self.other += other.other;
+    }
+
+    pub fn total(&self) -> u32 {
+        self.counts.iter().sum::<u32>() + self.other
+    }
+
+    /// Shannon entropy of the A/C/G/T distribution in bits (0..=2).
+    /// `None` if no unambiguous base was observed.
+    pub fn entropy(&self) -> Option<f64> {
+        let total: u32 = self.counts.iter().sum();
+        if total == 0 {
+            return None;
         }
+        let total = total as f64;
+        Some(
+            self.counts
+                .iter()
+                .filter(|&&c| c > 0)
+                .map(|&c| {
+                    let p = c as f64 / total;
+                    -p * p.log2()
+                })
+                .sum(),
+        )
     }
 }
 
@@ -149,6 +325,11 @@ impl Default for WindowAnalysisResult {
 pub struct LengthResult {
     pub oligo_length: u32,
     pub positions: Vec<PositionResult>,
+    /// Base composition per template position, summed over every window of
+    /// this length covering the position. Empty for results saved before it
+    /// was recorded.
+    #[serde(default)]
+    pub column_profile: Vec<ColumnComposition>,
 }
 
 /// Result at a specific template position
@@ -157,6 +338,61 @@ pub struct PositionResult {
     pub position: usize,
     pub variants_needed: usize,
     pub analysis: WindowAnalysisResult,
+    /// Set when the cell was taken from an additional template
+    #[serde(default)]
+    pub source: Option<CellSource>,
+}
+
+/// Window of an additional template that a merged cell was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellSource {
+    /// Index into `ScreeningResults::extra_templates`
+    pub template: usize,
+    /// Window start in that template
+    pub position: usize,
+}
+
+/// Window of an additional template that starts inside an insertion
+/// relative to the template, so it has no heatmap column of its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsertionWindow {
+    pub oligo_length: u32,
+    /// Template position right after the insertion
+    pub next_template_base: usize,
+    /// `position` is the heatmap column the insertion falls in, as for
+    /// merged cells, and `source` the window in the additional template
+    pub cell: PositionResult,
+}
+
+/// An additional template of a multi-template screen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtraTemplate {
+    pub name: String,
+    pub sequence: String,
+}
+
+/// How the template of a screen was obtained
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TemplateOrigin {
+    /// Entered or loaded by the user
+    #[default]
+    Provided,
+    /// The reference with the smallest total k-mer distance to all others
+    MedoidReference { name: String },
+    /// Majority-rule consensus of a progressive alignment of the references
+    MajorityConsensus { references: usize },
+}
+
+impl TemplateOrigin {
+    pub fn description(&self) -> String {
+        match self {
+            TemplateOrigin::Provided => "provided template".to_string(),
+            TemplateOrigin::MedoidReference { name } => format!("medoid reference {}", name),
+            TemplateOrigin::MajorityConsensus { references } => {
+                format!("majority consensus of {} references", references)
+            }
+        }
+    }
 }
 
 /// Complete screening results
@@ -167,6 +403,18 @@ pub struct ScreeningResults {
     pub total_sequences: usize,
     pub template_sequence: String,
     pub results_by_length: HashMap<u32, LengthResult>,
+    #[serde(default)]
+    pub template_origin: TemplateOrigin,
+    /// Additional templates whose windows were merged into these results
+    #[serde(default)]
+    pub extra_templates: Vec<ExtraTemplate>,
+    /// Screened windows of additional templates that start in an insertion
+    /// relative to the template, by column and oligo length
+    #[serde(default)]
+    pub insertion_windows: Vec<InsertionWindow>,
+    /// Soft-masked template ranges (0-based, half-open)
+    #[serde(default)]
+    pub template_masked: Vec<(usize, usize)>,
 }
 
 impl ScreeningResults {
@@ -182,8 +430,40 @@ impl ScreeningResults {
             total_sequences,
             template_sequence,
             results_by_length: HashMap::new(),
+            template_origin: TemplateOrigin::Provided,
+            extra_templates: Vec::new(),
+            insertion_windows: Vec::new(),
+            template_masked: Vec::new(),
+        }
+    }
+
+    /// The template a cell was screened on and the window start in it
+    pub fn cell_template(&self, cell: &PositionResult) -> (&str, usize) {
+        match cell.source {
+            Some(source) => (
+                self.extra_templates
+                    .get(source.template)
+                    .map_or("", |t| t.sequence.as_str()),
+                source.position,
+            ),
+            None => (&self.template_sequence, cell.position),
         }
     }
+
+    /// Whether a template position is soft-masked
+    pub fn is_masked(&self, position: usize) -> bool {
+        self.template_masked
+            .iter()
+            .any(|&(start, end)| start <= position && position < end)
+    }
+
+    /// Template oligo of a cell ("" if the window does not fit the template)
+    pub fn template_oligo(&self, cell: &PositionResult, oligo_length: u32) -> &str {
+        let (template, start) = self.cell_template(cell);
+        template
+            .get(start..start + oligo_length as usize)
+            .unwrap_or("")
+    }
 }
 
 /// Progress update during analysis
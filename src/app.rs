@@ -1,13 +1,35 @@
 //! Main application state and UI
 
 use eframe::egui;
-use std::sync::mpsc::{channel, Receiver};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::sync::Arc;
 use std::thread;
 
 use crate::analysis::{
-    parse_reference_fasta, parse_template_fasta, reverse_complement, run_screening,
-    AnalysisMethod, AnalysisParams, ProgressUpdate, ReferenceData, ScreeningResults, TemplateData,
-    ThreadCount,
+    align_extra_templates, alignment_rows, analyze_single_window, apply_filter_report,
+    assay_detection, assay_options, base_rgb, candidates_to_fasta, candidates_to_order_csv,
+    cell_table, check_oligos, check_rescreen, compare_results, consensus_template, design_panel,
+    detected_references, excluded_mask, filter_references, find_conserved_regions, group_coverage,
+    groups_from_header_tag, groups_from_mapping, heatmap_overview, included_references,
+    inclusivity_table, is_binary_results_file, medoid_template, name_ordinals, oligo_check_table,
+    open_sequence_file, panel_dimers, parse_oligo_list, parse_template_fasta, position_rgb,
+    position_tracks, rank_sites, read_sequence_text, read_sequences, read_text_file, reference_info,
+    regions_to_bed, render_heatmap_figure, rescreen_with_new_references, reverse_complement,
+    run_multi_template_screening, run_screening, screen_specificity, search_motif, sequence_logo,
+    set_excluded, sort_alignment_rows, table_to_delimited, threshold_coverage, variant_table,
+    variants_matrix_table, write_binary_results, write_xlsx, AlignmentRow, AlignmentRowSource,
+    AlignmentSort, AnalysisMethod, AnalysisParams, Candidate, CellSpecificity, ConservedRegion,
+    ConservedRegionParams, DelimitedFormat, ExcludedReference, FilterReport, GroupCoverage,
+    HeatmapImageSettings, LazyResultsFile, LogoSource, MethodChoice, MotifSearch, MultiplexPanel,
+    ORDER_PURIFICATIONS, ORDER_SCALES, OligoCheckParams, OligoCheckResult, OverviewCell, PanelAssay,
+    PanelDiscrimination, PanelParams, PanelTarget, ProgressUpdate, ProjectFile, RankedSite,
+    RankingWeights, ReadOptions, ReadSummary, ReferenceData, ReferenceFilter, ReferenceInfo,
+    ScreeningComparison, ScreeningResults, SpecificityMap, SpecificityParams, TemplateAmbiguity,
+    TemplateData, TemplateOrigin, ThreadCount, UiSelections, ViewerSettings, WindowAnalysisResult,
 };
 
 /// Application state
@@ -16,11 +38,39 @@ pub struct OligoscreenApp {
     template_input: String,
     template_data: Option<TemplateData>,
     template_error: Option<String>,
+    // How the template was obtained, and a running medoid/consensus build
+    template_origin: TemplateOrigin,
+    /// Why the last Run Analysis did not start
+    analysis_setup_error: Option<String>,
+    template_build_rx: Option<Receiver<Result<(TemplateData, TemplateOrigin), String>>>,
 
     // Input tab state - references
     reference_input: String,
     reference_data: Option<ReferenceData>,
     reference_error: Option<String>,
+    /// File the references were streamed from (not shown in the text box)
+    reference_file: Option<PathBuf>,
+    reference_summary: Option<ReadSummary>,
+    read_options: ReadOptions,
+    reference_load_rx: Option<Receiver<Result<(ReferenceData, ReadSummary), String>>>,
+    /// Compressed bytes read so far and the file size
+    reference_load_progress: Option<(Arc<AtomicU64>, u64)>,
+    reference_filter: ReferenceFilter,
+    reference_filter_rx: Option<Receiver<Result<(ReferenceData, FilterReport), String>>>,
+    reference_filter_report: Option<FilterReport>,
+    /// References as loaded, while `reference_data` holds the filtered set
+    unfiltered_references: Option<ReferenceData>,
+    /// Saved filter report of an opened project, applied once its reference
+    /// file has been read
+    pending_filter_report: Option<FilterReport>,
+    reference_filter_status: Option<Result<String, String>>,
+    /// Reference table rows, computed when the table is first shown
+    reference_info: Option<Vec<ReferenceInfo>>,
+    reference_table_search: String,
+    exclusion_input: String,
+    exclusion_data: Option<ReferenceData>,
+    exclusion_error: Option<String>,
+    exclusion_summary: Option<ReadSummary>,
 
     // Analysis parameters
     params: AnalysisParams,
@@ -36,10 +86,13 @@ pub struct OligoscreenApp {
     is_analyzing: bool,
     analysis_progress: Option<ProgressUpdate>,
     progress_rx: Option<Receiver<ProgressUpdate>>,
-    results_rx: Option<Receiver<ScreeningResults>>,
+    results_rx: Option<Receiver<Result<ScreeningResults, String>>>,
 
     // Results state
     results: Option<ScreeningResults>,
+    // Set when results were opened from a binary file whose cell details
+    // (variants, matches) are still partly on disk
+    lazy_results: Option<LazyResultsFile>,
     selected_position: Option<usize>,
     selected_length_for_detail: Option<u32>,
     show_detail_window: bool,
@@ -47,10 +100,18 @@ pub struct OligoscreenApp {
     // Detail window display options
     detail_show_reverse_complement: bool,
     detail_show_codon_spacing: bool,
+    detail_alignment_sort: AlignmentSort,
+    detail_alignment_references: bool,
+
+    // Detail window single-cell re-run: (length, position, result)
+    detail_rerun_params: AnalysisParams,
+    detail_rerun_result: Option<(u32, usize, WindowAnalysisResult)>,
+    detail_rerun_rx: Option<Receiver<(u32, usize, WindowAnalysisResult)>>,
 
     // View state
     current_tab: Tab,
     zoom_level: f32,
+    row_height: f32,
 
     // Results viewer settings (adjustable without re-running analysis)
     view_coverage_threshold: f64,
@@ -59,12 +120,125 @@ pub struct OligoscreenApp {
     nomatch_ok_percent: f64,   // no-match ratio at or below this: original color (no darkening)
     nomatch_bad_percent: f64,  // no-match ratio at or above this: fully dark red
 
+    // Line plots under the heatmap; `track_length` None = shortest length
+    show_entropy_track: bool,
+    show_matched_track: bool,
+    show_mismatch_track: bool,
+    track_length: Option<u32>,
+
+    // Best-site ranking (recomputed on request; cleared when results change)
+    ranking_weights: RankingWeights,
+    ranking_top_n: usize,
+    site_ranking: Option<Vec<RankedSite>>,
+
+    // Conserved regions, cached with the parameters they were computed for
+    region_params: ConservedRegionParams,
+    conserved_regions: Option<(ConservedRegionParams, Vec<ConservedRegion>)>,
+    show_region_brackets: bool,
+    region_status: Option<Result<String, String>>,
+
+    // Template position to scroll the heatmap to on the next frame
+    heatmap_scroll_to: Option<usize>,
+
+    // Heatmap navigation: go-to box (1-based), minimap bins cached with
+    // their bin count, and the (start, current) columns of a box selection
+    goto_position: usize,
+    heatmap_overview: Option<(usize, Vec<Vec<Option<OverviewCell>>>)>,
+    heatmap_box: Option<(usize, usize)>,
+
+    // Off-target screen against the exclusion set: cell map and background
+    // names, shading threshold (mismatches) and the running job
+    specificity: Option<(SpecificityMap, Vec<String>)>,
+    specificity_max_variants: usize,
+    specificity_close_mismatches: usize,
+    show_specificity: bool,
+    specificity_rx: Option<Receiver<SpecificityMap>>,
+    specificity_progress_rx: Option<Receiver<(usize, usize)>>,
+    specificity_progress: Option<(usize, usize)>,
+
+    // Inclusivity/exclusivity report: grouping (header tag or a mapping file
+    // as (file name, contents)), assay source and the last report with its
+    // assay label
+    group_tag: String,
+    group_mapping: Option<(String, String)>,
+    group_use_mapping: bool,
+    group_from_candidates: bool,
+    group_report: Option<Result<(String, Vec<GroupCoverage>), String>>,
+    group_targets: HashSet<String>,
+    group_status: Option<Result<String, String>>,
+
+    // Motif search in the template
+    motif_query: String,
+    motif_search: Option<Result<MotifSearch, String>>,
+
+    // Run comparison: (file name, results) for each side
+    compare_before: Option<(String, ScreeningResults)>,
+    compare_after: Option<(String, ScreeningResults)>,
+    compare_result: Option<ScreeningComparison>,
+    compare_error: Option<String>,
+    compare_metric: CompareMetric,
+
+    // Oligo check: named (possibly degenerate) oligos against the references
+    oligo_check_input: String,
+    oligo_check_three_prime: usize,
+    oligo_check_results: Option<(Vec<OligoCheckResult>, Vec<String>)>,
+    oligo_check_error: Option<String>,
+    oligo_check_selected: Option<usize>,
+    oligo_check_rx: Option<Receiver<Vec<OligoCheckResult>>>,
+    oligo_check_progress_rx: Option<Receiver<(usize, usize)>>,
+    oligo_check_progress: Option<(usize, usize)>,
+    oligo_check_status: Option<Result<String, String>>,
+
+    // Multiplex panel: targets with their own inputs, their screening
+    // results (same order), builder settings (dyes as comma-separated text)
+    // and the designed panel
+    panel_targets: Vec<PanelTarget>,
+    panel_results: Vec<Option<Result<ScreeningResults, String>>>,
+    panel_params: PanelParams,
+    panel_dyes: String,
+    panel: Option<MultiplexPanel>,
+    panel_rx: Option<Receiver<(usize, Result<ScreeningResults, String>)>>,
+    panel_progress: Option<(usize, usize)>,
+    panel_status: Option<Result<String, String>>,
+
     // Save/Load
     save_error: Option<String>,
     load_error: Option<String>,
+    project_include_results: bool,
+
+    // Candidate basket
+    candidates: Vec<Candidate>,
+    show_basket: bool,
+    basket_status: Option<Result<String, String>>,
+
+    // Table export window
+    show_export_window: bool,
+    export_format: ExportFormat,
+    export_matrix: bool,
+    export_cells: bool,
+    export_variants: bool,
+    export_thermo: bool,
+    export_status: Option<Result<String, String>>,
+
+    // Heatmap image export window (colors come from the current viewer settings)
+    show_image_export_window: bool,
+    image_format: ImageFormat,
+    image_settings: HeatmapImageSettings,
+    image_png_scale: f32,
+    image_export_status: Option<Result<String, String>>,
+    image_export_rx: Option<Receiver<Result<String, String>>>,
+
+    // Incremental re-screen (new references added to existing results)
+    rescreen_error: Option<String>,
+    /// Skipped records and invalid characters of the last added reference file
+    rescreen_notes: Option<String>,
+    // Reference input text and loaded reference count before the running
+    // re-screen appended its references, restored if it fails
+    rescreen_undo: Option<(String, usize)>,
 
     // Deferred actions
     pending_save: bool,
+    pending_add_references: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -72,6 +246,28 @@ enum Tab {
     Input,
     Analysis,
     Results,
+    Compare,
+    OligoCheck,
+    Multiplex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Csv,
+    Tsv,
+    Xlsx,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageFormat {
+    Svg,
+    Png,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareMetric {
+    VariantsNeeded,
+    NoMatchRate,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -96,9 +292,29 @@ impl Default for OligoscreenApp {
             template_input: String::new(),
             template_data: None,
             template_error: None,
+            template_origin: TemplateOrigin::Provided,
+            analysis_setup_error: None,
+            template_build_rx: None,
             reference_input: String::new(),
+            reference_file: None,
+            reference_summary: None,
+            read_options: ReadOptions::default(),
+            reference_load_rx: None,
+            reference_load_progress: None,
+            reference_filter: ReferenceFilter::default(),
+            reference_filter_rx: None,
+            reference_filter_report: None,
+            unfiltered_references: None,
+            pending_filter_report: None,
+            reference_filter_status: None,
+            reference_info: None,
+            reference_table_search: String::new(),
             reference_data: None,
             reference_error: None,
+            exclusion_input: String::new(),
+            exclusion_data: None,
+            exclusion_error: None,
+            exclusion_summary: None,
             params: AnalysisParams::default(),
             method_selection: MethodSelection::NoAmbiguities,
             thread_selection: ThreadSelection::Auto,
@@ -110,21 +326,102 @@ impl Default for OligoscreenApp {
             progress_rx: None,
             results_rx: None,
             results: None,
+            lazy_results: None,
             selected_position: None,
             selected_length_for_detail: None,
             show_detail_window: false,
             detail_show_reverse_complement: false,
             detail_show_codon_spacing: true,
+            detail_alignment_sort: AlignmentSort::Count,
+            detail_alignment_references: false,
+            detail_rerun_params: AnalysisParams::default(),
+            detail_rerun_result: None,
+            detail_rerun_rx: None,
             current_tab: Tab::Input,
             zoom_level: 1.0,
+            row_height: 54.0,
             view_coverage_threshold: 95.0,
             color_green_at: 1,
             color_red_at: 10,
             nomatch_ok_percent: 5.0,
             nomatch_bad_percent: 50.0,
+            show_entropy_track: true,
+            show_matched_track: true,
+            show_mismatch_track: true,
+            track_length: None,
+            ranking_weights: RankingWeights::default(),
+            ranking_top_n: 20,
+            site_ranking: None,
+            region_params: ConservedRegionParams::default(),
+            conserved_regions: None,
+            show_region_brackets: true,
+            region_status: None,
+            heatmap_scroll_to: None,
+            goto_position: 1,
+            heatmap_overview: None,
+            heatmap_box: None,
+            specificity: None,
+            specificity_max_variants: SpecificityParams::default().max_cell_variants,
+            specificity_close_mismatches: 2,
+            show_specificity: true,
+            specificity_rx: None,
+            specificity_progress_rx: None,
+            specificity_progress: None,
+            group_tag: "species".to_string(),
+            group_mapping: None,
+            group_use_mapping: false,
+            group_from_candidates: false,
+            group_report: None,
+            group_targets: HashSet::new(),
+            group_status: None,
+            motif_query: String::new(),
+            motif_search: None,
+            compare_before: None,
+            compare_after: None,
+            compare_result: None,
+            compare_error: None,
+            compare_metric: CompareMetric::VariantsNeeded,
+            oligo_check_input: String::new(),
+            oligo_check_three_prime: OligoCheckParams::default().three_prime_window,
+            oligo_check_results: None,
+            oligo_check_error: None,
+            oligo_check_selected: None,
+            oligo_check_rx: None,
+            oligo_check_progress_rx: None,
+            oligo_check_progress: None,
+            oligo_check_status: None,
+            panel_targets: Vec::new(),
+            panel_results: Vec::new(),
+            panel_params: PanelParams::default(),
+            panel_dyes: PanelParams::default().dyes.join(", "),
+            panel: None,
+            panel_rx: None,
+            panel_progress: None,
+            panel_status: None,
             save_error: None,
             load_error: None,
+            project_include_results: true,
+            candidates: Vec::new(),
+            show_basket: false,
+            basket_status: None,
+            show_export_window: false,
+            export_format: ExportFormat::Csv,
+            export_matrix: true,
+            export_cells: true,
+            export_variants: true,
+            export_thermo: true,
+            export_status: None,
+            show_image_export_window: false,
+            image_format: ImageFormat::Svg,
+            image_settings: HeatmapImageSettings::default(),
+            image_png_scale: 2.0,
+            image_export_status: None,
+            image_export_rx: None,
+            rescreen_error: None,
+            rescreen_notes: None,
+            rescreen_undo: None,
             pending_save: false,
+            pending_add_references: false,
         }
     }
 }
@@ -138,30 +435,28 @@ impl OligoscreenApp {
     /// positions using the current view_coverage_threshold, without re-running
     /// the full analysis.
     fn recalculate_coverage_threshold(&mut self) {
+        // Binary files store the variant percentages in their header, so no
+        // block needs to be loaded
         let threshold = self.view_coverage_threshold;
+        self.clear_result_views();
         let Some(results) = &mut self.results else {
             return;
         };
+        let lazy = self.lazy_results.as_ref();
 
-        for length_result in results.results_by_length.values_mut() {
-            for pos_result in &mut length_result.positions {
+        for (&length, length_result) in results.results_by_length.iter_mut() {
+            for (index, pos_result) in length_result.positions.iter_mut().enumerate() {
                 if pos_result.analysis.skipped {
                     continue;
                 }
-                let mut cumulative = 0.0;
-                let mut new_needed = pos_result.analysis.variants.len();
-                let mut new_coverage = 0.0;
-                for (i, variant) in pos_result.analysis.variants.iter().enumerate() {
-                    cumulative += variant.percentage;
-                    if cumulative >= threshold {
-                        new_needed = i + 1;
-                        new_coverage = cumulative;
-                        break;
-                    }
-                }
-                if cumulative < threshold {
-                    new_coverage = cumulative;
-                }
+                let stored = lazy.and_then(|lazy| lazy.variant_percentages(length, index));
+                let (new_needed, new_coverage) = match stored {
+                    Some(percentages) => threshold_coverage(percentages, threshold),
+                    None => threshold_coverage(
+                        pos_result.analysis.variants.iter().map(|v| v.percentage),
+                        threshold,
+                    ),
+                };
                 pos_result.analysis.variants_for_threshold = new_needed;
                 pos_result.analysis.coverage_at_threshold = new_coverage;
                 pos_result.variants_needed = new_needed;
@@ -172,6 +467,7 @@ impl OligoscreenApp {
     fn parse_template_input(&mut self) {
         self.template_error = None;
         self.template_data = None;
+        self.template_origin = TemplateOrigin::Provided;
 
         if self.template_input.trim().is_empty() {
             return;
@@ -187,33 +483,578 @@ impl OligoscreenApp {
         }
     }
 
+    /// Derive the template from the references (medoid or majority
+    /// consensus) on a background thread
+    fn start_template_build(&mut self, consensus: bool) {
+        let Some(references) = &self.reference_data else {
+            return;
+        };
+        let references = references.clone();
+        let pairwise = self.params.pairwise;
+        let (tx, rx) = channel();
+        self.template_build_rx = Some(rx);
+        thread::spawn(move || {
+            let result = if consensus {
+                consensus_template(&references, &pairwise)
+            } else {
+                medoid_template(&references)
+            };
+            let _ = tx.send(result);
+        });
+    }
+
+    fn check_template_build(&mut self) {
+        let Some(rx) = &self.template_build_rx else {
+            return;
+        };
+        let Ok(result) = rx.try_recv() else {
+            return;
+        };
+        self.template_build_rx = None;
+        match result {
+            Ok((template, origin)) => {
+                self.template_input = format!(">{}\n{}\n", template.name, template.sequence);
+                self.parse_template_input();
+                self.template_origin = origin;
+            }
+            Err(e) => self.template_error = Some(e),
+        }
+    }
+
     fn parse_reference_input(&mut self) {
         self.reference_error = None;
         self.reference_data = None;
+        self.reference_summary = None;
+        self.reference_file = None;
+        self.reference_load_rx = None;
+        self.reference_load_progress = None;
+        self.clear_reference_filter();
+        self.reference_info = None;
 
         if self.reference_input.trim().is_empty() {
             return;
         }
 
-        match parse_reference_fasta(&self.reference_input) {
-            Ok(data) => {
+        match read_sequence_text(&self.reference_input, &self.read_options) {
+            Ok((data, summary)) => {
+                self.reference_data = Some(data);
+                self.reference_summary = Some(summary);
+            }
+            Err(e) => {
+                self.reference_error = Some(e);
+            }
+        }
+    }
+
+    /// Stream references from a (possibly compressed) FASTA/FASTQ file on a
+    /// background thread, without copying them into the text box
+    fn start_reference_file_load(&mut self, path: PathBuf) {
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let bytes_read = Arc::new(AtomicU64::new(0));
+        let options = self.read_options;
+        let counter = Arc::clone(&bytes_read);
+        let file = path.clone();
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let result = open_sequence_file(&file, Some(counter))
+                .and_then(|(reader, _)| read_sequences(reader, &options));
+            let _ = tx.send(result);
+        });
+
+        self.reference_input.clear();
+        self.reference_data = None;
+        self.reference_summary = None;
+        self.reference_error = None;
+        self.reference_file = Some(path);
+        self.reference_load_rx = Some(rx);
+        self.reference_load_progress = Some((bytes_read, size));
+        self.clear_reference_filter();
+    }
+
+    /// Names of the loaded references that went into `results` (without the
+    /// ones the run excluded), or `None` if the loaded set is not the one
+    /// these results were computed from
+    fn run_reference_names(&self, results: &ScreeningResults) -> Option<Cow<'_, [String]>> {
+        let references = self.reference_data.as_ref()?;
+        let excluded = &results.params.excluded_references;
+        let names: Cow<'_, [String]> = if excluded.is_empty() {
+            Cow::Borrowed(&references.names)
+        } else {
+            let mask = excluded_mask(&references.names, excluded);
+            Cow::Owned(
+                references
+                    .names
+                    .iter()
+                    .zip(mask)
+                    .filter(|(_, excluded)| !excluded)
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+            )
+        };
+        (names.len() == results.total_sequences).then_some(names)
+    }
+
+    /// Table of the loaded references with checkboxes that exclude them from
+    /// the next run (`AnalysisParams::excluded_references`)
+    fn show_reference_table(&mut self, ui: &mut egui::Ui) {
+        const MAX_ROWS: usize = 1000;
+        let Some(references) = &self.reference_data else {
+            return;
+        };
+        if self
+            .reference_info
+            .as_ref()
+            .is_none_or(|info| info.len() != references.len())
+        {
+            self.reference_info = Some(reference_info(references));
+        }
+        let Some(info) = &self.reference_info else {
+            return;
+        };
+
+        let excluded = excluded_mask(&references.names, &self.params.excluded_references);
+        let search = self.reference_table_search.trim().to_lowercase();
+        let shown: Vec<usize> = (0..references.len())
+            .filter(|&i| search.is_empty() || references.names[i].to_lowercase().contains(&search))
+            .collect();
+        let included = excluded.iter().filter(|&&e| !e).count();
+        // Entries matching no loaded reference
+        let mut name_counts: HashMap<&str, usize> = HashMap::new();
+        for name in &references.names {
+            *name_counts.entry(name).or_default() += 1;
+        }
+        let is_stale = |e: &ExcludedReference| {
+            name_counts
+                .get(e.name.as_str())
+                .is_none_or(|&count| e.ordinal.is_some_and(|o| o >= count))
+        };
+        let stale = self
+            .params
+            .excluded_references
+            .iter()
+            .filter(|e| is_stale(e))
+            .count();
+
+        let mut include_shown = None;
+        let mut clear_stale = false;
+        ui.horizontal(|ui| {
+            ui.label("Search:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.reference_table_search).desired_width(160.0),
+            );
+            if ui.button("Include Shown").clicked() {
+                include_shown = Some(true);
+            }
+            if ui.button("Exclude Shown").clicked() {
+                include_shown = Some(false);
+            }
+            ui.label(format!(
+                "{} of {} references included in the next run",
+                included,
+                references.len()
+            ));
+        });
+        if stale > 0 {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{} excluded references are not in the loaded references",
+                    stale
+                ));
+                clear_stale = ui.small_button("Forget").clicked();
+            });
+        }
+
+        let mut toggled: Vec<(usize, bool)> = Vec::new();
+        egui::ScrollArea::both()
+            .id_salt("reference_table_scroll")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                egui::Grid::new("reference_table_grid")
+                    .striped(true)
+                    .min_col_width(30.0)
+                    .show(ui, |ui| {
+                        for header in [
+                            "Use", "Name", "Length", "GC", "Ambiguous", "Accession", "Strain",
+                            "Date", "Country",
+                        ] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for &i in shown.iter().take(MAX_ROWS) {
+                            let name = &references.names[i];
+                            let row = &info[i];
+                            let mut use_reference = !excluded[i];
+                            if ui.checkbox(&mut use_reference, "").changed() {
+                                toggled.push((i, use_reference));
+                            }
+                            let short: String = name.chars().take(40).collect();
+                            let label = if short.len() < name.len() {
+                                format!("{}...", short)
+                            } else {
+                                short
+                            };
+                            if label.len() < name.len() {
+                                ui.label(label).on_hover_text(name);
+                            } else {
+                                ui.label(label);
+                            }
+                            ui.label(format!("{} bp", row.length));
+                            ui.label(format!("{:.1}%", row.gc_percent));
+                            ui.label(format!("{}", row.ambiguous));
+                            let metadata = &row.metadata;
+                            for field in [
+                                &metadata.accession,
+                                &metadata.strain,
+                                &metadata.date,
+                                &metadata.country,
+                            ] {
+                                ui.label(field.as_deref().unwrap_or(""));
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        if shown.len() > MAX_ROWS {
+            ui.label(format!(
+                "Showing {} of {} matching references; search to narrow the list.",
+                MAX_ROWS,
+                shown.len()
+            ));
+        }
+
+        if let Some(include) = include_shown {
+            toggled.extend(shown.iter().map(|&i| (i, include)));
+        }
+        if clear_stale {
+            self.params.excluded_references.retain(|e| !is_stale(e));
+        }
+        let excluded_references = &mut self.params.excluded_references;
+        if !toggled.is_empty() {
+            let ordinals = name_ordinals(&references.names);
+            for (i, include) in toggled {
+                set_excluded(excluded_references, &references.names, &ordinals, i, !include);
+            }
+        }
+    }
+
+    /// Forget the quality filter result (the references it applied to changed)
+    fn clear_reference_filter(&mut self) {
+        self.reference_filter_rx = None;
+        self.reference_filter_report = None;
+        self.unfiltered_references = None;
+        self.reference_filter_status = None;
+        self.pending_filter_report = None;
+    }
+
+    /// Remove the references a saved filter report removed, without running
+    /// the filter again
+    fn restore_reference_filter(&mut self, report: FilterReport) {
+        let Some(references) = &self.reference_data else {
+            return;
+        };
+        match apply_filter_report(references, &report) {
+            Ok(kept) => {
+                self.unfiltered_references = self.reference_data.replace(kept);
+                self.reference_filter_report = Some(report);
+            }
+            Err(e) => self.reference_filter_status = Some(Err(e)),
+        }
+    }
+
+    /// Run the import quality filter on the loaded references in the
+    /// background. Re-filtering starts again from the unfiltered set.
+    fn start_reference_filter(&mut self) {
+        let Some(references) = self
+            .unfiltered_references
+            .as_ref()
+            .or(self.reference_data.as_ref())
+        else {
+            return;
+        };
+        let references = references.clone();
+        let template = self.template_data.as_ref().map(|t| t.sequence.clone());
+        let filter = self.reference_filter;
+        let pairwise = self.params.pairwise;
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let result = filter_references(&references, template.as_deref(), &filter, &pairwise);
+            let _ = tx.send(result);
+        });
+        self.reference_filter_rx = Some(rx);
+        self.reference_filter_status = None;
+    }
+
+    fn check_reference_filter(&mut self) {
+        let Some(rx) = &self.reference_filter_rx else {
+            return;
+        };
+        let Ok(result) = rx.try_recv() else {
+            return;
+        };
+        self.reference_filter_rx = None;
+        match result {
+            Ok((kept, report)) => {
+                if kept.is_empty() {
+                    self.reference_filter_status =
+                        Some(Err("The filter would remove every reference".to_string()));
+                    return;
+                }
+                if self.unfiltered_references.is_none() {
+                    self.unfiltered_references = self.reference_data.take();
+                }
+                self.reference_data = Some(kept);
+                self.reference_filter_report = Some(report);
+            }
+            Err(e) => self.reference_filter_status = Some(Err(e)),
+        }
+    }
+
+    fn restore_unfiltered_references(&mut self) {
+        if let Some(references) = self.unfiltered_references.take() {
+            self.reference_data = Some(references);
+        }
+        self.reference_filter_report = None;
+        self.reference_filter_status = None;
+    }
+
+    fn export_reference_filter_report(&mut self) {
+        let Some(report) = &self.reference_filter_report else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("TSV", &["tsv"])
+            .set_file_name("removed_references.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let format = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("tsv")) {
+            DelimitedFormat::Tsv
+        } else {
+            DelimitedFormat::Csv
+        };
+        let content = table_to_delimited(&report.table(), format);
+        self.reference_filter_status = Some(
+            std::fs::write(&path, content)
+                .map(|_| format!("Wrote {}", path.display()))
+                .map_err(|e| format!("Failed to write file: {}", e)),
+        );
+    }
+
+    fn show_reference_filter(&mut self, ui: &mut egui::Ui) {
+        let filter = &mut self.reference_filter;
+        ui.horizontal(|ui| {
+            ui.label("Min. length:");
+            ui.add(egui::DragValue::new(&mut filter.min_length).suffix(" bp"));
+            ui.label("Max. ambiguous:");
+            ui.add(
+                egui::DragValue::new(&mut filter.max_ambiguous_percent)
+                    .range(0.0..=100.0)
+                    .suffix("%"),
+            );
+            ui.checkbox(&mut filter.remove_duplicates, "Remove exact duplicates");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Min. template coverage:");
+            ui.add(
+                egui::DragValue::new(&mut filter.min_template_coverage)
+                    .range(0.0..=100.0)
+                    .suffix("%"),
+            )
+            .on_hover_text(
+                "Share of the template spanned by its best local alignment to the reference, \
+                 mismatches and gaps included (0 = off)",
+            );
+        });
+
+        ui.horizontal(|ui| {
+            let running = self.reference_filter_rx.is_some();
+            if ui
+                .add_enabled(
+                    !running && self.reference_data.is_some(),
+                    egui::Button::new("Filter References"),
+                )
+                .clicked()
+            {
+                self.start_reference_filter();
+            }
+            if ui
+                .add_enabled(
+                    self.unfiltered_references.is_some(),
+                    egui::Button::new("Restore All"),
+                )
+                .clicked()
+            {
+                self.restore_unfiltered_references();
+            }
+            if ui
+                .add_enabled(
+                    self.reference_filter_report.is_some(),
+                    egui::Button::new("Export Report..."),
+                )
+                .clicked()
+            {
+                self.export_reference_filter_report();
+            }
+            if running {
+                ui.spinner();
+            }
+        });
+
+        match &self.reference_filter_status {
+            Some(Ok(message)) => {
+                ui.label(message);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            None => {}
+        }
+        if let Some(report) = &self.reference_filter_report {
+            ui.label(format!(
+                "Kept {} of {} references; {} removed",
+                report.kept(),
+                report.total,
+                report.removed.len()
+            ));
+            egui::ScrollArea::vertical()
+                .id_salt("reference_filter_report")
+                .max_height(120.0)
+                .show(ui, |ui| {
+                    for removed in report.removed.iter().take(500) {
+                        ui.label(format!(
+                            "{}. {}: {}",
+                            removed.index + 1,
+                            removed.name,
+                            removed.reason.description()
+                        ));
+                    }
+                    if report.removed.len() > 500 {
+                        ui.label(format!("... and {} more", report.removed.len() - 500));
+                    }
+                });
+        }
+    }
+
+    fn check_reference_load(&mut self) {
+        let Some(rx) = &self.reference_load_rx else {
+            return;
+        };
+        let Ok(result) = rx.try_recv() else {
+            return;
+        };
+        self.reference_load_rx = None;
+        self.reference_load_progress = None;
+        match result {
+            Ok((data, summary)) => {
                 self.reference_data = Some(data);
+                self.reference_info = None;
+                self.reference_summary = Some(summary);
+                if let Some(report) = self.pending_filter_report.take() {
+                    self.restore_reference_filter(report);
+                }
             }
             Err(e) => {
+                self.reference_file = None;
                 self.reference_error = Some(e);
+                self.pending_filter_report = None;
+            }
+        }
+    }
+
+    fn parse_exclusion_input(&mut self) {
+        self.exclusion_error = None;
+        self.exclusion_data = None;
+        self.exclusion_summary = None;
+
+        if self.exclusion_input.trim().is_empty() {
+            return;
+        }
+
+        match read_sequence_text(&self.exclusion_input, &self.read_options) {
+            Ok((data, summary)) => {
+                self.exclusion_data = Some(data);
+                self.exclusion_summary = Some(summary);
+            }
+            Err(e) => {
+                self.exclusion_error = Some(e);
             }
         }
     }
 
     fn start_analysis(&mut self) {
+        self.apply_setup_selections();
         let Some(template) = &self.template_data else {
             return;
         };
         let Some(references) = &self.reference_data else {
             return;
         };
+        let references = if self.params.excluded_references.is_empty() {
+            Cow::Borrowed(references)
+        } else {
+            Cow::Owned(included_references(
+                references,
+                &self.params.excluded_references,
+            ))
+        };
+        if references.is_empty() {
+            self.analysis_setup_error =
+                Some("Every reference is excluded in the reference table".to_string());
+            return;
+        }
+
+        self.analysis_setup_error = None;
+        let template_clone = template.clone();
+        let references_clone = references.into_owned();
+        let params_clone = self.params.clone();
+        let template_origin = self.template_origin.clone();
+
+        let (progress_tx, progress_rx) = channel();
+        let (results_tx, results_rx) = channel();
+
+        self.progress_rx = Some(progress_rx);
+        self.results_rx = Some(results_rx);
+        self.is_analyzing = true;
+        self.analysis_progress = None;
 
-        // Update method from selection
+        thread::spawn(move || {
+            // Picking and aligning the additional templates compares every
+            // reference pair, so it runs here rather than on the UI thread
+            let extra_templates =
+                match align_extra_templates(&template_clone, &references_clone, &params_clone) {
+                    Ok(extras) => extras,
+                    Err(e) => {
+                        let _ = results_tx.send(Err(e));
+                        return;
+                    }
+                };
+            let mut results = if extra_templates.is_empty() {
+                run_screening(
+                    &template_clone,
+                    &references_clone,
+                    &params_clone,
+                    Some(progress_tx),
+                )
+            } else {
+                run_multi_template_screening(
+                    &template_clone,
+                    &extra_templates,
+                    &references_clone,
+                    &params_clone,
+                    Some(progress_tx),
+                )
+            };
+            results.template_origin = template_origin;
+            let _ = results_tx.send(Ok(results));
+        });
+    }
+
+    /// Copy the method and thread radio selections of the setup tab into
+    /// `params`
+    fn apply_setup_selections(&mut self) {
         self.params.method = match self.method_selection {
             MethodSelection::NoAmbiguities => AnalysisMethod::NoAmbiguities,
             MethodSelection::FixedAmbiguities => {
@@ -234,10 +1075,66 @@ impl OligoscreenApp {
             ThreadSelection::Auto => ThreadCount::Auto,
             ThreadSelection::Manual => ThreadCount::Fixed(self.manual_thread_count),
         };
+    }
 
-        let template_clone = template.clone();
-        let references_clone = references.clone();
-        let params_clone = self.params.clone();
+    /// Pick a FASTA file of additional references and merge them into the
+    /// current results on a background thread (see `rescreen_with_new_references`).
+    fn add_references_to_results(&mut self) {
+        self.rescreen_notes = None;
+        if let Err(e) = self.ensure_all_details() {
+            self.rescreen_error = Some(e);
+            return;
+        }
+        let Some(results) = &self.results else {
+            return;
+        };
+        if let Err(e) = check_rescreen(results) {
+            self.rescreen_error = Some(e);
+            return;
+        }
+
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("FASTA/FASTQ", READ_FILE_EXTENSIONS)
+            .pick_file()
+        else {
+            return;
+        };
+
+        let new_references = match open_sequence_file(&path, None)
+            .and_then(|(reader, _)| read_sequences(reader, &self.read_options))
+        {
+            Ok((data, summary)) => {
+                self.rescreen_notes = summary.notes();
+                data
+            }
+            Err(e) => {
+                self.rescreen_error = Some(e);
+                return;
+            }
+        };
+        self.rescreen_error = None;
+        let previous = results.clone();
+        self.rescreen_undo = Some((
+            self.reference_input.clone(),
+            self.reference_data.as_ref().map_or(0, |r| r.len()),
+        ));
+
+        // Keep the Input tab in sync when it holds the references these results came from
+        if self.run_reference_names(&previous).is_some() {
+            if self.reference_file.is_some() {
+                // Streamed from a file: there is no text to append to
+                if let Some(data) = self.reference_data.as_mut() {
+                    data.names.extend(new_references.names.iter().cloned());
+                    data.sequences.extend(new_references.sequences.iter().cloned());
+                }
+            } else {
+                if !self.reference_input.ends_with('\n') {
+                    self.reference_input.push('\n');
+                }
+                self.reference_input.push_str(&new_references.to_fasta());
+                self.parse_reference_input();
+            }
+        }
 
         let (progress_tx, progress_rx) = channel();
         let (results_tx, results_rx) = channel();
@@ -248,16 +1145,28 @@ impl OligoscreenApp {
         self.analysis_progress = None;
 
         thread::spawn(move || {
-            let results = run_screening(
-                &template_clone,
-                &references_clone,
-                &params_clone,
-                Some(progress_tx),
-            );
+            let results =
+                rescreen_with_new_references(&previous, &new_references, Some(progress_tx));
             let _ = results_tx.send(results);
         });
     }
 
+    /// Take back the references a failed re-screen appended to the Input tab
+    fn undo_rescreen_references(&mut self) {
+        let Some((input, count)) = self.rescreen_undo.take() else {
+            return;
+        };
+        if self.reference_file.is_some() {
+            if let Some(data) = self.reference_data.as_mut() {
+                data.names.truncate(count);
+                data.sequences.truncate(count);
+            }
+        } else if self.reference_input != input {
+            self.reference_input = input;
+            self.parse_reference_input();
+        }
+    }
+
     fn check_analysis_progress(&mut self) {
         if let Some(rx) = &self.progress_rx {
             while let Ok(progress) = rx.try_recv() {
@@ -266,28 +1175,196 @@ impl OligoscreenApp {
         }
 
         if let Some(rx) = &self.results_rx {
+            let received = match rx.try_recv() {
+                Ok(received) => received,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    Err("The analysis stopped unexpectedly".to_string())
+                }
+            };
+            self.is_analyzing = false;
+            self.progress_rx = None;
+            self.results_rx = None;
+            match received {
+                Ok(results) => {
+                    self.rescreen_undo = None;
+                    self.view_coverage_threshold = results.params.coverage_threshold;
+                    self.detail_rerun_params = results.params.clone();
+                    self.detail_rerun_result = None;
+                    self.results = Some(results);
+                    self.lazy_results = None;
+                    self.clear_result_views();
+                    self.current_tab = Tab::Results;
+                }
+                Err(e) if self.rescreen_undo.is_some() => {
+                    self.undo_rescreen_references();
+                    self.rescreen_error = Some(e);
+                }
+                Err(e) => self.analysis_setup_error = Some(e),
+            }
+        }
+    }
+
+    /// Re-analyze the selected detail window with `detail_rerun_params` on a
+    /// background thread. The result is tagged with its cell so a late result
+    /// is not shown for a different selection.
+    fn start_window_rerun(&mut self, position: usize, length: u32) {
+        let Some(results) = &self.results else {
+            return;
+        };
+        let Some(references) = &self.reference_data else {
+            return;
+        };
+        let references = included_references(references, &results.params.excluded_references);
+
+        // Cells taken from an additional template are re-run on it
+        let source = results
+            .results_by_length
+            .get(&length)
+            .and_then(|lr| {
+                lr.positions
+                    .binary_search_by_key(&position, |p| p.position)
+                    .ok()
+                    .map(|i| lr.positions[i].source)
+            })
+            .flatten();
+        let (sequence, masked, window_position) = match source {
+            Some(s) => (
+                results.extra_templates[s.template].sequence.clone(),
+                Vec::new(),
+                s.position,
+            ),
+            None => (
+                results.template_sequence.clone(),
+                results.template_masked.clone(),
+                position,
+            ),
+        };
+        let template = TemplateData {
+            name: "Template".to_string(),
+            sequence,
+            masked,
+        };
+        let references_clone = references;
+        let params_clone = self.detail_rerun_params.clone();
+
+        let (tx, rx) = channel();
+        self.detail_rerun_rx = Some(rx);
+
+        thread::spawn(move || {
+            let analysis = analyze_single_window(
+                &template,
+                &references_clone,
+                &params_clone,
+                window_position,
+                length,
+            );
+            let _ = tx.send((length, position, analysis));
+        });
+    }
+
+    fn check_window_rerun(&mut self) {
+        if let Some(rx) = &self.detail_rerun_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.detail_rerun_result = Some(result);
+                self.detail_rerun_rx = None;
+            }
+        }
+    }
+
+    /// Check the oligos in the Oligo Check tab against the loaded references
+    /// on a background thread. Alignment settings come from Analysis Setup.
+    fn start_oligo_check(&mut self) {
+        let Some(references) = &self.reference_data else {
+            return;
+        };
+        let oligos = match parse_oligo_list(&self.oligo_check_input) {
+            Ok(oligos) => oligos,
+            Err(e) => {
+                self.oligo_check_error = Some(e);
+                return;
+            }
+        };
+        self.oligo_check_error = None;
+        self.oligo_check_status = None;
+
+        let params = OligoCheckParams {
+            pairwise: self.params.pairwise,
+            three_prime_window: self.oligo_check_three_prime,
+            thread_count: match self.thread_selection {
+                ThreadSelection::Auto => ThreadCount::Auto,
+                ThreadSelection::Manual => ThreadCount::Fixed(self.manual_thread_count),
+            },
+        };
+        let references_clone = references.clone();
+
+        let (progress_tx, progress_rx) = channel();
+        let (results_tx, results_rx) = channel();
+        self.oligo_check_progress_rx = Some(progress_rx);
+        self.oligo_check_rx = Some(results_rx);
+        self.oligo_check_progress = Some((0, oligos.len()));
+
+        thread::spawn(move || {
+            let results = check_oligos(&oligos, &references_clone, &params, Some(progress_tx));
+            let _ = results_tx.send(results);
+        });
+    }
+
+    fn check_oligo_check_progress(&mut self) {
+        if let Some(rx) = &self.oligo_check_progress_rx {
+            while let Ok(progress) = rx.try_recv() {
+                self.oligo_check_progress = Some(progress);
+            }
+        }
+        if let Some(rx) = &self.oligo_check_rx {
             if let Ok(results) = rx.try_recv() {
-                self.view_coverage_threshold = results.params.coverage_threshold;
-                self.results = Some(results);
-                self.is_analyzing = false;
-                self.progress_rx = None;
-                self.results_rx = None;
-                self.current_tab = Tab::Results;
+                let names = self
+                    .reference_data
+                    .as_ref()
+                    .map(|r| r.names.clone())
+                    .unwrap_or_default();
+                self.oligo_check_results = Some((results, names));
+                self.oligo_check_selected = None;
+                self.oligo_check_rx = None;
+                self.oligo_check_progress_rx = None;
+                self.oligo_check_progress = None;
             }
         }
     }
 
+    /// Make sure every cell of lazily loaded binary results has its details
+    /// in memory. Needed before operations that touch all variants.
+    fn ensure_all_details(&mut self) -> Result<(), String> {
+        if let (Some(lazy), Some(results)) = (&mut self.lazy_results, &mut self.results) {
+            lazy.load_all(results)?;
+        }
+        self.lazy_results = None;
+        Ok(())
+    }
+
     fn save_results(&mut self) {
-        let Some(results) = &self.results else {
+        if self.results.is_none() {
             self.save_error = Some("No results to save".to_string());
             return;
+        }
+        if let Err(e) = self.ensure_all_details() {
+            self.save_error = Some(e);
+            return;
+        }
+        let Some(results) = &self.results else {
+            return;
         };
 
         if let Some(path) = rfd::FileDialog::new()
             .add_filter("JSON", &["json"])
+            .add_filter("Compressed binary", &["osrb"])
             .set_file_name("screening_results.json")
             .save_file()
         {
+            if path.extension().is_some_and(|e| e == "osrb") {
+                self.save_error = write_binary_results(results, &path).err();
+                return;
+            }
             match serde_json::to_string_pretty(results) {
                 Ok(json) => {
                     if let Err(e) = std::fs::write(&path, json) {
@@ -305,82 +1382,292 @@ impl OligoscreenApp {
 
     fn load_results(&mut self) {
         if let Some(path) = rfd::FileDialog::new()
-            .add_filter("JSON", &["json"])
+            .add_filter("Results", &["json", "osrb"])
             .pick_file()
         {
-            match std::fs::read_to_string(&path) {
-                Ok(json) => match serde_json::from_str::<ScreeningResults>(&json) {
-                    Ok(results) => {
-                        self.view_coverage_threshold = results.params.coverage_threshold;
-                        self.results = Some(results);
-                        self.load_error = None;
-                        self.current_tab = Tab::Results;
+            // Binary files are opened lazily: cell details load on demand
+            let loaded = if is_binary_results_file(&path) {
+                LazyResultsFile::open(&path).map(|(results, lazy)| (results, Some(lazy)))
+            } else {
+                read_results_file(&path).map(|results| (results, None))
+            };
+            match loaded {
+                Ok((results, lazy)) => {
+                    self.lazy_results = lazy;
+                    self.view_coverage_threshold = results.params.coverage_threshold;
+                    self.detail_rerun_params = results.params.clone();
+                    self.detail_rerun_result = None;
+                    self.results = Some(results);
+                    self.clear_result_views();
+                    self.load_error = None;
+                    self.current_tab = Tab::Results;
+                }
+                Err(e) => {
+                    self.load_error = Some(e);
+                }
+            }
+        }
+    }
+
+    /// Load one side of the run comparison and recompute the diff if both
+    /// sides are present.
+    fn load_comparison_run(&mut self, after: bool) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Results", &["json", "osrb"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match read_results_file(&path) {
+            Ok(results) => {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if after {
+                    self.compare_after = Some((name, results));
+                } else {
+                    self.compare_before = Some((name, results));
+                }
+                self.compare_error = None;
+                self.compare_result = None;
+                if let (Some((_, before)), Some((_, after))) =
+                    (&self.compare_before, &self.compare_after)
+                {
+                    match compare_results(before, after) {
+                        Ok(cmp) => self.compare_result = Some(cmp),
+                        Err(e) => self.compare_error = Some(e),
                     }
-                    Err(e) => {
-                        self.load_error = Some(format!("Failed to parse: {}", e));
+                }
+            }
+            Err(e) => {
+                self.compare_error = Some(e);
+            }
+        }
+    }
+
+    fn viewer_settings(&self) -> ViewerSettings {
+        ViewerSettings {
+            coverage_threshold: self.view_coverage_threshold,
+            color_green_at: self.color_green_at,
+            color_red_at: self.color_red_at,
+            nomatch_ok_percent: self.nomatch_ok_percent,
+            nomatch_bad_percent: self.nomatch_bad_percent,
+            zoom_level: self.zoom_level,
+            row_height: self.row_height,
+            show_reverse_complement: self.detail_show_reverse_complement,
+            show_codon_spacing: self.detail_show_codon_spacing,
+            show_entropy_track: self.show_entropy_track,
+            show_matched_track: self.show_matched_track,
+            show_mismatch_track: self.show_mismatch_track,
+        }
+    }
+
+    fn ui_selections(&self) -> UiSelections {
+        UiSelections {
+            method: match self.method_selection {
+                MethodSelection::NoAmbiguities => MethodChoice::NoAmbiguities,
+                MethodSelection::FixedAmbiguities => MethodChoice::FixedAmbiguities,
+                MethodSelection::Incremental => MethodChoice::Incremental,
+            },
+            incremental_limit_ambiguities: self.incremental_limit_ambiguities,
+            incremental_max_ambiguities: self.incremental_max_ambiguities,
+            manual_thread_count: match self.thread_selection {
+                ThreadSelection::Auto => None,
+                ThreadSelection::Manual => Some(self.manual_thread_count),
+            },
+        }
+    }
+
+    fn save_project(&mut self) {
+        if self.project_include_results {
+            if let Err(e) = self.ensure_all_details() {
+                self.save_error = Some(e);
+                return;
+            }
+        }
+        let mut project = ProjectFile::new(
+            self.template_input.clone(),
+            self.reference_input.clone(),
+            self.params.clone(),
+            self.viewer_settings(),
+            self.ui_selections(),
+            if self.project_include_results {
+                self.results.clone()
+            } else {
+                None
+            },
+            self.candidates.clone(),
+        );
+        project.template_origin = self.template_origin.clone();
+        project.reference_file = self
+            .reference_file
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned());
+        project.read_options = self.read_options;
+        project.reference_filter = self.reference_filter;
+        if self.unfiltered_references.is_some() {
+            project.reference_filter_report = self.reference_filter_report.clone();
+        }
+
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Oligoscreen Project", &["oligoproj"])
+            .set_file_name("project.oligoproj")
+            .save_file()
+        {
+            match project.to_json() {
+                Ok(json) => {
+                    if let Err(e) = std::fs::write(&path, json) {
+                        self.save_error = Some(format!("Failed to write file: {}", e));
+                    } else {
+                        self.save_error = None;
                     }
-                },
+                }
                 Err(e) => {
-                    self.load_error = Some(format!("Failed to read file: {}", e));
+                    self.save_error = Some(e);
+                }
+            }
+        }
+    }
+
+    /// Open a project file and restore inputs, parameters, viewer state and
+    /// (if present) results.
+    fn open_project(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Oligoscreen Project", &["oligoproj"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        let project = match std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read file: {}", e))
+            .and_then(|json| ProjectFile::from_json(&json))
+        {
+            Ok(project) => project,
+            Err(e) => {
+                self.load_error = Some(e);
+                return;
+            }
+        };
+
+        self.load_error = if project.is_newer_than_supported() {
+            Some(format!(
+                "Project was written by a newer version (format {}); unknown settings were ignored",
+                project.version
+            ))
+        } else {
+            None
+        };
+
+        self.template_input = project.template_fasta;
+        self.parse_template_input();
+        self.template_origin = project.template_origin;
+        self.read_options = project.read_options;
+        self.reference_filter = project.reference_filter;
+        self.reference_input = project.reference_fasta;
+        self.parse_reference_input();
+        if let Some(path) = project.reference_file {
+            self.start_reference_file_load(PathBuf::from(path));
+            self.pending_filter_report = project.reference_filter_report;
+        } else if let Some(report) = project.reference_filter_report {
+            self.restore_reference_filter(report);
+        }
+        self.params = project.params;
+
+        let viewer = project.viewer;
+        self.view_coverage_threshold = viewer.coverage_threshold;
+        self.color_green_at = viewer.color_green_at;
+        self.color_red_at = viewer.color_red_at;
+        self.nomatch_ok_percent = viewer.nomatch_ok_percent;
+        self.nomatch_bad_percent = viewer.nomatch_bad_percent;
+        self.zoom_level = viewer.zoom_level;
+        self.row_height = viewer.row_height;
+        self.detail_show_reverse_complement = viewer.show_reverse_complement;
+        self.detail_show_codon_spacing = viewer.show_codon_spacing;
+        self.show_entropy_track = viewer.show_entropy_track;
+        self.show_matched_track = viewer.show_matched_track;
+        self.show_mismatch_track = viewer.show_mismatch_track;
+
+        let ui = project.ui;
+        self.method_selection = match ui.method {
+            MethodChoice::NoAmbiguities => MethodSelection::NoAmbiguities,
+            MethodChoice::FixedAmbiguities => MethodSelection::FixedAmbiguities,
+            MethodChoice::Incremental => MethodSelection::Incremental,
+        };
+        self.incremental_limit_ambiguities = ui.incremental_limit_ambiguities;
+        self.incremental_max_ambiguities = ui.incremental_max_ambiguities;
+        match ui.manual_thread_count {
+            Some(n) => {
+                self.thread_selection = ThreadSelection::Manual;
+                self.manual_thread_count = n;
+            }
+            None => self.thread_selection = ThreadSelection::Auto,
+        }
+
+        self.candidates = project.candidates;
+        self.show_detail_window = false;
+        self.detail_rerun_result = None;
+        self.lazy_results = None;
+        self.clear_result_views();
+        match project.results {
+            Some(results) => {
+                self.detail_rerun_params = results.params.clone();
+                let run_threshold = results.params.coverage_threshold;
+                self.results = Some(results);
+                if self.view_coverage_threshold != run_threshold {
+                    self.recalculate_coverage_threshold();
                 }
+                self.current_tab = Tab::Results;
+            }
+            None => {
+                self.results = None;
+                self.current_tab = Tab::Input;
             }
         }
     }
 
     fn load_template_file(&mut self) {
         if let Some(path) = rfd::FileDialog::new()
-            .add_filter("FASTA", &["fasta", "fa", "fna", "fas", "txt"])
+            .add_filter("FASTA", FASTA_EXTENSIONS)
             .pick_file()
         {
-            match std::fs::read_to_string(&path) {
+            match read_text_file(&path) {
                 Ok(content) => {
                     self.template_input = content;
                     self.parse_template_input();
                 }
                 Err(e) => {
-                    self.template_error = Some(format!("Failed to read file: {}", e));
+                    self.template_error = Some(e);
                 }
             }
         }
     }
 
-    fn load_reference_file(&mut self) {
+    fn load_exclusion_file(&mut self) {
         if let Some(path) = rfd::FileDialog::new()
-            .add_filter("FASTA", &["fasta", "fa", "fna", "fas", "txt"])
+            .add_filter("FASTA", FASTA_EXTENSIONS)
             .pick_file()
         {
-            match std::fs::read_to_string(&path) {
+            match read_text_file(&path) {
                 Ok(content) => {
-                    self.reference_input = content;
-                    self.parse_reference_input();
+                    self.exclusion_input = content;
+                    self.parse_exclusion_input();
                 }
                 Err(e) => {
-                    self.reference_error = Some(format!("Failed to read file: {}", e));
+                    self.exclusion_error = Some(e);
                 }
             }
         }
     }
-}
-
-impl AnalysisMethod {
-    fn get_fixed_ambiguities(&self) -> u32 {
-        match self {
-            AnalysisMethod::FixedAmbiguities(n) => *n,
-            _ => 1,
-        }
-    }
 
-    fn get_incremental_pct(&self) -> u32 {
-        match self {
-            AnalysisMethod::Incremental(pct, _) => *pct,
-            _ => 50,
-        }
-    }
-
-    fn get_incremental_max_amb(&self) -> Option<u32> {
-        match self {
-            AnalysisMethod::Incremental(_, max_amb) => *max_amb,
-            _ => None,
+    fn load_reference_file(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("FASTA / FASTQ", READ_FILE_EXTENSIONS)
+            .pick_file()
+        {
+            self.start_reference_file_load(path);
         }
     }
 }
@@ -392,11 +1679,59 @@ impl eframe::App for OligoscreenApp {
             ctx.request_repaint();
         }
 
+        if self.detail_rerun_rx.is_some() {
+            self.check_window_rerun();
+            ctx.request_repaint();
+        }
+
+        if self.reference_load_rx.is_some() {
+            self.check_reference_load();
+            ctx.request_repaint();
+        }
+
+        if self.reference_filter_rx.is_some() {
+            self.check_reference_filter();
+            ctx.request_repaint();
+        }
+
+        if let Some(rx) = &self.image_export_rx {
+            if let Ok(status) = rx.try_recv() {
+                self.image_export_status = Some(status);
+                self.image_export_rx = None;
+            }
+            ctx.request_repaint();
+        }
+
+        if self.oligo_check_rx.is_some() {
+            self.check_oligo_check_progress();
+            ctx.request_repaint();
+        }
+
+        if self.specificity_rx.is_some() {
+            self.check_specificity_progress();
+            ctx.request_repaint();
+        }
+
+        if self.panel_rx.is_some() {
+            self.check_panel_screening();
+            ctx.request_repaint();
+        }
+
+        if self.template_build_rx.is_some() {
+            self.check_template_build();
+            ctx.request_repaint();
+        }
+
         if self.pending_save {
             self.pending_save = false;
             self.save_results();
         }
 
+        if self.pending_add_references {
+            self.pending_add_references = false;
+            self.add_references_to_results();
+        }
+
         // Top menu bar
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
@@ -418,6 +1753,33 @@ impl eframe::App for OligoscreenApp {
                         self.save_results();
                         ui.close_menu();
                     }
+                    if ui
+                        .add_enabled(self.results.is_some(), egui::Button::new("Export Tables..."))
+                        .clicked()
+                    {
+                        self.show_export_window = true;
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(
+                            self.results.is_some(),
+                            egui::Button::new("Export Heatmap Image..."),
+                        )
+                        .clicked()
+                    {
+                        self.show_image_export_window = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Open Project...").clicked() {
+                        self.open_project();
+                        ui.close_menu();
+                    }
+                    if ui.button("Save Project...").clicked() {
+                        self.save_project();
+                        ui.close_menu();
+                    }
+                    ui.checkbox(&mut self.project_include_results, "Include results in project");
                 });
             });
         });
@@ -428,6 +1790,15 @@ impl eframe::App for OligoscreenApp {
                 ui.selectable_value(&mut self.current_tab, Tab::Input, "Input Data");
                 ui.selectable_value(&mut self.current_tab, Tab::Analysis, "Analysis Setup");
                 ui.selectable_value(&mut self.current_tab, Tab::Results, "Results");
+                ui.selectable_value(&mut self.current_tab, Tab::Compare, "Compare Runs");
+                ui.selectable_value(&mut self.current_tab, Tab::OligoCheck, "Oligo Check");
+                ui.selectable_value(&mut self.current_tab, Tab::Multiplex, "Multiplex Panel");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.toggle_value(
+                        &mut self.show_basket,
+                        format!("Candidates ({})", self.candidates.len()),
+                    );
+                });
             });
         });
 
@@ -442,9 +1813,13 @@ impl eframe::App for OligoscreenApp {
                         ui.label("Starting analysis...");
                     }
                 } else if let Some(ref results) = self.results {
+                    let origin = match results.template_origin {
+                        TemplateOrigin::Provided => String::new(),
+                        ref origin => format!(" ({})", origin.description()),
+                    };
                     ui.label(format!(
-                        "Results: {} references, {} bp template",
-                        results.total_sequences, results.template_length
+                        "Results: {} references, {} bp template{}",
+                        results.total_sequences, results.template_length, origin
                     ));
                 } else {
                     let mut parts = Vec::new();
@@ -463,12 +1838,21 @@ impl eframe::App for OligoscreenApp {
             });
         });
 
+        if self.show_basket {
+            egui::SidePanel::right("candidate_basket")
+                .default_width(320.0)
+                .show(ctx, |ui| self.show_candidate_basket(ui));
+        }
+
         // Main content
         egui::CentralPanel::default().show(ctx, |ui| {
             match self.current_tab {
                 Tab::Input => self.show_input_tab(ui),
                 Tab::Analysis => self.show_analysis_tab(ui),
                 Tab::Results => self.show_results_tab(ui),
+                Tab::Compare => self.show_compare_tab(ui),
+                Tab::OligoCheck => self.show_oligo_check_tab(ui),
+                Tab::Multiplex => self.show_multiplex_tab(ui),
             }
         });
 
@@ -476,6 +1860,14 @@ impl eframe::App for OligoscreenApp {
         if self.show_detail_window {
             self.show_variant_detail_window(ctx);
         }
+
+        if self.show_export_window {
+            self.show_export_tables_window(ctx);
+        }
+
+        if self.show_image_export_window {
+            self.show_heatmap_image_window(ctx);
+        }
     }
 }
 
@@ -508,7 +1900,29 @@ impl OligoscreenApp {
                 });
             });
 
-            ui.label("Single sequence in FASTA format (A, C, G, T only):");
+            ui.horizontal(|ui| {
+                ui.label("Single sequence in FASTA format (IUPAC codes allowed, lowercase = soft-masked), or derive it from the references:");
+                let enabled = self.reference_data.is_some() && self.template_build_rx.is_none();
+                if ui
+                    .add_enabled(enabled, egui::Button::new("Medoid"))
+                    .on_hover_text("Use the reference closest to all others (k-mer distance)")
+                    .clicked()
+                {
+                    self.start_template_build(false);
+                }
+                if ui
+                    .add_enabled(enabled, egui::Button::new("Consensus"))
+                    .on_hover_text(
+                        "Majority-rule consensus of a progressive alignment of the references",
+                    )
+                    .clicked()
+                {
+                    self.start_template_build(true);
+                }
+                if self.template_build_rx.is_some() {
+                    ui.spinner();
+                }
+            });
 
             egui::ScrollArea::vertical()
                 .id_salt("template_scroll")
@@ -529,9 +1943,31 @@ impl OligoscreenApp {
                 ui.colored_label(egui::Color32::RED, format!("Error: {}", error));
             }
             if let Some(ref data) = self.template_data {
+                let ambiguous = data
+                    .sequence
+                    .bytes()
+                    .filter(|b| !b"ACGT".contains(b))
+                    .count();
+                let masked: usize = data.masked.iter().map(|(start, end)| end - start).sum();
+                if ambiguous > 0 || masked > 0 {
+                    ui.label(format!(
+                        "{} ambiguous and {} soft-masked bases (see Template Positions in Analysis Setup)",
+                        ambiguous, masked
+                    ));
+                }
                 ui.colored_label(
                     egui::Color32::from_rgb(100, 200, 100),
-                    format!("Template: {} ({} bp)", data.name, data.sequence.len()),
+                    match self.template_origin {
+                        TemplateOrigin::Provided => {
+                            format!("Template: {} ({} bp)", data.name, data.sequence.len())
+                        }
+                        ref origin => format!(
+                            "Template: {} ({} bp, {})",
+                            data.name,
+                            data.sequence.len(),
+                            origin.description()
+                        ),
+                    },
                 );
             }
         });
@@ -547,8 +1983,20 @@ impl OligoscreenApp {
                         self.reference_input.clear();
                         self.reference_data = None;
                         self.reference_error = None;
+                        self.reference_summary = None;
+                        self.reference_file = None;
+                        self.reference_load_rx = None;
+                        self.reference_load_progress = None;
+                        self.clear_reference_filter();
                     }
-                    if ui.button("Load File").clicked() {
+                    if ui
+                        .add_enabled(
+                            self.reference_load_rx.is_none(),
+                            egui::Button::new("Load File"),
+                        )
+                        .on_hover_text("FASTA or FASTQ, optionally gzip/BGZF or zstd compressed")
+                        .clicked()
+                    {
                         self.load_reference_file();
                     }
                     if ui.button("Load Example").clicked() {
@@ -560,24 +2008,77 @@ impl OligoscreenApp {
 
             ui.label("Multiple sequences in FASTA format (unaligned):");
 
-            egui::ScrollArea::vertical()
-                .id_salt("reference_scroll")
-                .max_height(panel_height)
-                .show(ui, |ui| {
-                    let response = ui.add(
-                        egui::TextEdit::multiline(&mut self.reference_input)
-                            .font(egui::TextStyle::Monospace)
-                            .desired_width(f32::INFINITY)
-                            .desired_rows(6),
+            let options_before = self.read_options;
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.read_options.accept_fastq, "Accept FASTQ");
+                ui.add_enabled_ui(self.read_options.accept_fastq, |ui| {
+                    ui.label("Min. mean quality:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.read_options.min_mean_quality)
+                            .range(0..=60),
                     );
-                    if response.changed() {
-                        self.parse_reference_input();
+                });
+                ui.checkbox(
+                    &mut self.read_options.skip_invalid_records,
+                    "Skip records with invalid characters",
+                );
+            });
+            let options_changed = self.read_options != options_before;
+            if options_changed {
+                self.parse_exclusion_input();
+            }
+
+            if let Some(path) = self.reference_file.clone() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Streamed from {}", path.display()));
+                    let reload = ui
+                        .add_enabled(self.reference_load_rx.is_none(), egui::Button::new("Reload"))
+                        .on_hover_text("Read the file again with the current options");
+                    if reload.clicked() || (options_changed && self.reference_load_rx.is_none())
+                    {
+                        self.start_reference_file_load(path.clone());
                     }
                 });
+                if let Some((bytes_read, size)) = &self.reference_load_progress {
+                    let done = bytes_read.load(Ordering::Relaxed);
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        if *size > 0 {
+                            ui.add(
+                                egui::ProgressBar::new(done as f32 / *size as f32)
+                                    .desired_width(200.0)
+                                    .text(format!(
+                                        "{:.1} / {:.1} MB",
+                                        done as f64 / 1e6,
+                                        *size as f64 / 1e6
+                                    )),
+                            );
+                        }
+                    });
+                }
+            } else {
+                egui::ScrollArea::vertical()
+                    .id_salt("reference_scroll")
+                    .max_height(panel_height)
+                    .show(ui, |ui| {
+                        let response = ui.add(
+                            egui::TextEdit::multiline(&mut self.reference_input)
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(f32::INFINITY)
+                                .desired_rows(6),
+                        );
+                        if response.changed() || options_changed {
+                            self.parse_reference_input();
+                        }
+                    });
+            }
 
             if let Some(ref error) = self.reference_error {
                 ui.colored_label(egui::Color32::RED, format!("Error: {}", error));
             }
+            if let Some(summary) = &self.reference_summary {
+                show_read_summary(ui, summary, "reference");
+            }
             if let Some(ref data) = self.reference_data {
                 let min_len = data.sequences.iter().map(|s| s.len()).min().unwrap_or(0);
                 let max_len = data.sequences.iter().map(|s| s.len()).max().unwrap_or(0);
@@ -591,16 +2092,77 @@ impl OligoscreenApp {
                     ),
                 );
             }
+            if self.reference_data.is_some() {
+                egui::CollapsingHeader::new("Quality filter")
+                    .id_salt("reference_quality_filter")
+                    .show(ui, |ui| self.show_reference_filter(ui));
+                egui::CollapsingHeader::new("Reference table")
+                    .id_salt("reference_table")
+                    .show(ui, |ui| self.show_reference_table(ui));
+            }
         });
-    }
 
-    fn show_analysis_tab(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Analysis Setup");
-        ui.separator();
-
-        // Check if data is loaded
-        let has_template = self.template_data.is_some();
-        let has_references = self.reference_data.is_some();
+        ui.add_space(5.0);
+
+        // --- Exclusion Sequences ---
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Exclusion Sequences");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("Clear").clicked() {
+                        self.exclusion_input.clear();
+                        self.exclusion_data = None;
+                        self.exclusion_error = None;
+                        self.exclusion_summary = None;
+                    }
+                    if ui.button("Load File").clicked() {
+                        self.load_exclusion_file();
+                    }
+                });
+            });
+
+            ui.label(
+                "Optional background set (near neighbors, host) for the specificity screen, \
+                 FASTA (read with the reference options above):",
+            );
+
+            egui::ScrollArea::vertical()
+                .id_salt("exclusion_scroll")
+                .max_height(100.0)
+                .show(ui, |ui| {
+                    let response = ui.add(
+                        egui::TextEdit::multiline(&mut self.exclusion_input)
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(3),
+                    );
+                    if response.changed() {
+                        self.parse_exclusion_input();
+                    }
+                });
+
+            if let Some(ref error) = self.exclusion_error {
+                ui.colored_label(egui::Color32::RED, format!("Error: {}", error));
+            }
+            if let Some(summary) = &self.exclusion_summary {
+                show_read_summary(ui, summary, "exclusion");
+            }
+            if let Some(ref data) = self.exclusion_data {
+                ui.colored_label(
+                    egui::Color32::from_rgb(100, 200, 100),
+                    format!("Exclusion set: {} sequences", data.len()),
+                );
+            }
+        });
+    }
+
+    fn show_analysis_tab(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Analysis Setup");
+        ui.separator();
+
+        // Check if data is loaded
+        let has_template = self.template_data.is_some();
+        let has_references = self.reference_data.is_some();
 
         if !has_template || !has_references {
             ui.colored_label(
@@ -726,6 +2288,56 @@ impl OligoscreenApp {
                     &mut self.params.exclude_n,
                     "Exclude N (any base) as ambiguity code",
                 );
+                ui.checkbox(
+                    &mut self.params.store_match_data,
+                    "Keep per-reference match data (needed for adding references later and group reports; uses more memory)",
+                );
+            });
+
+            ui.add_space(10.0);
+
+            // Ambiguous and soft-masked template positions
+            ui.group(|ui| {
+                ui.heading("Template Positions");
+                ui.horizontal(|ui| {
+                    ui.label("Ambiguity codes in the template:");
+                    for option in [
+                        TemplateAmbiguity::SkipWindows,
+                        TemplateAmbiguity::MajorityReferenceBase,
+                    ] {
+                        ui.radio_value(
+                            &mut self.params.template_ambiguity,
+                            option,
+                            option.description(),
+                        );
+                    }
+                });
+                ui.checkbox(
+                    &mut self.params.exclude_soft_masked,
+                    "Skip windows overlapping soft-masked (lowercase) template regions",
+                );
+            });
+
+            ui.add_space(10.0);
+
+            // Additional templates
+            ui.group(|ui| {
+                ui.heading("Additional Templates");
+                ui.horizontal(|ui| {
+                    ui.label("Representative references screened as templates:");
+                    ui.add(egui::DragValue::new(&mut self.params.extra_templates).range(0..=5));
+                });
+                ui.label(
+                    "The most distinct references are screened too and aligned to the template; \
+                     each cell keeps the best window across templates.",
+                );
+                if self.params.extra_templates > 0 {
+                    ui.label(format!(
+                        "Runs {} screens. Windows starting in an insertion relative to the \
+                         template are listed under the heatmap settings.",
+                        self.params.extra_templates + 1
+                    ));
+                }
             });
 
             ui.add_space(10.0);
@@ -843,6 +2455,9 @@ impl OligoscreenApp {
                     }
                 }
             });
+            if let Some(ref error) = self.analysis_setup_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
         });
     }
 
@@ -862,10 +2477,30 @@ impl OligoscreenApp {
                 if ui.button("Save Results").clicked() && has_results {
                     self.pending_save = true;
                 }
+                if ui.button("Export Image...").clicked() {
+                    self.show_image_export_window = true;
+                }
+                if ui.button("Export Tables...").clicked() {
+                    self.show_export_window = true;
+                }
+                if ui
+                    .add_enabled(!self.is_analyzing, egui::Button::new("Add References..."))
+                    .on_hover_text("Align only new references and merge them into these results")
+                    .clicked()
+                {
+                    self.pending_add_references = true;
+                }
             });
         });
         ui.separator();
 
+        if let Some(ref error) = self.rescreen_error {
+            ui.colored_label(egui::Color32::RED, format!("Error: {}", error));
+        }
+        if let Some(ref notes) = self.rescreen_notes {
+            ui.colored_label(egui::Color32::YELLOW, format!("Added references: {}", notes));
+        }
+
         // Extract data we need
         let (lengths, template_seq, total_seqs) = {
             let results = self.results.as_ref().unwrap();
@@ -878,15 +2513,28 @@ impl OligoscreenApp {
             )
         };
 
+
         if lengths.is_empty() {
             ui.label("No length results available.");
             return;
         }
 
-        // Controls row 1: zoom + info
+        // Controls row 1: zoom, row height, go to position + info
         ui.horizontal(|ui| {
             ui.label("Zoom:");
-            ui.add(egui::Slider::new(&mut self.zoom_level, 0.5..=3.0));
+            ui.add(egui::Slider::new(&mut self.zoom_level, 0.2..=3.0).logarithmic(true));
+            ui.label("Row height:");
+            ui.add(egui::Slider::new(&mut self.row_height, 8.0..=80.0).suffix(" px"));
+            ui.separator();
+            ui.label("Go to:");
+            let goto = ui.add(
+                egui::DragValue::new(&mut self.goto_position).range(1..=template_seq.len().max(1)),
+            );
+            if ui.button("Go").clicked()
+                || (goto.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+            {
+                self.heatmap_scroll_to = Some(self.goto_position - 1);
+            }
             ui.add_space(20.0);
             ui.label(format!(
                 "{} reference sequences | Template: {} bp",
@@ -948,6 +2596,63 @@ impl OligoscreenApp {
             self.nomatch_bad_percent = self.nomatch_ok_percent;
         }
 
+        // Controls row 4: line plots under the heatmap
+        ui.horizontal(|ui| {
+            ui.label("Tracks:");
+            ui.checkbox(&mut self.show_entropy_track, "Entropy")
+                .on_hover_text("Shannon entropy of the matched reference bases (0-2 bits)");
+            ui.checkbox(&mut self.show_matched_track, "Matched fraction")
+                .on_hover_text("Fraction of references with an accepted match");
+            ui.checkbox(&mut self.show_mismatch_track, "Mean mismatches")
+                .on_hover_text("Mean mismatches of the matches against the template oligo");
+            ui.separator();
+            ui.label("Length:");
+            let current = self.track_length.unwrap_or(lengths[0]);
+            egui::ComboBox::from_id_salt("track_length")
+                .selected_text(format!("{} bp", current))
+                .show_ui(ui, |ui| {
+                    for &length in &lengths {
+                        if ui
+                            .selectable_label(current == length, format!("{} bp", length))
+                            .clicked()
+                        {
+                            self.track_length = Some(length);
+                        }
+                    }
+                });
+        });
+
+        egui::CollapsingHeader::new("Best sites")
+            .default_open(false)
+            .show(ui, |ui| self.show_site_ranking(ui));
+
+        self.update_conserved_regions();
+        egui::CollapsingHeader::new("Conserved regions")
+            .default_open(false)
+            .show(ui, |ui| self.show_conserved_regions(ui));
+
+        egui::CollapsingHeader::new("Specificity")
+            .default_open(false)
+            .show(ui, |ui| self.show_specificity_controls(ui));
+
+        egui::CollapsingHeader::new("Inclusivity / exclusivity")
+            .default_open(false)
+            .show(ui, |ui| self.show_group_report(ui));
+
+        egui::CollapsingHeader::new("Motif search")
+            .default_open(false)
+            .show(ui, |ui| self.show_motif_search(ui));
+
+        if self
+            .results
+            .as_ref()
+            .is_some_and(|r| !r.insertion_windows.is_empty())
+        {
+            egui::CollapsingHeader::new("Windows in insertions")
+                .default_open(false)
+                .show(ui, |ui| self.show_insertion_windows(ui));
+        }
+
         ui.add_space(5.0);
 
         // Heatmap display
@@ -971,6 +2676,10 @@ impl OligoscreenApp {
         coverage_threshold: f64,
     ) {
         let results = self.results.as_ref().unwrap();
+        // Soft-masked bases are drawn dimmed (and in lowercase)
+        let masked: Vec<bool> = (0..template_seq.len())
+            .map(|p| results.is_masked(p))
+            .collect();
 
         // Get positions from the first length result
         let first_length_result = results.results_by_length.get(&lengths[0]);
@@ -983,13 +2692,51 @@ impl OligoscreenApp {
             return;
         }
 
-        // Cell dimensions: zoom only affects horizontal width, height is fixed
+        // Cell dimensions: zoom sets the width, row height is set separately
         let cell_w = (14.0 * self.zoom_level).max(3.0);
-        let cell_h: f32 = 54.0;
+        let cell_h = self.row_height;
         let label_width: f32 = 50.0;
         let header_height: f32 = 20.0;
         let pos_label_height: f32 = 14.0;
 
+        // Conserved region brackets as (start column, end column, region)
+        let brackets: Vec<(usize, usize, ConservedRegion)> = match &self.conserved_regions {
+            Some((_, regions)) if self.show_region_brackets => regions
+                .iter()
+                .map(|r| {
+                    (
+                        positions.partition_point(|&p| p < r.start),
+                        positions.partition_point(|&p| p < r.end),
+                        r.clone(),
+                    )
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let bracket_height: f32 = if brackets.is_empty() { 0.0 } else { 12.0 };
+
+        // Motif hits as (start column, end column, row of the motif length)
+        let (motif_marks, motif_exact): (Vec<(usize, usize)>, bool) = match &self.motif_search {
+            Some(Ok(search)) => (
+                search
+                    .hits
+                    .iter()
+                    .map(|h| {
+                        (
+                            positions.partition_point(|&p| p < h.position),
+                            positions.partition_point(|&p| p < h.position + search.len()),
+                        )
+                    })
+                    .collect(),
+                search.exact,
+            ),
+            _ => (Vec::new(), false),
+        };
+        let motif_row = match &self.motif_search {
+            Some(Ok(search)) => lengths.iter().position(|&l| l as usize == search.len()),
+            _ => None,
+        };
+
         let num_cols = positions.len();
         let num_rows = lengths.len();
 
@@ -1029,10 +2776,18 @@ impl OligoscreenApp {
         ui.add_space(5.0);
 
         ui.label(format!(
-            "Variants needed to reach {:.0}% coverage (click cell for details):",
+            "Variants needed to reach {:.0}% coverage (click cell for details, drag across positions to zoom to them):",
             coverage_threshold
         ));
 
+        // Minimap strip over the whole template; painted after the heatmap
+        // so the viewport of this frame can be drawn
+        let minimap_height = (lengths.len() as f32 * 3.0).clamp(12.0, 40.0);
+        let (minimap_rect, minimap_response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), minimap_height),
+            egui::Sense::click_and_drag(),
+        );
+
         // Build heatmap data: lookup by (length, position) -> variants_needed
         let heatmap_data: std::collections::HashMap<(u32, usize), &crate::analysis::PositionResult> =
             {
@@ -1047,14 +2802,79 @@ impl OligoscreenApp {
                 map
             };
 
+        // Line plot tracks: (name, values, axis maximum, color)
+        let track_length = self
+            .track_length
+            .filter(|l| lengths.contains(l))
+            .unwrap_or(lengths[0]);
+        let tracks = position_tracks(results, track_length, &positions);
+        let max_mismatches = tracks
+            .mean_mismatches
+            .iter()
+            .flatten()
+            .fold(1.0f64, |a, &b| a.max(b));
+        let mut track_list: Vec<(&str, &[Option<f64>], f64, egui::Color32)> = Vec::new();
+        if self.show_entropy_track {
+            track_list.push((
+                "Entropy",
+                &tracks.entropy,
+                2.0,
+                egui::Color32::from_rgb(100, 180, 255),
+            ));
+        }
+        if self.show_matched_track {
+            track_list.push((
+                "Matched",
+                &tracks.matched_fraction,
+                1.0,
+                egui::Color32::from_rgb(100, 200, 100),
+            ));
+        }
+        if self.show_mismatch_track {
+            track_list.push((
+                "Mismatch",
+                &tracks.mean_mismatches,
+                max_mismatches,
+                egui::Color32::from_rgb(255, 180, 100),
+            ));
+        }
+        let track_h: f32 = 50.0;
+        let track_gap: f32 = 6.0;
+
         // Total width/height for the heatmap area
         let total_width = label_width + (num_cols as f32 * cell_w);
-        let total_height =
-            pos_label_height + header_height + (num_rows as f32 * cell_h) + 30.0; // +30 for legend
+        let total_height = pos_label_height
+            + bracket_height
+            + header_height
+            + (num_rows as f32 * cell_h)
+            + track_list.len() as f32 * (track_h + track_gap)
+            + 30.0; // +30 for legend
+
+        let mut scroll_area = egui::ScrollArea::horizontal().id_salt("heatmap_scroll");
+        if let Some(target) = self.heatmap_scroll_to.take() {
+            // Center the target column in the visible area
+            let col = positions.partition_point(|&p| p < target);
+            let offset = label_width + col as f32 * cell_w - ui.available_width() / 2.0;
+            scroll_area = scroll_area.horizontal_scroll_offset(offset.max(0.0));
+        }
+        let highlighted_cell = if self.show_detail_window {
+            self.selected_length_for_detail.zip(self.selected_position)
+        } else {
+            None
+        };
 
-        let scroll_output = egui::ScrollArea::horizontal()
-            .id_salt("heatmap_scroll")
-            .show(ui, |ui| {
+        // Closest off-target hit of a cell, if within the shading threshold
+        let specificity = self.specificity.as_ref().filter(|_| self.show_specificity);
+        let close_mismatches = self.specificity_close_mismatches;
+        let off_target_close = |length: u32, pos: usize| {
+            specificity
+                .and_then(|(map, _)| map.get(&(length, pos)))
+                .and_then(|c| c.closest)
+                .filter(|h| h.mismatches <= close_mismatches)
+        };
+
+        let mut box_zoom: Option<(usize, usize)> = None;
+        let scroll_output = scroll_area.show(ui, |ui| {
                 let (response, painter) = ui.allocate_painter(
                     egui::vec2(total_width, total_height),
                     egui::Sense::click_and_drag(),
@@ -1083,8 +2903,48 @@ impl OligoscreenApp {
                     );
                 }
 
+                // --- Conserved region brackets ---
+                let bracket_y = origin.y + pos_label_height;
+                let bracket_color = egui::Color32::from_rgb(120, 220, 160);
+                let mut hovered_region: Option<&ConservedRegion> = None;
+                for (start_col, end_col, region) in &brackets {
+                    let x0 = origin.x + label_width + *start_col as f32 * cell_w;
+                    let x1 = (origin.x + label_width + *end_col as f32 * cell_w - 1.0).max(x0 + 1.0);
+                    let y = bracket_y + 3.0;
+                    let stroke = egui::Stroke::new(2.0, bracket_color);
+                    painter.line_segment([egui::pos2(x0, y), egui::pos2(x1, y)], stroke);
+                    painter.line_segment(
+                        [egui::pos2(x0, y), egui::pos2(x0, bracket_y + bracket_height - 2.0)],
+                        stroke,
+                    );
+                    painter.line_segment(
+                        [egui::pos2(x1, y), egui::pos2(x1, bracket_y + bracket_height - 2.0)],
+                        stroke,
+                    );
+                    let bracket_rect = egui::Rect::from_min_max(
+                        egui::pos2(x0, bracket_y),
+                        egui::pos2(x1, bracket_y + bracket_height),
+                    );
+                    if response
+                        .hover_pos()
+                        .is_some_and(|p| bracket_rect.contains(p))
+                    {
+                        hovered_region = Some(region);
+                    }
+                }
+                if let Some(region) = hovered_region {
+                    response.clone().on_hover_text(format!(
+                        "Conserved region {}-{} ({} bp)\nWorst case: {} variants\nMean coverage: {:.1}%",
+                        region.start + 1,
+                        region.end,
+                        region.len(),
+                        region.worst_variants,
+                        region.mean_coverage
+                    ));
+                }
+
                 // --- Template sequence row ---
-                let seq_y_start = origin.y + pos_label_height;
+                let seq_y_start = bracket_y + bracket_height;
                 // Only draw base letters when cells are wide enough to read
                 if cell_w >= 8.0 {
                     for (col, &pos) in positions.iter().enumerate() {
@@ -1094,7 +2954,13 @@ impl OligoscreenApp {
                                 origin.x + label_width + (col as f32 * cell_w) + cell_w / 2.0;
                             let y = seq_y_start + header_height / 2.0;
 
-                            let color = base_color(base.chars().next().unwrap_or('N'));
+                            let mut color = base_color(base.chars().next().unwrap_or('N'));
+                            let base = if masked[pos] {
+                                color = color.gamma_multiply(0.45);
+                                base.to_ascii_lowercase()
+                            } else {
+                                base.to_string()
+                            };
                             painter.text(
                                 egui::pos2(x, y),
                                 egui::Align2::CENTER_CENTER,
@@ -1109,7 +2975,10 @@ impl OligoscreenApp {
                     for (col, &pos) in positions.iter().enumerate() {
                         if pos < template_seq.len() {
                             let base_char = template_seq.as_bytes()[pos] as char;
-                            let color = base_color(base_char);
+                            let mut color = base_color(base_char);
+                            if masked[pos] {
+                                color = color.gamma_multiply(0.45);
+                            }
                             let x = origin.x + label_width + (col as f32 * cell_w);
                             let tick_rect = egui::Rect::from_min_size(
                                 egui::pos2(x, seq_y_start + 2.0),
@@ -1122,13 +2991,18 @@ impl OligoscreenApp {
 
                 // --- Row labels (oligo lengths) ---
                 let grid_y_start = seq_y_start + header_height;
+                let label_every_n = (8.0 / cell_h).ceil().max(1.0) as usize;
+                let label_font = egui::FontId::proportional((cell_h - 1.0).clamp(7.0, 11.0));
                 for (row, &length) in lengths.iter().enumerate() {
+                    if row % label_every_n != 0 {
+                        continue;
+                    }
                     let y = grid_y_start + (row as f32 * cell_h) + cell_h / 2.0;
                     painter.text(
                         egui::pos2(origin.x + label_width - 5.0, y),
                         egui::Align2::RIGHT_CENTER,
                         format!("{} bp", length),
-                        egui::FontId::proportional(11.0),
+                        label_font.clone(),
                         egui::Color32::LIGHT_GRAY,
                     );
                 }
@@ -1170,6 +3044,21 @@ impl OligoscreenApp {
                         };
 
                         painter.rect_filled(cell_rect, 1.0, color);
+                        if off_target_close(length, pos).is_some() {
+                            painter.rect_filled(
+                                cell_rect,
+                                1.0,
+                                egui::Color32::from_rgba_unmultiplied(120, 0, 160, 150),
+                            );
+                        }
+                        if highlighted_cell == Some((length, pos)) {
+                            painter.rect_stroke(
+                                cell_rect,
+                                1.0,
+                                egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 180, 255)),
+                                egui::StrokeKind::Outside,
+                            );
+                        }
 
                         // Check hover/click using the response's pointer
                         if let Some(pointer_pos) = response.hover_pos() {
@@ -1194,10 +3083,125 @@ impl OligoscreenApp {
                     }
                 }
 
+                let grid_rect = egui::Rect::from_min_size(
+                    egui::pos2(origin.x + label_width, grid_y_start),
+                    egui::vec2(num_cols as f32 * cell_w, num_rows as f32 * cell_h),
+                );
+
+                // --- Motif hits: shaded columns, outlined cell at the motif length ---
+                let motif_color = if motif_exact {
+                    egui::Color32::from_rgb(255, 220, 0)
+                } else {
+                    egui::Color32::from_rgb(255, 140, 0)
+                };
+                for &(start_col, end_col) in &motif_marks {
+                    let x0 = grid_rect.left() + start_col as f32 * cell_w;
+                    let x1 = (grid_rect.left() + end_col as f32 * cell_w).max(x0 + 2.0);
+                    painter.rect_filled(
+                        egui::Rect::from_x_y_ranges(x0..=x1, grid_rect.y_range()),
+                        0.0,
+                        motif_color.gamma_multiply(0.15),
+                    );
+                    if let Some(row) = motif_row {
+                        let cell_rect = egui::Rect::from_min_size(
+                            egui::pos2(x0, grid_y_start + row as f32 * cell_h),
+                            egui::vec2(cell_w - 1.0, cell_h - 1.0),
+                        );
+                        painter.rect_stroke(
+                            cell_rect,
+                            1.0,
+                            egui::Stroke::new(2.0, motif_color),
+                            egui::StrokeKind::Outside,
+                        );
+                    }
+                }
+
+                // --- Box selection: drag across columns to zoom to them ---
+                let col_at = |x: f32| {
+                    (((x - grid_rect.left()) / cell_w).max(0.0) as usize).min(num_cols - 1)
+                };
+                if response.drag_started() {
+                    self.heatmap_box = ui
+                        .ctx()
+                        .pointer_latest_pos()
+                        .filter(|p| grid_rect.contains(*p))
+                        .map(|p| (col_at(p.x), col_at(p.x)));
+                }
+                if let Some((start, current)) = &mut self.heatmap_box {
+                    if let Some(p) = ui.ctx().pointer_latest_pos() {
+                        *current = col_at(p.x);
+                    }
+                    let (c0, c1) = (*start.min(current), *start.max(current));
+                    let selection = egui::Rect::from_x_y_ranges(
+                        grid_rect.left() + c0 as f32 * cell_w
+                            ..=grid_rect.left() + (c1 + 1) as f32 * cell_w,
+                        grid_rect.y_range(),
+                    );
+                    painter.rect(
+                        selection,
+                        0.0,
+                        egui::Color32::from_rgba_unmultiplied(100, 180, 255, 40),
+                        egui::Stroke::new(1.0, egui::Color32::from_rgb(100, 180, 255)),
+                        egui::StrokeKind::Inside,
+                    );
+                    if response.drag_stopped() {
+                        box_zoom = Some((c0, c1));
+                        self.heatmap_box = None;
+                    }
+                }
+
+                // --- Line plot tracks (share the heatmap's horizontal scroll) ---
+                let mut hovered_track: Option<String> = None;
+                let mut track_y = grid_y_start + num_rows as f32 * cell_h + track_gap;
+                for (name, values, max, color) in &track_list {
+                    let rect = egui::Rect::from_min_size(
+                        egui::pos2(origin.x + label_width, track_y),
+                        egui::vec2(num_cols as f32 * cell_w, track_h),
+                    );
+                    draw_track(&painter, rect, values, *max, cell_w, *color);
+                    painter.text(
+                        egui::pos2(origin.x + label_width - 5.0, rect.center().y),
+                        egui::Align2::RIGHT_CENTER,
+                        *name,
+                        egui::FontId::proportional(11.0),
+                        egui::Color32::LIGHT_GRAY,
+                    );
+                    painter.text(
+                        egui::pos2(origin.x + label_width - 5.0, rect.top()),
+                        egui::Align2::RIGHT_TOP,
+                        format!("{:.1}", max),
+                        egui::FontId::proportional(9.0),
+                        egui::Color32::GRAY,
+                    );
+
+                    if let Some(pointer_pos) = response.hover_pos() {
+                        if rect.contains(pointer_pos) {
+                            let col = ((pointer_pos.x - rect.left()) / cell_w) as usize;
+                            if let (Some(value), Some(&pos)) = (values.get(col), positions.get(col))
+                            {
+                                hovered_track = Some(match value {
+                                    Some(v) => format!(
+                                        "Position: {}, {} bp windows\n{}: {:.3}",
+                                        pos + 1,
+                                        track_length,
+                                        name,
+                                        v
+                                    ),
+                                    None => format!("Position: {}\n{}: no data", pos + 1, name),
+                                });
+                            }
+                        }
+                    }
+                    track_y += track_h + track_gap;
+                }
+                if let Some(text) = hovered_track {
+                    response.clone().on_hover_text(text);
+                }
+
                 // Handle tooltip
                 if let Some((length, pos)) = hovered_cell {
                     if let Some(pr) = heatmap_data.get(&(length, pos)) {
-                        let tooltip_text = if pr.analysis.skipped {
+                        let mut tooltip_text = if pr.analysis.skipped {
                             format!(
                                 "Position: {}, Length: {} bp\nSkipped: {}",
                                 pos + 1,
@@ -1219,6 +3223,50 @@ impl OligoscreenApp {
                                 pr.analysis.no_match_count,
                             )
                         };
+                        if let (Some(source), Some(results)) = (pr.source, &self.results) {
+                            tooltip_text.push_str(&format!(
+                                "\nFrom template {} (position {})",
+                                results
+                                    .extra_templates
+                                    .get(source.template)
+                                    .map_or("?", |t| t.name.as_str()),
+                                source.position + 1
+                            ));
+                        }
+                        if let Some(results) = &self.results {
+                            let insertions = results
+                                .insertion_windows
+                                .iter()
+                                .filter(|w| w.oligo_length == length && w.cell.position == pos)
+                                .count();
+                            if insertions > 0 {
+                                tooltip_text.push_str(&format!(
+                                    "\n{} window{} of additional templates in insertions here",
+                                    insertions,
+                                    if insertions == 1 { "" } else { "s" }
+                                ));
+                            }
+                        }
+                        if let Some((map, names)) = &self.specificity {
+                            match map.get(&(length, pos)) {
+                                Some(CellSpecificity {
+                                    closest: Some(hit),
+                                    variant,
+                                }) => tooltip_text.push_str(&format!(
+                                    "\nOff-target: V{} {} mismatch{} to {} ({} strand)",
+                                    variant + 1,
+                                    hit.mismatches,
+                                    if hit.mismatches == 1 { "" } else { "es" },
+                                    names
+                                        .get(hit.background)
+                                        .map(String::as_str)
+                                        .unwrap_or("?"),
+                                    if hit.reverse_strand { "-" } else { "+" }
+                                )),
+                                Some(_) => tooltip_text.push_str("\nOff-target: no hit"),
+                                None => {}
+                            }
+                        }
                         response.clone().on_hover_text(tooltip_text);
                     }
                 }
@@ -1231,6 +3279,94 @@ impl OligoscreenApp {
                 }
             });
 
+        // Zoom so the selected columns fill the visible width
+        if let Some((c0, c1)) = box_zoom {
+            if c1 > c0 {
+                let visible = scroll_output.inner_rect.width() - label_width;
+                self.zoom_level =
+                    (visible / ((c1 - c0 + 1) as f32 * 14.0)).clamp(0.2, 3.0);
+                self.heatmap_scroll_to = Some(positions[(c0 + c1) / 2]);
+                ui.ctx().request_repaint();
+            }
+        }
+
+        // --- Minimap: whole template with the visible part outlined ---
+        let bins = ((minimap_rect.width() / 2.0) as usize).max(1);
+        if self.heatmap_overview.as_ref().is_none_or(|(b, _)| *b != bins) {
+            self.heatmap_overview =
+                Some((bins, heatmap_overview(results, lengths, &positions, bins)));
+        }
+        if let Some((_, overview)) = &self.heatmap_overview {
+            let painter = ui.painter_at(minimap_rect);
+            painter.rect_filled(minimap_rect, 0.0, egui::Color32::from_rgb(30, 30, 30));
+            let row_h = minimap_rect.height() / overview.len().max(1) as f32;
+            for (row, cells) in overview.iter().enumerate() {
+                let bin_w = minimap_rect.width() / cells.len().max(1) as f32;
+                for (bin, cell) in cells.iter().enumerate() {
+                    let color = match cell {
+                        Some(c) => position_color(
+                            c.variants_needed,
+                            c.no_match_fraction,
+                            self.color_green_at,
+                            self.color_red_at,
+                            self.nomatch_ok_percent / 100.0,
+                            self.nomatch_bad_percent / 100.0,
+                        ),
+                        None => egui::Color32::from_rgb(40, 40, 40),
+                    };
+                    painter.rect_filled(
+                        egui::Rect::from_min_size(
+                            egui::pos2(
+                                minimap_rect.left() + bin as f32 * bin_w,
+                                minimap_rect.top() + row as f32 * row_h,
+                            ),
+                            egui::vec2(bin_w + 0.5, row_h + 0.5),
+                        ),
+                        0.0,
+                        color,
+                    );
+                }
+            }
+
+            let grid_width = num_cols as f32 * cell_w;
+            let visible_width = scroll_output.inner_rect.width();
+            let offset = scroll_output.state.offset.x;
+            let to_minimap = |content_x: f32| {
+                minimap_rect.left()
+                    + ((content_x - label_width) / grid_width).clamp(0.0, 1.0)
+                        * minimap_rect.width()
+            };
+            let viewport = egui::Rect::from_x_y_ranges(
+                to_minimap(offset)..=to_minimap(offset + visible_width),
+                minimap_rect.y_range(),
+            );
+            painter.rect_stroke(
+                viewport,
+                0.0,
+                egui::Stroke::new(1.5, egui::Color32::WHITE),
+                egui::StrokeKind::Inside,
+            );
+
+            if minimap_response.clicked() || minimap_response.dragged() {
+                if let Some(p) = minimap_response.interact_pointer_pos() {
+                    let fraction = ((p.x - minimap_rect.left()) / minimap_rect.width()).clamp(0.0, 1.0);
+                    let mut state = scroll_output.state;
+                    state.offset.x = (label_width + fraction * grid_width - visible_width / 2.0)
+                        .clamp(0.0, (total_width - visible_width).max(0.0));
+                    state.store(ui.ctx(), scroll_output.id);
+                    ui.ctx().request_repaint();
+                }
+            }
+            if let Some(p) = minimap_response.hover_pos() {
+                let fraction = ((p.x - minimap_rect.left()) / minimap_rect.width()).clamp(0.0, 1.0);
+                let col = ((fraction * num_cols as f32) as usize).min(num_cols - 1);
+                minimap_response.on_hover_text(format!(
+                    "Position {}: click or drag to jump",
+                    positions[col] + 1
+                ));
+            }
+        }
+
         // Redirect vertical mouse wheel to horizontal scroll when hovering over heatmap
         if let Some(hover_pos) = ui.ctx().pointer_hover_pos() {
             if scroll_output.inner_rect.contains(hover_pos) {
@@ -1309,232 +3445,2577 @@ impl OligoscreenApp {
             ui.painter()
                 .rect_filled(rect, 2.0, egui::Color32::from_rgb(40, 40, 40));
             ui.label("skipped/no data");
+
+            if self.show_specificity && self.specificity.is_some() {
+                ui.separator();
+                let (rect, _) =
+                    ui.allocate_exact_size(egui::vec2(15.0, 15.0), egui::Sense::hover());
+                ui.painter().rect_filled(rect, 2.0, egui::Color32::from_rgb(70, 0, 100));
+                ui.label(format!(
+                    "off-target within {} mismatches",
+                    self.specificity_close_mismatches
+                ));
+            }
         });
     }
 
-    fn show_variant_detail_window(&mut self, ctx: &egui::Context) {
-        let Some(ref results) = self.results else {
-            self.show_detail_window = false;
-            return;
-        };
+    fn show_site_ranking(&mut self, ui: &mut egui::Ui) {
+        let mut rank_clicked = false;
+        let w = &mut self.ranking_weights;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Weights - variants:");
+            ui.add(egui::DragValue::new(&mut w.variants).range(0.0..=10.0).speed(0.05));
+            ui.label("coverage:");
+            ui.add(egui::DragValue::new(&mut w.coverage).range(0.0..=10.0).speed(0.05));
+            ui.label("no-match:");
+            ui.add(egui::DragValue::new(&mut w.no_match).range(0.0..=10.0).speed(0.05));
+            ui.label("Tm/GC:");
+            ui.add(egui::DragValue::new(&mut w.thermo).range(0.0..=10.0).speed(0.05));
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Target Tm:");
+            ui.add(
+                egui::DragValue::new(&mut w.target_tm)
+                    .range(30.0..=90.0)
+                    .suffix(" °C"),
+            );
+            ui.label("±");
+            ui.add(
+                egui::DragValue::new(&mut w.tm_tolerance)
+                    .range(1.0..=40.0)
+                    .suffix(" °C"),
+            );
+            ui.label("GC range:");
+            ui.add(egui::DragValue::new(&mut w.gc_min).range(0.0..=100.0).suffix("%"));
+            ui.label("-");
+            ui.add(egui::DragValue::new(&mut w.gc_max).range(0.0..=100.0).suffix("%"));
+            if w.gc_min > w.gc_max {
+                w.gc_max = w.gc_min;
+            }
+            ui.separator();
+            ui.label("Top:");
+            ui.add(egui::DragValue::new(&mut self.ranking_top_n).range(1..=500));
+            rank_clicked = ui.button("Rank").clicked();
+        });
 
-        let Some(length) = self.selected_length_for_detail else {
-            self.show_detail_window = false;
-            return;
-        };
+        if rank_clicked {
+            if let Some(results) = &self.results {
+                self.site_ranking = Some(rank_sites(
+                    results,
+                    &self.ranking_weights,
+                    self.ranking_top_n,
+                ));
+            }
+        }
 
-        let Some(position) = self.selected_position else {
-            self.show_detail_window = false;
+        let Some(ranking) = &self.site_ranking else {
+            ui.label("Click Rank to score all windows. Overlapping windows are listed once.");
             return;
         };
-
-        let Some(length_result) = results.results_by_length.get(&length) else {
-            self.show_detail_window = false;
+        if ranking.is_empty() {
+            ui.label("No analyzed windows to rank.");
             return;
-        };
+        }
 
-        let Some(pos_result) = length_result
-            .positions
-            .iter()
-            .find(|p| p.position == position)
-        else {
-            self.show_detail_window = false;
-            return;
-        };
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .id_salt("ranking_scroll")
+            .max_height(220.0)
+            .show(ui, |ui| {
+                egui::Grid::new("ranking_grid")
+                    .striped(true)
+                    .min_col_width(40.0)
+                    .show(ui, |ui| {
+                        for header in [
+                            "#", "Position", "Length", "Score", "Variants", "Coverage",
+                            "No match", "Tm", "GC",
+                        ] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for (i, site) in ranking.iter().enumerate() {
+                            let selected = self.show_detail_window
+                                && self.selected_position == Some(site.position)
+                                && self.selected_length_for_detail == Some(site.oligo_length);
+                            if ui
+                                .selectable_label(selected, format!("{}", i + 1))
+                                .on_hover_text("Show in heatmap and open details")
+                                .clicked()
+                            {
+                                clicked = Some(site);
+                            }
+                            ui.label(format!("{}", site.position + 1));
+                            ui.label(format!("{} bp", site.oligo_length));
+                            ui.label(format!("{:.3}", site.score));
+                            ui.label(format!("{}", site.variants_needed));
+                            ui.label(format!("{:.1}%", site.coverage));
+                            ui.label(format!("{:.1}%", site.no_match_fraction * 100.0));
+                            ui.label(format!("{:.1} °C", site.tm));
+                            ui.label(format!("{:.0}%", site.gc));
+                            ui.end_row();
+                        }
+                    });
+            });
 
-        let pos_result = pos_result.clone();
-        let coverage_threshold = results.params.coverage_threshold;
+        if let Some(site) = clicked {
+            self.selected_position = Some(site.position);
+            self.selected_length_for_detail = Some(site.oligo_length);
+            self.show_detail_window = true;
+            self.heatmap_scroll_to = Some(site.position);
+        }
+    }
 
-        // Extract template oligo for display
-        let template_oligo = if position + length as usize <= results.template_sequence.len() {
-            &results.template_sequence[position..position + length as usize]
+    /// Drop views derived from the current results (after they change)
+    fn clear_result_views(&mut self) {
+        self.site_ranking = None;
+        self.conserved_regions = None;
+        self.heatmap_overview = None;
+        self.motif_search = None;
+        self.specificity = None;
+        // A screen still running belongs to the old results
+        self.specificity_rx = None;
+        self.specificity_progress_rx = None;
+        self.specificity_progress = None;
+        self.group_report = None;
+    }
+
+    fn show_specificity_controls(&mut self, ui: &mut egui::Ui) {
+        let running = self.specificity_rx.is_some();
+        let mut start_clicked = false;
+        ui.horizontal_wrapped(|ui| {
+            match &self.exclusion_data {
+                Some(data) => ui.label(format!("Exclusion set: {} sequences.", data.len())),
+                None => ui.colored_label(
+                    egui::Color32::from_rgb(255, 180, 100),
+                    "Load an exclusion set in the Input Data tab first.",
+                ),
+            };
+            ui.label("Check cells with at most");
+            ui.add(egui::DragValue::new(&mut self.specificity_max_variants).range(1..=50));
+            ui.label("variants.");
+            start_clicked = ui
+                .add_enabled(
+                    !running && self.exclusion_data.is_some(),
+                    egui::Button::new("Screen Specificity"),
+                )
+                .clicked();
+            if let Some((done, total)) = self.specificity_progress {
+                ui.spinner();
+                ui.label(format!("{}/{} variant sequences", done, total));
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(&mut self.show_specificity, "Shade cells with off-target hits within");
+            ui.add(egui::DragValue::new(&mut self.specificity_close_mismatches).range(0..=10));
+            ui.label("mismatches");
+        });
+        if start_clicked {
+            self.start_specificity_screen();
+        }
+
+        if let Some((map, _)) = &self.specificity {
+            let close = map
+                .values()
+                .filter(|c| {
+                    c.closest
+                        .is_some_and(|h| h.mismatches <= self.specificity_close_mismatches)
+                })
+                .count();
+            ui.label(format!(
+                "{} cells checked, {} with an off-target hit within {} mismatches. Hits are gap-free alignments within the max mismatches of Analysis Setup.",
+                map.len(),
+                close,
+                self.specificity_close_mismatches
+            ));
+        }
+    }
+
+    /// Align the variants of all eligible cells against the exclusion set on
+    /// a background thread
+    fn start_specificity_screen(&mut self) {
+        if let Err(e) = self.ensure_all_details() {
+            self.load_error = Some(e);
+            return;
+        }
+        let (Some(results), Some(background)) = (&self.results, &self.exclusion_data) else {
+            return;
+        };
+        let params = SpecificityParams {
+            pairwise: results.params.pairwise,
+            max_cell_variants: self.specificity_max_variants,
+            thread_count: match self.thread_selection {
+                ThreadSelection::Auto => ThreadCount::Auto,
+                ThreadSelection::Manual => ThreadCount::Fixed(self.manual_thread_count),
+            },
+        };
+        let results_clone = results.clone();
+        let background_clone = background.clone();
+
+        let (progress_tx, progress_rx) = channel();
+        let (results_tx, results_rx) = channel();
+        self.specificity_progress_rx = Some(progress_rx);
+        self.specificity_rx = Some(results_rx);
+        self.specificity_progress = Some((0, 0));
+
+        thread::spawn(move || {
+            let map = screen_specificity(
+                &results_clone,
+                &background_clone,
+                &params,
+                Some(progress_tx),
+            );
+            let _ = results_tx.send(map);
+        });
+    }
+
+    fn check_specificity_progress(&mut self) {
+        if let Some(rx) = &self.specificity_progress_rx {
+            while let Ok(progress) = rx.try_recv() {
+                self.specificity_progress = Some(progress);
+            }
+        }
+        if let Some(rx) = &self.specificity_rx {
+            if let Ok(map) = rx.try_recv() {
+                let names = self
+                    .exclusion_data
+                    .as_ref()
+                    .map(|d| d.names.clone())
+                    .unwrap_or_default();
+                self.specificity = Some((map, names));
+                self.specificity_rx = None;
+                self.specificity_progress_rx = None;
+                self.specificity_progress = None;
+            }
+        }
+    }
+
+    fn show_group_report(&mut self, ui: &mut egui::Ui) {
+        let mut load_clicked = false;
+        let mut build_clicked = false;
+        let mut export_clicked = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Group references by");
+            ui.radio_value(&mut self.group_use_mapping, false, "header tag");
+            ui.add_enabled(
+                !self.group_use_mapping,
+                egui::TextEdit::singleline(&mut self.group_tag).desired_width(80.0),
+            )
+            .on_hover_text("Tag name, e.g. species for headers with [species=E. coli]");
+            ui.radio_value(&mut self.group_use_mapping, true, "mapping file");
+            load_clicked = ui
+                .button("Load Mapping...")
+                .on_hover_text("Tab-separated: reference ID or header, then group")
+                .clicked();
+            if let Some((name, _)) = &self.group_mapping {
+                ui.label(name);
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Assay:");
+            ui.radio_value(&mut self.group_from_candidates, false, "selected window");
+            ui.radio_value(
+                &mut self.group_from_candidates,
+                true,
+                format!("candidate basket ({})", self.candidates.len()),
+            );
+            build_clicked = ui.button("Build Report").clicked();
+        });
+        ui.label(
+            egui::RichText::new(
+                "A reference is detected when its matched sequence is covered by the oligo variants; with candidates, every candidate must detect it. Target groups pass at 100%, non-target groups at 0%.",
+            )
+            .weak(),
+        );
+
+        if load_clicked {
+            self.load_group_mapping();
+        }
+        if build_clicked {
+            self.build_group_report();
+        }
+
+        match &self.group_report {
+            Some(Ok((label, coverage))) => {
+                ui.label(format!(
+                    "{} groups for {}. Tick the target groups:",
+                    coverage.len(),
+                    label
+                ));
+                egui::ScrollArea::vertical()
+                    .id_salt("group_report")
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("group_report_grid")
+                            .striped(true)
+                            .num_columns(6)
+                            .show(ui, |ui| {
+                                for header in [
+                                    "Target",
+                                    "Group",
+                                    "References",
+                                    "Detected",
+                                    "Coverage",
+                                    "Result",
+                                ] {
+                                    ui.strong(header);
+                                }
+                                ui.end_row();
+                                for group in coverage {
+                                    let mut target = self.group_targets.contains(&group.group);
+                                    if ui.checkbox(&mut target, "").changed() {
+                                        if target {
+                                            self.group_targets.insert(group.group.clone());
+                                        } else {
+                                            self.group_targets.remove(&group.group);
+                                        }
+                                    }
+                                    ui.label(&group.group);
+                                    ui.label(group.references.to_string());
+                                    ui.label(group.detected.to_string());
+                                    ui.label(format!("{:.1}%", group.percent()));
+                                    let (color, result) = if group.passes(target) {
+                                        (egui::Color32::from_rgb(100, 200, 100), "pass")
+                                    } else {
+                                        (egui::Color32::from_rgb(255, 100, 100), "fail")
+                                    };
+                                    ui.colored_label(color, result);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                export_clicked = ui.button("Export CSV...").clicked();
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            None => {}
+        }
+        match &self.group_status {
+            Some(Ok(msg)) => {
+                ui.label(msg);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            None => {}
+        }
+        if export_clicked {
+            self.export_group_report();
+        }
+    }
+
+    fn load_group_mapping(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Mapping", &["tsv", "txt"])
+            .pick_file()
+        {
+            match std::fs::read_to_string(&path) {
+                Ok(content) => {
+                    let name = path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    self.group_mapping = Some((name, content));
+                    self.group_use_mapping = true;
+                    self.group_status = None;
+                }
+                Err(e) => {
+                    self.group_status = Some(Err(format!("Failed to read file: {}", e)));
+                }
+            }
+        }
+    }
+
+    /// Per-group detection of the selected window or the candidate basket
+    fn build_group_report(&mut self) {
+        if let Err(e) = self.ensure_all_details() {
+            self.group_report = Some(Err(e));
+            return;
+        }
+        let Some(results) = &self.results else {
+            return;
+        };
+        let report = (|| {
+            let Some(names) = self.run_reference_names(results) else {
+                return Err(
+                    "Load the reference sequences of this run in the Input Data tab.".to_string(),
+                );
+            };
+            let names = names.as_ref();
+            let groups = if self.group_use_mapping {
+                let Some((_, mapping)) = &self.group_mapping else {
+                    return Err("Load a mapping file first.".to_string());
+                };
+                groups_from_mapping(names, mapping)?
+            } else {
+                groups_from_header_tag(names, &self.group_tag)
+            };
+
+            let (label, detected) = if self.group_from_candidates {
+                if self.candidates.is_empty() {
+                    return Err("The candidate basket is empty.".to_string());
+                }
+                let labels: Vec<&str> = self.candidates.iter().map(|c| c.label.as_str()).collect();
+                (labels.join(" + "), assay_detection(results, &self.candidates)?)
+            } else {
+                let (Some(length), Some(position)) =
+                    (self.selected_length_for_detail, self.selected_position)
+                else {
+                    return Err("Select a heatmap cell first.".to_string());
+                };
+                let cell = results
+                    .results_by_length
+                    .get(&length)
+                    .and_then(|lr| {
+                        lr.positions
+                            .binary_search_by_key(&position, |p| p.position)
+                            .ok()
+                            .map(|i| &lr.positions[i])
+                    })
+                    .ok_or_else(|| "No result for the selected cell.".to_string())?;
+                let variants: Vec<String> = cell
+                    .analysis
+                    .variants
+                    .iter()
+                    .take(cell.variants_needed)
+                    .map(|v| v.sequence.clone())
+                    .collect();
+                (
+                    format!("the {} bp window at position {}", length, position + 1),
+                    detected_references(&cell.analysis, &variants, results.total_sequences)?,
+                )
+            };
+            Ok((label, group_coverage(&groups, &detected)))
+        })();
+        self.group_report = Some(report);
+        self.group_status = None;
+    }
+
+    fn export_group_report(&mut self) {
+        let Some(Ok((_, coverage))) = &self.group_report else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("TSV", &["tsv"])
+            .set_file_name("inclusivity.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let format = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("tsv")) {
+            DelimitedFormat::Tsv
+        } else {
+            DelimitedFormat::Csv
+        };
+        let content = table_to_delimited(&inclusivity_table(coverage, &self.group_targets), format);
+        self.group_status = Some(
+            std::fs::write(&path, content)
+                .map(|_| format!("Wrote {}", path.display()))
+                .map_err(|e| format!("Failed to write file: {}", e)),
+        );
+    }
+
+    fn show_motif_search(&mut self, ui: &mut egui::Ui) {
+        let mut search_clicked = false;
+        ui.horizontal(|ui| {
+            ui.label("Motif:");
+            let edit = ui.add(
+                egui::TextEdit::singleline(&mut self.motif_query)
+                    .hint_text("primer or motif, IUPAC codes allowed")
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(300.0),
+            );
+            search_clicked = ui.button("Search").clicked()
+                || (edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)));
+            if ui.button("Clear").clicked() {
+                self.motif_search = None;
+            }
+        });
+
+        let Some(results) = &self.results else {
+            return;
+        };
+        if search_clicked {
+            self.motif_search = Some(search_motif(
+                &results.template_sequence,
+                &self.motif_query,
+                MOTIF_CLOSEST_WINDOWS,
+            ));
+        }
+
+        let search = match &self.motif_search {
+            Some(Ok(search)) => search,
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+                return;
+            }
+            None => {
+                ui.label("Finds the motif and its reverse complement in the template and marks the hits in the heatmap.");
+                return;
+            }
+        };
+        if search.exact {
+            let reverse = search.hits.iter().filter(|h| h.reverse_complement).count();
+            ui.label(format!(
+                "{} exact hit{} ({} forward, {} reverse complement)",
+                search.hits.len(),
+                if search.hits.len() == 1 { "" } else { "s" },
+                search.hits.len() - reverse,
+                reverse
+            ));
+        } else {
+            ui.colored_label(
+                egui::Color32::from_rgb(255, 180, 100),
+                "The motif does not occur in the template. Closest windows:",
+            );
+        }
+
+        let length = search.len() as u32;
+        let screened = results.results_by_length.get(&length);
+        if screened.is_none() {
+            ui.label(format!(
+                "{} bp was not screened; hits are marked but have no heatmap cell.",
+                length
+            ));
+        }
+
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .id_salt("motif_scroll")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("motif_grid")
+                    .striped(true)
+                    .min_col_width(40.0)
+                    .show(ui, |ui| {
+                        for header in [
+                            "#", "Position", "Strand", "Mismatches", "Template", "Variants",
+                            "Coverage",
+                        ] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for (i, hit) in search.hits.iter().enumerate() {
+                            if ui
+                                .selectable_label(false, format!("{}", i + 1))
+                                .on_hover_text("Show in heatmap")
+                                .clicked()
+                            {
+                                clicked = Some(hit.position);
+                            }
+                            ui.label(format!("{}", hit.position + 1));
+                            ui.label(if hit.reverse_complement { "-" } else { "+" });
+                            ui.label(format!("{}", hit.mismatches));
+                            let window = results
+                                .template_sequence
+                                .get(hit.position..hit.position + search.len())
+                                .unwrap_or("");
+                            ui.monospace(if hit.reverse_complement {
+                                reverse_complement(window)
+                            } else {
+                                window.to_string()
+                            });
+                            let cell = screened.and_then(|lr| {
+                                lr.positions
+                                    .binary_search_by_key(&hit.position, |p| p.position)
+                                    .ok()
+                                    .map(|i| &lr.positions[i])
+                            });
+                            match cell {
+                                Some(p) if !p.analysis.skipped => {
+                                    ui.label(format!("{}", p.variants_needed));
+                                    ui.label(format!("{:.1}%", p.analysis.coverage_at_threshold));
+                                }
+                                Some(_) => {
+                                    ui.label("skipped");
+                                    ui.label("");
+                                }
+                                None => {
+                                    ui.label("-");
+                                    ui.label("-");
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(position) = clicked {
+            self.heatmap_scroll_to = Some(position);
+            if screened.is_some() {
+                self.selected_position = Some(position);
+                self.selected_length_for_detail = Some(length);
+                self.show_detail_window = true;
+            }
+        }
+    }
+
+    /// Recompute conserved regions if the parameters changed since the last run
+    fn update_conserved_regions(&mut self) {
+        let Some(results) = &self.results else {
+            return;
+        };
+        if self
+            .conserved_regions
+            .as_ref()
+            .is_some_and(|(params, _)| *params == self.region_params)
+        {
+            return;
+        }
+        let regions = find_conserved_regions(results, &self.region_params);
+        self.conserved_regions = Some((self.region_params.clone(), regions));
+    }
+
+    fn show_conserved_regions(&mut self, ui: &mut egui::Ui) {
+        let params = &mut self.region_params;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Max variants (every length):");
+            ui.add(egui::DragValue::new(&mut params.max_variants).range(1..=50));
+            ui.label("Max no-match:");
+            ui.add(
+                egui::DragValue::new(&mut params.max_no_match_percent)
+                    .range(0.0..=100.0)
+                    .speed(0.5)
+                    .suffix("%"),
+            );
+            ui.label("Min length:");
+            ui.add(
+                egui::DragValue::new(&mut params.min_length)
+                    .range(1..=10000)
+                    .suffix(" bp"),
+            );
+            ui.checkbox(&mut self.show_region_brackets, "Brackets above heatmap");
+        });
+
+        let Some((_, regions)) = &self.conserved_regions else {
+            return;
+        };
+        let mut export_clicked = false;
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} region{} covering {} bp",
+                regions.len(),
+                if regions.len() == 1 { "" } else { "s" },
+                regions.iter().map(|r| r.len()).sum::<usize>()
+            ));
+            export_clicked = ui
+                .add_enabled(!regions.is_empty(), egui::Button::new("Export BED..."))
+                .clicked();
+        });
+        if let Some(status) = &self.region_status {
+            match status {
+                Ok(msg) => ui.label(msg),
+                Err(e) => ui.colored_label(egui::Color32::RED, e),
+            };
+        }
+        if regions.is_empty() {
+            ui.label("No stretch passes the thresholds at every length.");
+            return;
+        }
+
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .id_salt("regions_scroll")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("regions_grid")
+                    .striped(true)
+                    .min_col_width(40.0)
+                    .show(ui, |ui| {
+                        for header in ["#", "Start", "End", "Length", "Worst variants", "Mean coverage"] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for (i, region) in regions.iter().enumerate() {
+                            if ui
+                                .selectable_label(false, format!("{}", i + 1))
+                                .on_hover_text("Show in heatmap")
+                                .clicked()
+                            {
+                                clicked = Some(region.start);
+                            }
+                            ui.label(format!("{}", region.start + 1));
+                            ui.label(format!("{}", region.end));
+                            ui.label(format!("{} bp", region.len()));
+                            ui.label(format!("{}", region.worst_variants));
+                            ui.label(format!("{:.1}%", region.mean_coverage));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(start) = clicked {
+            self.heatmap_scroll_to = Some(start);
+        }
+        if export_clicked {
+            self.export_regions_bed();
+        }
+    }
+
+    /// Windows of additional templates that start in an insertion relative
+    /// to the template and so have no heatmap cell
+    fn show_insertion_windows(&mut self, ui: &mut egui::Ui) {
+        let Some(results) = &self.results else {
+            return;
+        };
+        ui.label(
+            "Screened windows of additional templates that start in an insertion relative to \
+             the template. They are not part of the heatmap.",
+        );
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .id_salt("insertion_windows_scroll")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("insertion_windows_grid")
+                    .striped(true)
+                    .min_col_width(40.0)
+                    .show(ui, |ui| {
+                        for header in [
+                            "Before",
+                            "Template",
+                            "Position",
+                            "Length",
+                            "Variants",
+                            "Coverage",
+                        ] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for window in &results.insertion_windows {
+                            let Some(source) = window.cell.source else {
+                                continue;
+                            };
+                            if ui
+                                .selectable_label(
+                                    false,
+                                    format!("{}", window.next_template_base + 1),
+                                )
+                                .on_hover_text("Show in heatmap")
+                                .clicked()
+                            {
+                                clicked = Some(window.cell.position);
+                            }
+                            ui.label(
+                                results
+                                    .extra_templates
+                                    .get(source.template)
+                                    .map_or("?", |t| t.name.as_str()),
+                            );
+                            ui.label(format!("{}", source.position + 1));
+                            ui.label(format!("{} bp", window.oligo_length));
+                            ui.label(format!("{}", window.cell.variants_needed));
+                            ui.label(format!(
+                                "{:.1}%",
+                                window.cell.analysis.coverage_at_threshold
+                            ));
+                            ui.end_row();
+                        }
+                    });
+            });
+        if let Some(position) = clicked {
+            self.heatmap_scroll_to = Some(position);
+        }
+    }
+
+    fn export_regions_bed(&mut self) {
+        let Some((_, regions)) = &self.conserved_regions else {
+            return;
+        };
+        let chrom = self
+            .template_data
+            .as_ref()
+            .map(|t| t.name.clone())
+            .unwrap_or_else(|| "template".to_string());
+        let content = regions_to_bed(regions, &chrom);
+
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("BED", &["bed"])
+            .set_file_name("conserved_regions.bed")
+            .save_file()
+        {
+            self.region_status = Some(
+                std::fs::write(&path, content)
+                    .map(|_| format!("Wrote {}", path.display()))
+                    .map_err(|e| format!("Failed to write file: {}", e)),
+            );
+        }
+    }
+
+    fn show_variant_detail_window(&mut self, ctx: &egui::Context) {
+        if let (Some(lazy), Some(results), Some(length), Some(position)) = (
+            &mut self.lazy_results,
+            &mut self.results,
+            self.selected_length_for_detail,
+            self.selected_position,
+        ) {
+            if let Err(e) = lazy.load_cell(results, length, position) {
+                self.load_error = Some(e);
+            }
+        }
+
+        let Some(ref results) = self.results else {
+            self.show_detail_window = false;
+            return;
+        };
+
+        let Some(length) = self.selected_length_for_detail else {
+            self.show_detail_window = false;
+            return;
+        };
+
+        let Some(position) = self.selected_position else {
+            self.show_detail_window = false;
+            return;
+        };
+
+        let Some(length_result) = results.results_by_length.get(&length) else {
+            self.show_detail_window = false;
+            return;
+        };
+
+        let Some(pos_result) = length_result
+            .positions
+            .iter()
+            .find(|p| p.position == position)
+        else {
+            self.show_detail_window = false;
+            return;
+        };
+
+        let pos_result = pos_result.clone();
+        let coverage_threshold = results.params.coverage_threshold;
+
+        // Extract template oligo for display
+        let template_oligo = results.template_oligo(&pos_result, length).to_string();
+        let source_template = pos_result.source.map(|s| {
+            let name = results
+                .extra_templates
+                .get(s.template)
+                .map_or("?", |t| t.name.as_str());
+            (name.to_string(), s.position)
+        });
+
+        let show_reverse_complement = self.detail_show_reverse_complement;
+        let show_codon_spacing = self.detail_show_codon_spacing;
+
+        // Alignment view rows (reference names only if they belong to these results)
+        let run_names = self.run_reference_names(results);
+        let reference_names: &[String] = run_names.as_deref().unwrap_or(&[]);
+        let has_match_data = !pos_result.analysis.matches.is_empty();
+        let alignment_source = if self.detail_alignment_references && has_match_data {
+            AlignmentRowSource::References
+        } else {
+            AlignmentRowSource::Variants
+        };
+        let mut alignment = alignment_rows(
+            &pos_result.analysis,
+            &template_oligo,
+            alignment_source,
+            reference_names,
+            show_reverse_complement,
+        );
+        sort_alignment_rows(&mut alignment, self.detail_alignment_sort);
+        let alignment_template = if show_reverse_complement {
+            reverse_complement(&template_oligo)
+        } else {
+            template_oligo.clone()
+        };
+
+        // Single-window re-run needs the reference set the results were computed from
+        let rerun_blocker = match (&self.reference_data, &run_names) {
+            (None, _) => Some("Load the reference sequences in the Input tab to re-run this window."),
+            (Some(_), None) => Some(
+                "Loaded references differ from the ones used for these results (sequence count mismatch).",
+            ),
+            (Some(_), Some(_)) => None,
+        };
+        let rerun_running = self.detail_rerun_rx.is_some();
+        let rerun_result = self
+            .detail_rerun_result
+            .as_ref()
+            .filter(|(l, p, _)| *l == length && *p == position)
+            .map(|(_, _, r)| r.clone());
+        let rerun_threshold = self.detail_rerun_params.coverage_threshold;
+        let mut rerun_clicked = false;
+        let mut add_candidate_clicked = false;
+
+        egui::Window::new(format!("Position {} Details", position + 1))
+            .open(&mut self.show_detail_window)
+            .default_width(650.0)
+            .default_height(450.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Position: {}", position + 1));
+                    ui.separator();
+                    ui.label(format!("Oligo length: {} bp", length));
+                });
+                if let Some((name, source_position)) = &source_template {
+                    ui.label(format!(
+                        "Best window from additional template {} (position {})",
+                        name,
+                        source_position + 1
+                    ));
+                }
+
+                // Template oligo display
+                if !template_oligo.is_empty() {
+                    let display_template = format_sequence_for_display(
+                        &template_oligo,
+                        show_reverse_complement,
+                        show_codon_spacing,
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Template oligo:");
+                        ui.add(
+                            egui::Label::new(
+                                egui::RichText::new(&display_template)
+                                    .monospace()
+                                    .size(11.0)
+                                    .color(egui::Color32::from_rgb(100, 180, 255)),
+                            )
+                            .wrap_mode(egui::TextWrapMode::Extend),
+                        );
+                    });
+                }
+
+                egui::CollapsingHeader::new("Sequence logo")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        show_sequence_logo(ui, &pos_result.analysis, show_reverse_complement);
+                    });
+
+                ui.separator();
+
+                // Display options
+                ui.horizontal(|ui| {
+                    ui.heading("Variants");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui
+                            .add_enabled(
+                                !pos_result.analysis.skipped,
+                                egui::Button::new("Add to candidates"),
+                            )
+                            .on_hover_text(
+                                "Add the variants needed for the threshold to the candidate basket (in the orientation shown)",
+                            )
+                            .clicked()
+                        {
+                            add_candidate_clicked = true;
+                        }
+                        ui.checkbox(&mut self.detail_show_codon_spacing, "Codon spacing");
+                        ui.checkbox(
+                            &mut self.detail_show_reverse_complement,
+                            "Reverse complement",
+                        );
+                    });
+                });
+
+                if let Some(ref rerun) = rerun_result {
+                    // Side-by-side comparison of the screened result and the re-run
+                    ui.columns(2, |cols| {
+                        cols[0].strong("Original run");
+                        show_window_summary(
+                            &mut cols[0],
+                            &pos_result.analysis,
+                            pos_result.variants_needed,
+                            coverage_threshold,
+                        );
+                        show_variants_grid(
+                            &mut cols[0],
+                            "variants_grid_original",
+                            &pos_result.analysis,
+                            pos_result.variants_needed,
+                            show_reverse_complement,
+                            show_codon_spacing,
+                        );
+
+                        cols[1].strong("Re-run");
+                        show_window_summary(
+                            &mut cols[1],
+                            rerun,
+                            rerun.variants_for_threshold,
+                            rerun_threshold,
+                        );
+                        show_variants_grid(
+                            &mut cols[1],
+                            "variants_grid_rerun",
+                            rerun,
+                            rerun.variants_for_threshold,
+                            show_reverse_complement,
+                            show_codon_spacing,
+                        );
+                    });
+                } else {
+                    show_window_summary(
+                        ui,
+                        &pos_result.analysis,
+                        pos_result.variants_needed,
+                        coverage_threshold,
+                    );
+                    show_variants_grid(
+                        ui,
+                        "variants_grid",
+                        &pos_result.analysis,
+                        pos_result.variants_needed,
+                        show_reverse_complement,
+                        show_codon_spacing,
+                    );
+                }
+
+                egui::CollapsingHeader::new("Alignment view")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Sort by:");
+                            ui.radio_value(
+                                &mut self.detail_alignment_sort,
+                                AlignmentSort::Count,
+                                "Count",
+                            );
+                            ui.radio_value(
+                                &mut self.detail_alignment_sort,
+                                AlignmentSort::Mismatches,
+                                "Mismatches",
+                            );
+                            ui.separator();
+                            ui.add_enabled(
+                                has_match_data,
+                                egui::Checkbox::new(
+                                    &mut self.detail_alignment_references,
+                                    "Individual references",
+                                ),
+                            )
+                            .on_disabled_hover_text(
+                                "Per-reference match data was not kept for these results",
+                            );
+                        });
+                        show_alignment_view(ui, &alignment_template, &alignment);
+                    });
+
+                ui.separator();
+
+                egui::CollapsingHeader::new("Re-run this window")
+                    .id_salt("detail_rerun")
+                    .show(ui, |ui| {
+                        if let Some(reason) = rerun_blocker {
+                            ui.colored_label(egui::Color32::YELLOW, reason);
+                            return;
+                        }
+
+                        let params = &mut self.detail_rerun_params;
+                        edit_analysis_method(ui, "detail_rerun_method", &mut params.method);
+                        ui.horizontal(|ui| {
+                            ui.label("Max mismatches:");
+                            ui.add(
+                                egui::DragValue::new(&mut params.pairwise.max_mismatches)
+                                    .range(0..=50),
+                            );
+                            ui.add_space(10.0);
+                            ui.label("Coverage (%):");
+                            ui.add(
+                                egui::DragValue::new(&mut params.coverage_threshold)
+                                    .range(1.0..=100.0)
+                                    .speed(0.5),
+                            );
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut params.exclude_n, "Exclude N");
+                        });
+                        ui.horizontal(|ui| {
+                            if ui
+                                .add_enabled(!rerun_running, egui::Button::new("Re-run"))
+                                .clicked()
+                            {
+                                rerun_clicked = true;
+                            }
+                            if ui.button("Reset to run parameters").clicked() {
+                                if let Some(ref results) = self.results {
+                                    self.detail_rerun_params = results.params.clone();
+                                }
+                            }
+                            if rerun_running {
+                                ui.spinner();
+                            }
+                        });
+                    });
+            });
+
+        if rerun_clicked {
+            self.start_window_rerun(position, length);
+        }
+
+        if add_candidate_clicked {
+            let variants = pos_result
+                .analysis
+                .variants
+                .iter()
+                .take(pos_result.variants_needed)
+                .map(|v| v.sequence.clone())
+                .collect();
+            self.candidates.push(Candidate::new(
+                length,
+                position,
+                variants,
+                self.detail_show_reverse_complement,
+            ));
+            self.show_basket = true;
+        }
+    }
+
+    fn show_candidate_basket(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Candidate Basket");
+        ui.separator();
+
+        if self.candidates.is_empty() {
+            ui.label("No candidates yet. Use \"Add to candidates\" in a position's detail window.");
+            return;
+        }
+
+        let mut remove = None;
+        egui::ScrollArea::vertical()
+            .id_salt("basket_scroll")
+            .max_height(ui.available_height() - 80.0)
+            .show(ui, |ui| {
+                for (i, c) in self.candidates.iter_mut().enumerate() {
+                    ui.push_id(i, |ui| {
+                        ui.group(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::TextEdit::singleline(&mut c.label).desired_width(50.0));
+                                ui.label(format!(
+                                    "pos {} | {} bp | {} variant(s)",
+                                    c.position + 1,
+                                    c.oligo_length,
+                                    c.variants.len()
+                                ));
+                                ui.with_layout(
+                                    egui::Layout::right_to_left(egui::Align::Center),
+                                    |ui| {
+                                        if ui.small_button("Remove").clicked() {
+                                            remove = Some(i);
+                                        }
+                                    },
+                                );
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut c.reverse_complement, "Reverse complement");
+                                egui::ComboBox::from_id_salt("scale")
+                                    .selected_text(c.scale.clone())
+                                    .width(80.0)
+                                    .show_ui(ui, |ui| {
+                                        for s in ORDER_SCALES {
+                                            ui.selectable_value(&mut c.scale, s.to_string(), s);
+                                        }
+                                    });
+                                egui::ComboBox::from_id_salt("purification")
+                                    .selected_text(c.purification.clone())
+                                    .width(70.0)
+                                    .show_ui(ui, |ui| {
+                                        for p in ORDER_PURIFICATIONS {
+                                            ui.selectable_value(
+                                                &mut c.purification,
+                                                p.to_string(),
+                                                p,
+                                            );
+                                        }
+                                    });
+                            });
+                            for (name, seq) in c.oligos() {
+                                ui.label(
+                                    egui::RichText::new(format!("{}  {}", name, seq))
+                                        .monospace()
+                                        .size(10.0),
+                                );
+                            }
+                        });
+                    });
+                }
+            });
+
+        if let Some(i) = remove {
+            self.candidates.remove(i);
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Export FASTA...").clicked() {
+                self.export_candidates(false);
+            }
+            if ui.button("Export Order Sheet...").clicked() {
+                self.export_candidates(true);
+            }
+            if ui.button("Clear").clicked() {
+                self.candidates.clear();
+            }
+        });
+        match &self.basket_status {
+            Some(Ok(msg)) => {
+                ui.colored_label(egui::Color32::from_rgb(100, 200, 100), msg);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, format!("Error: {}", e));
+            }
+            None => {}
+        }
+    }
+
+    /// Write the basket as FASTA or as a vendor order sheet (CSV)
+    fn export_candidates(&mut self, order_sheet: bool) {
+        let (filter, ext, content) = if order_sheet {
+            ("CSV", "csv", candidates_to_order_csv(&self.candidates))
         } else {
-            ""
+            ("FASTA", "fasta", candidates_to_fasta(&self.candidates))
+        };
+
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter(filter, &[ext])
+            .set_file_name(format!("candidates.{}", ext))
+            .save_file()
+        {
+            self.basket_status = Some(
+                std::fs::write(&path, content)
+                    .map(|_| format!("Wrote {}", path.display()))
+                    .map_err(|e| format!("Failed to write file: {}", e)),
+            );
+        }
+    }
+
+    fn show_export_tables_window(&mut self, ctx: &egui::Context) {
+        let mut export_clicked = false;
+
+        egui::Window::new("Export Tables")
+            .open(&mut self.show_export_window)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Tables:");
+                ui.checkbox(&mut self.export_matrix, "Variants matrix (length × position)");
+                ui.checkbox(&mut self.export_cells, "Cells (coverage, no-match, skip reason)");
+                ui.checkbox(&mut self.export_variants, "Variants (sequence, count, percentage)");
+                ui.horizontal(|ui| {
+                    ui.add_space(20.0);
+                    ui.add_enabled(
+                        self.export_variants,
+                        egui::Checkbox::new(&mut self.export_thermo, "Include GC % and Tm"),
+                    );
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Format:");
+                    ui.radio_value(&mut self.export_format, ExportFormat::Csv, "CSV");
+                    ui.radio_value(&mut self.export_format, ExportFormat::Tsv, "TSV");
+                    ui.radio_value(&mut self.export_format, ExportFormat::Xlsx, "XLSX");
+                });
+                if self.export_format != ExportFormat::Xlsx {
+                    ui.label("Each table is written to its own file next to the chosen name.");
+                } else {
+                    ui.label("Each table is written to its own sheet.");
+                }
+
+                ui.separator();
+                let any = self.export_matrix || self.export_cells || self.export_variants;
+                if ui.add_enabled(any, egui::Button::new("Export...")).clicked() {
+                    export_clicked = true;
+                }
+
+                match &self.export_status {
+                    Some(Ok(msg)) => {
+                        ui.colored_label(egui::Color32::from_rgb(100, 200, 100), msg);
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, format!("Error: {}", e));
+                    }
+                    None => {}
+                }
+            });
+
+        if export_clicked {
+            self.export_tables();
+        }
+    }
+
+    fn export_tables(&mut self) {
+        if let Err(e) = self.ensure_all_details() {
+            self.export_status = Some(Err(e));
+            return;
+        }
+        let Some(results) = &self.results else {
+            return;
         };
-        let template_oligo = template_oligo.to_string();
 
-        let show_reverse_complement = self.detail_show_reverse_complement;
-        let show_codon_spacing = self.detail_show_codon_spacing;
+        // (file suffix, table)
+        let mut tables = Vec::new();
+        if self.export_matrix {
+            tables.push(("matrix", variants_matrix_table(results)));
+        }
+        if self.export_cells {
+            tables.push(("cells", cell_table(results)));
+        }
+        if self.export_variants {
+            tables.push(("variants", variant_table(results, self.export_thermo)));
+        }
 
-        egui::Window::new(format!("Position {} Details", position + 1))
-            .open(&mut self.show_detail_window)
-            .default_width(650.0)
-            .default_height(450.0)
+        let delimited = match self.export_format {
+            ExportFormat::Csv => Some(DelimitedFormat::Csv),
+            ExportFormat::Tsv => Some(DelimitedFormat::Tsv),
+            ExportFormat::Xlsx => None,
+        };
+
+        let Some(delimited) = delimited else {
+            let Some(path) = rfd::FileDialog::new()
+                .add_filter("Excel workbook", &["xlsx"])
+                .set_file_name("screening_results.xlsx")
+                .save_file()
+            else {
+                return;
+            };
+            let tables: Vec<_> = tables.into_iter().map(|(_, t)| t).collect();
+            self.export_status = Some(
+                write_xlsx(&tables, &path).map(|_| format!("Wrote {}", path.display())),
+            );
+            return;
+        };
+
+        let ext = delimited.extension();
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(ext.to_uppercase(), &[ext])
+            .set_file_name(format!("screening_results.{}", ext))
+            .save_file()
+        else {
+            return;
+        };
+
+        // A single table goes to the chosen path; several get a suffix each
+        let single = tables.len() == 1;
+        let mut written = Vec::new();
+        for (suffix, table) in &tables {
+            let target = if single {
+                path.clone()
+            } else {
+                let stem = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "screening_results".to_string());
+                path.with_file_name(format!("{}_{}.{}", stem, suffix, ext))
+            };
+            if let Err(e) = std::fs::write(&target, table_to_delimited(table, delimited)) {
+                self.export_status = Some(Err(format!("Failed to write file: {}", e)));
+                return;
+            }
+            written.push(
+                target
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            );
+        }
+        self.export_status = Some(Ok(format!("Wrote {}", written.join(", "))));
+    }
+
+    fn show_heatmap_image_window(&mut self, ctx: &egui::Context) {
+        let mut export_clicked = false;
+
+        egui::Window::new("Export Heatmap Image")
+            .open(&mut self.show_image_export_window)
+            .resizable(false)
             .show(ctx, |ui| {
+                let settings = &mut self.image_settings;
+                egui::Grid::new("image_export_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Cell width:");
+                        ui.add(egui::Slider::new(&mut settings.cell_width, 2.0..=40.0));
+                        ui.end_row();
+                        ui.label("Cell height:");
+                        ui.add(egui::Slider::new(&mut settings.cell_height, 4.0..=80.0));
+                        ui.end_row();
+                    });
+                ui.checkbox(&mut settings.show_base_track, "Template base track");
+                ui.checkbox(&mut settings.show_legend, "Legend");
+                ui.label("Colors follow the current display settings.");
+
+                ui.separator();
                 ui.horizontal(|ui| {
-                    ui.label(format!("Position: {}", position + 1));
-                    ui.separator();
-                    ui.label(format!("Oligo length: {} bp", length));
+                    ui.label("Format:");
+                    ui.radio_value(&mut self.image_format, ImageFormat::Svg, "SVG");
+                    ui.radio_value(&mut self.image_format, ImageFormat::Png, "PNG");
+                });
+                if self.image_format == ImageFormat::Png {
+                    ui.horizontal(|ui| {
+                        ui.label("Resolution:");
+                        ui.add(
+                            egui::Slider::new(&mut self.image_png_scale, 1.0..=8.0)
+                                .suffix("×")
+                                .step_by(0.5),
+                        );
+                    });
+                }
+
+                ui.separator();
+                let exporting = self.image_export_rx.is_some();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!exporting, egui::Button::new("Export...")).clicked() {
+                        export_clicked = true;
+                    }
+                    if exporting {
+                        ui.spinner();
+                        ui.label("Rendering...");
+                    }
+                });
+
+                match &self.image_export_status {
+                    Some(Ok(msg)) => {
+                        ui.colored_label(egui::Color32::from_rgb(100, 200, 100), msg);
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, format!("Error: {}", e));
+                    }
+                    None => {}
+                }
+            });
+
+        if export_clicked {
+            self.export_heatmap_image();
+        }
+    }
+
+    fn export_heatmap_image(&mut self) {
+        let Some(results) = &self.results else {
+            return;
+        };
+        let settings = HeatmapImageSettings {
+            color_green_at: self.color_green_at,
+            color_red_at: self.color_red_at,
+            nomatch_ok_percent: self.nomatch_ok_percent,
+            nomatch_bad_percent: self.nomatch_bad_percent,
+            ..self.image_settings.clone()
+        };
+        let (filter, ext) = match self.image_format {
+            ImageFormat::Svg => ("SVG image", "svg"),
+            ImageFormat::Png => ("PNG image", "png"),
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(filter, &[ext])
+            .set_file_name(format!("heatmap.{}", ext))
+            .save_file()
+        else {
+            return;
+        };
+
+        // Rasterizing a large figure takes a while, so it runs off the UI thread
+        let figure = render_heatmap_figure(results, &settings);
+        let format = self.image_format;
+        let scale = self.image_png_scale;
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let data = match format {
+                ImageFormat::Svg => Ok(figure.to_svg().into_bytes()),
+                ImageFormat::Png => figure.to_png(scale),
+            };
+            let _ = tx.send(data.and_then(|data| {
+                std::fs::write(&path, data)
+                    .map(|_| format!("Wrote {}", path.display()))
+                    .map_err(|e| format!("Failed to write file: {}", e))
+            }));
+        });
+        self.image_export_rx = Some(rx);
+        self.image_export_status = None;
+    }
+
+    fn show_compare_tab(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Compare Runs");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("Load Baseline Run...").clicked() {
+                self.load_comparison_run(false);
+            }
+            match &self.compare_before {
+                Some((name, r)) => ui.label(format!("{} ({} refs)", name, r.total_sequences)),
+                None => ui.label("(none)"),
+            };
+            ui.separator();
+            if ui.button("Load New Run...").clicked() {
+                self.load_comparison_run(true);
+            }
+            match &self.compare_after {
+                Some((name, r)) => ui.label(format!("{} ({} refs)", name, r.total_sequences)),
+                None => ui.label("(none)"),
+            };
+        });
+
+        if let Some(ref error) = self.compare_error {
+            ui.colored_label(egui::Color32::RED, format!("Error: {}", error));
+        }
+
+        let Some(ref cmp) = self.compare_result else {
+            ui.label("Load two saved result files to compare them.");
+            return;
+        };
+
+        let changed = cmp.cells.iter().filter(|c| c.variant_set_changed).count();
+        let worse = cmp.cells.iter().filter(|c| c.variants_delta() > 0).count();
+        let better = cmp.cells.iter().filter(|c| c.variants_delta() < 0).count();
+        ui.label(format!(
+            "{} cells compared | {} need more variants | {} need fewer | {} with changed variant sets",
+            cmp.cells.len(),
+            worse,
+            better,
+            changed
+        ));
+
+        ui.horizontal(|ui| {
+            ui.label("Show difference in:");
+            ui.radio_value(
+                &mut self.compare_metric,
+                CompareMetric::VariantsNeeded,
+                "Variants needed",
+            );
+            ui.radio_value(
+                &mut self.compare_metric,
+                CompareMetric::NoMatchRate,
+                "No-match rate",
+            );
+            ui.separator();
+            ui.label("Zoom:");
+            ui.add(egui::Slider::new(&mut self.zoom_level, 0.5..=3.0));
+        });
+
+        show_comparison_heatmap(ui, cmp, self.compare_metric, self.zoom_level);
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label("Legend:");
+            for (color, label) in [
+                (diff_color(-1.0), "improved"),
+                (diff_color(0.0), "unchanged"),
+                (diff_color(1.0), "regressed"),
+            ] {
+                let (rect, _) =
+                    ui.allocate_exact_size(egui::vec2(15.0, 15.0), egui::Sense::hover());
+                ui.painter().rect_filled(rect, 2.0, color);
+                ui.label(label);
+                ui.add_space(8.0);
+            }
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(15.0, 15.0), egui::Sense::hover());
+            ui.painter().rect_stroke(
+                rect,
+                2.0,
+                egui::Stroke::new(1.5, egui::Color32::YELLOW),
+                egui::StrokeKind::Inside,
+            );
+            ui.label("variant set changed");
+        });
+
+        ui.separator();
+        ui.heading("Biggest Regressions");
+        let regressions = cmp.biggest_regressions(50);
+        if regressions.is_empty() {
+            ui.label("No cell got worse.");
+            return;
+        }
+
+        egui::ScrollArea::vertical()
+            .id_salt("regressions_scroll")
+            .show(ui, |ui| {
+                egui::Grid::new("regressions_grid")
+                    .striped(true)
+                    .min_col_width(60.0)
+                    .show(ui, |ui| {
+                        ui.strong("Length");
+                        ui.strong("Position");
+                        ui.strong("Variants");
+                        ui.strong("No match");
+                        ui.strong("Variant set");
+                        ui.end_row();
+
+                        for cell in regressions {
+                            ui.label(format!("{} bp", cell.oligo_length));
+                            ui.label(format!("{}", cell.position + 1));
+                            if cell.became_skipped() {
+                                ui.colored_label(
+                                    egui::Color32::from_rgb(255, 100, 100),
+                                    format!("{} → skipped", cell.variants_before),
+                                );
+                            } else {
+                                ui.label(format!(
+                                    "{} → {} ({:+})",
+                                    cell.variants_before,
+                                    cell.variants_after,
+                                    cell.variants_delta()
+                                ));
+                            }
+                            ui.label(format!(
+                                "{:.1}% → {:.1}%",
+                                cell.no_match_rate_before * 100.0,
+                                cell.no_match_rate_after * 100.0
+                            ));
+                            ui.label(if cell.variant_set_changed {
+                                "changed"
+                            } else {
+                                "same"
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    fn show_oligo_check_tab(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Oligo Check");
+        ui.label("Check existing oligos (IUPAC codes allowed) against the references on both strands. No template is needed.");
+        ui.separator();
+
+        match &self.reference_data {
+            Some(r) => ui.label(format!("References: {} sequences", r.len())),
+            None => ui.colored_label(
+                egui::Color32::from_rgb(255, 180, 100),
+                "Load references in the Input Data tab first.",
+            ),
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Oligos (FASTA, or one \"name sequence\" per line):");
+            if ui.button("Load File...").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Oligo list", &["fasta", "fa", "txt", "tsv", "csv"])
+                    .pick_file()
+                {
+                    match std::fs::read_to_string(&path) {
+                        Ok(content) => self.oligo_check_input = content,
+                        Err(e) => {
+                            self.oligo_check_error = Some(format!("Failed to read file: {}", e))
+                        }
+                    }
+                }
+            }
+        });
+        ui.add(
+            egui::TextEdit::multiline(&mut self.oligo_check_input)
+                .font(egui::TextStyle::Monospace)
+                .desired_rows(6)
+                .desired_width(f32::INFINITY)
+                .hint_text("Fwd1\tACGTRYTTGCA\nRev1\tGGTNCAAGT"),
+        );
+
+        let running = self.oligo_check_rx.is_some();
+        ui.horizontal(|ui| {
+            ui.label("3' window:");
+            ui.add(
+                egui::DragValue::new(&mut self.oligo_check_three_prime)
+                    .range(1..=20)
+                    .suffix(" bases"),
+            );
+            ui.label(format!(
+                "Max mismatches: {} (alignment settings from Analysis Setup)",
+                self.params.pairwise.max_mismatches
+            ));
+            ui.separator();
+            if ui
+                .add_enabled(
+                    !running && self.reference_data.is_some(),
+                    egui::Button::new("Run Check"),
+                )
+                .clicked()
+            {
+                self.start_oligo_check();
+            }
+            if let Some((done, total)) = self.oligo_check_progress {
+                ui.spinner();
+                ui.label(format!("{}/{} oligos", done, total));
+            }
+        });
+        if let Some(ref error) = self.oligo_check_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+
+        let Some((results, names)) = &self.oligo_check_results else {
+            return;
+        };
+        ui.separator();
+        let mut export_clicked = false;
+        ui.horizontal(|ui| {
+            ui.strong(format!("Report ({} oligos)", results.len()));
+            export_clicked = ui.button("Export Report...").clicked();
+            match &self.oligo_check_status {
+                Some(Ok(msg)) => {
+                    ui.label(msg);
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+                None => {}
+            }
+        });
+
+        let mut selected = self.oligo_check_selected;
+        egui::ScrollArea::vertical()
+            .id_salt("oligo_check_scroll")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                egui::Grid::new("oligo_check_grid")
+                    .striped(true)
+                    .min_col_width(50.0)
+                    .show(ui, |ui| {
+                        for header in [
+                            "Name", "Sequence", "Coverage", "Perfect", "Mismatches (count)",
+                            "3' mismatches", "Missed",
+                        ] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for (i, r) in results.iter().enumerate() {
+                            if ui
+                                .selectable_label(selected == Some(i), &r.name)
+                                .on_hover_text("Show missed references")
+                                .clicked()
+                            {
+                                selected = if selected == Some(i) { None } else { Some(i) };
+                            }
+                            ui.monospace(&r.sequence);
+                            ui.label(format!(
+                                "{:.1}% ({}/{})",
+                                r.coverage_percent(),
+                                r.detected(),
+                                r.total_references()
+                            ));
+                            ui.label(format!("{:.1}%", r.perfect_percent()));
+                            let distribution: Vec<String> = r
+                                .mismatch_distribution()
+                                .iter()
+                                .enumerate()
+                                .filter(|(_, &n)| n > 0)
+                                .map(|(m, n)| format!("{}:{}", m, n))
+                                .collect();
+                            ui.label(distribution.join("  "));
+                            ui.label(format!("{}", r.three_prime_mismatch_count()));
+                            ui.label(format!("{}", r.total_references() - r.detected()));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(r) = selected.and_then(|i| results.get(i)) {
+            let missed = r.missed();
+            ui.separator();
+            ui.strong(format!("{}: {} references missed", r.name, missed.len()));
+            egui::ScrollArea::vertical()
+                .id_salt("oligo_missed_scroll")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for i in missed {
+                        ui.label(names.get(i).cloned().unwrap_or_else(|| format!("#{}", i + 1)));
+                    }
+                });
+        }
+        self.oligo_check_selected = selected;
+
+        if export_clicked {
+            self.export_oligo_check();
+        }
+    }
+
+    fn export_oligo_check(&mut self) {
+        let Some((results, names)) = &self.oligo_check_results else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("TSV", &["tsv"])
+            .set_file_name("oligo_check.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let format = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("tsv")) {
+            DelimitedFormat::Tsv
+        } else {
+            DelimitedFormat::Csv
+        };
+        let content = table_to_delimited(&oligo_check_table(results, names), format);
+        self.oligo_check_status = Some(
+            std::fs::write(&path, content)
+                .map(|_| format!("Wrote {}", path.display()))
+                .map_err(|e| format!("Failed to write file: {}", e)),
+        );
+    }
+
+    fn show_multiplex_tab(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Multiplex Panel");
+        ui.label("Screen several targets, each with its own template and references, then pick one assay per target that can run in the same tube.");
+        ui.separator();
+
+        let running = self.panel_rx.is_some();
+        let read_options = self.read_options;
+        let mut remove = None;
+        let mut load_template = None;
+        let mut load_references = None;
+        egui::ScrollArea::vertical()
+            .id_salt("panel_targets")
+            .max_height(220.0)
+            .show(ui, |ui| {
+                for (i, target) in self.panel_targets.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(
+                            !running,
+                            egui::TextEdit::singleline(&mut target.name).desired_width(120.0),
+                        );
+                        let template = parse_template_fasta(&target.template_fasta)
+                            .map(|t| format!("{} bp", t.sequence.len()))
+                            .unwrap_or_else(|_| "none".to_string());
+                        let references = read_sequence_text(&target.reference_fasta, &read_options)
+                            .map(|(r, summary)| match summary.notes() {
+                                Some(notes) => format!("{} ({})", r.len(), notes),
+                                None => r.len().to_string(),
+                            })
+                            .unwrap_or_else(|_| "none".to_string());
+                        ui.label(format!("Template: {}, references: {}", template, references));
+                        if ui.add_enabled(!running, egui::Button::new("Template...")).clicked() {
+                            load_template = Some(i);
+                        }
+                        if ui.add_enabled(!running, egui::Button::new("References...")).clicked() {
+                            load_references = Some(i);
+                        }
+                        if ui.add_enabled(!running, egui::Button::new("Remove")).clicked() {
+                            remove = Some(i);
+                        }
+                        match self.panel_results.get(i) {
+                            Some(Some(Ok(results))) => {
+                                ui.colored_label(
+                                    egui::Color32::from_rgb(100, 200, 100),
+                                    format!(
+                                        "screened ({} lengths)",
+                                        results.results_by_length.len()
+                                    ),
+                                );
+                            }
+                            Some(Some(Err(e))) => {
+                                ui.colored_label(egui::Color32::RED, e);
+                            }
+                            _ => {}
+                        }
+                    });
+                }
+            });
+
+        if let Some(i) = load_template.or(load_references) {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("FASTA", FASTA_EXTENSIONS)
+                .pick_file()
+            {
+                match read_text_file(&path) {
+                    Ok(content) => {
+                        let target = &mut self.panel_targets[i];
+                        if load_template.is_some() {
+                            target.template_fasta = content;
+                        } else {
+                            target.reference_fasta = content;
+                        }
+                        self.panel_results[i] = None;
+                    }
+                    Err(e) => {
+                        self.panel_status = Some(Err(e));
+                    }
+                }
+            }
+        }
+        if let Some(i) = remove {
+            self.panel_targets.remove(i);
+            self.panel_results.remove(i);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!running, egui::Button::new("Add Target")).clicked() {
+                self.panel_targets.push(PanelTarget {
+                    name: format!("Target{}", self.panel_targets.len() + 1),
+                    template_fasta: String::new(),
+                    reference_fasta: String::new(),
                 });
+                self.panel_results.push(None);
+            }
+            if ui
+                .add_enabled(!running, egui::Button::new("Add Current Inputs"))
+                .on_hover_text("Add the template and references of the Input Data tab as a target")
+                .clicked()
+            {
+                let name = self
+                    .template_data
+                    .as_ref()
+                    .map(|t| t.name.clone())
+                    .unwrap_or_else(|| format!("Target{}", self.panel_targets.len() + 1));
+                let excluded = &self.params.excluded_references;
+                let reference_fasta = match (&self.reference_file, &self.reference_data) {
+                    (_, Some(data)) if !excluded.is_empty() => {
+                        included_references(data, excluded).to_fasta()
+                    }
+                    (Some(_), Some(data)) => data.to_fasta(),
+                    _ => self.reference_input.clone(),
+                };
+                self.panel_targets.push(PanelTarget {
+                    name,
+                    template_fasta: self.template_input.clone(),
+                    reference_fasta,
+                });
+                self.panel_results.push(None);
+            }
+            ui.separator();
+            if ui
+                .add_enabled(
+                    !running && !self.panel_targets.is_empty(),
+                    egui::Button::new("Screen All Targets"),
+                )
+                .on_hover_text("Uses the settings of the Analysis Setup tab")
+                .clicked()
+            {
+                self.start_panel_screening();
+            }
+            if let Some((done, total)) = self.panel_progress {
+                ui.spinner();
+                ui.label(format!("{}/{} targets", done, total));
+            }
+        });
+
+        ui.separator();
+        ui.strong("Panel builder");
+        let p = &mut self.panel_params;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Amplicon:");
+            ui.add(egui::DragValue::new(&mut p.min_amplicon).range(20..=5000));
+            ui.label("to");
+            ui.add(egui::DragValue::new(&mut p.max_amplicon).range(20..=5000).suffix(" bp"));
+            ui.separator();
+            ui.label("Tell assays apart by");
+            ui.radio_value(&mut p.discrimination, PanelDiscrimination::AmpliconSize, "size");
+            ui.radio_value(&mut p.discrimination, PanelDiscrimination::ProbeColor, "probe color");
+        });
+        ui.horizontal_wrapped(|ui| {
+            match p.discrimination {
+                PanelDiscrimination::AmpliconSize => {
+                    ui.label("Min size difference:");
+                    ui.add(
+                        egui::DragValue::new(&mut p.min_size_difference)
+                            .range(0..=500)
+                            .suffix(" bp"),
+                    );
+                    ui.checkbox(&mut p.include_probe, "Include probe");
+                }
+                PanelDiscrimination::ProbeColor => {
+                    ui.label("Dyes:");
+                    ui.add(egui::TextEdit::singleline(&mut self.panel_dyes).desired_width(200.0));
+                }
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Dimer if a complementary run reaches");
+            ui.add(egui::DragValue::new(&mut p.three_prime_dimer_run).range(2..=20));
+            ui.label("bases at a 3' end or");
+            ui.add(egui::DragValue::new(&mut p.dimer_run).range(2..=30));
+            ui.label("bases anywhere. Sites per target:");
+            ui.add(egui::DragValue::new(&mut p.sites_per_target).range(5..=500));
+        });
+
+        let screened = !self.panel_targets.is_empty()
+            && self.panel_results.iter().all(|r| matches!(r, Some(Ok(_))));
+        let mut build_clicked = false;
+        let mut save_clicked = false;
+        let mut open_clicked = false;
+        let mut order_clicked = false;
+        ui.horizontal(|ui| {
+            build_clicked = ui
+                .add_enabled(!running && screened, egui::Button::new("Build Panel"))
+                .clicked();
+            open_clicked = ui.button("Open Panel...").clicked();
+            save_clicked = ui
+                .add_enabled(self.panel.is_some(), egui::Button::new("Save Panel..."))
+                .clicked();
+            order_clicked = ui
+                .add_enabled(self.panel.is_some(), egui::Button::new("Export Order Sheet..."))
+                .clicked();
+        });
+        match &self.panel_status {
+            Some(Ok(msg)) => {
+                ui.label(msg);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            None => {}
+        }
+        if build_clicked {
+            self.build_panel();
+        }
+        if open_clicked {
+            self.open_panel();
+        }
+        if save_clicked {
+            self.save_panel();
+        }
+        if order_clicked {
+            self.export_panel_order_sheet();
+        }
 
-                // Template oligo display
-                if !template_oligo.is_empty() {
-                    let display_template = format_sequence_for_display(
-                        &template_oligo,
-                        show_reverse_complement,
-                        show_codon_spacing,
+        let Some(panel) = &self.panel else {
+            return;
+        };
+        ui.separator();
+        let describe = |c: &Candidate| {
+            format!(
+                "pos {} len {} ({} var.)",
+                c.position + 1,
+                c.oligo_length,
+                c.variants.len()
+            )
+        };
+        egui::Grid::new("panel_assays")
+            .striped(true)
+            .num_columns(7)
+            .show(ui, |ui| {
+                for header in [
+                    "Target", "Forward", "Reverse", "Probe", "Amplicon", "Dye", "Score",
+                ] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for assay in &panel.assays {
+                    ui.label(&assay.target);
+                    ui.label(describe(&assay.forward));
+                    ui.label(describe(&assay.reverse));
+                    ui.label(assay.probe.as_ref().map(describe).unwrap_or_else(|| "-".to_string()));
+                    ui.label(format!("{} bp", assay.amplicon_size));
+                    ui.label(assay.dye.as_deref().unwrap_or("-"));
+                    ui.label(format!("{:.3}", assay.score));
+                    ui.end_row();
+                }
+            });
+
+        ui.add_space(5.0);
+        ui.strong(format!("Complementary runs ({} oligo pairs)", panel.dimers.len()));
+        egui::ScrollArea::vertical()
+            .id_salt("panel_dimers")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for dimer in &panel.dimers {
+                    let text = format!(
+                        "{} x {}: {} bases, {} at a 3' end",
+                        dimer.first, dimer.second, dimer.run, dimer.three_prime_run
                     );
-                    ui.horizontal(|ui| {
-                        ui.label("Template oligo:");
-                        ui.add(
-                            egui::Label::new(
-                                egui::RichText::new(&display_template)
-                                    .monospace()
-                                    .size(11.0)
-                                    .color(egui::Color32::from_rgb(100, 180, 255)),
-                            )
-                            .wrap_mode(egui::TextWrapMode::Extend),
-                        );
-                    });
+                    if dimer.three_prime_run >= panel.params.three_prime_dimer_run
+                        || dimer.run >= panel.params.dimer_run
+                    {
+                        ui.colored_label(egui::Color32::from_rgb(255, 100, 100), text);
+                    } else {
+                        ui.label(text);
+                    }
                 }
+            });
+    }
 
-                ui.separator();
+    /// Screen every panel target with the Analysis Setup parameters, one
+    /// after another on a background thread
+    fn start_panel_screening(&mut self) {
+        self.apply_setup_selections();
+        let targets = self.panel_targets.clone();
+        let params = self.params.clone();
+        let read_options = self.read_options;
+        let (tx, rx) = channel();
+        self.panel_results = vec![None; targets.len()];
+        self.panel_progress = Some((0, targets.len()));
+        self.panel_rx = Some(rx);
+        self.panel = None;
+        self.panel_status = None;
 
-                if pos_result.analysis.skipped {
-                    ui.colored_label(
-                        egui::Color32::YELLOW,
-                        format!(
-                            "This window was skipped: {}",
-                            pos_result
-                                .analysis
-                                .skip_reason
-                                .as_deref()
-                                .unwrap_or("Unknown reason")
-                        ),
-                    );
+        thread::spawn(move || {
+            for (i, target) in targets.iter().enumerate() {
+                let result = parse_template_fasta(&target.template_fasta)
+                    .map_err(|e| format!("Template: {}", e))
+                    .and_then(|template| {
+                        let (references, _) =
+                            read_sequence_text(&target.reference_fasta, &read_options)
+                                .map_err(|e| format!("References: {}", e))?;
+                        Ok(run_screening(&template, &references, &params, None))
+                    });
+                if tx.send((i, result)).is_err() {
                     return;
                 }
+            }
+        });
+    }
 
-                ui.label(format!(
-                    "Total references: {}",
-                    pos_result.analysis.total_sequences
-                ));
-                ui.label(format!(
-                    "Matched: {}",
-                    pos_result.analysis.sequences_analyzed
+    fn check_panel_screening(&mut self) {
+        let Some(rx) = &self.panel_rx else {
+            return;
+        };
+        loop {
+            match rx.try_recv() {
+                Ok((i, result)) => {
+                    if let Some(slot) = self.panel_results.get_mut(i) {
+                        *slot = Some(result);
+                    }
+                    if let Some((done, _)) = &mut self.panel_progress {
+                        *done += 1;
+                    }
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => return,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    self.panel_rx = None;
+                    self.panel_progress = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Form assay options for every screened target and pick the panel
+    fn build_panel(&mut self) {
+        self.panel_params.dyes = self
+            .panel_dyes
+            .split(',')
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty())
+            .collect();
+        let options: Vec<Vec<PanelAssay>> = self
+            .panel_targets
+            .iter()
+            .zip(&self.panel_results)
+            .filter_map(|(target, results)| match results {
+                Some(Ok(results)) => Some(assay_options(
+                    results,
+                    &target.name,
+                    &self.panel_params,
+                    &self.ranking_weights,
+                )),
+                _ => None,
+            })
+            .collect();
+
+        match design_panel(&options, &self.panel_params) {
+            Ok(assays) => {
+                let dimers = panel_dimers(&assays);
+                self.panel_status = Some(Ok(format!("Panel of {} assays", assays.len())));
+                self.panel = Some(MultiplexPanel::new(
+                    self.panel_targets.clone(),
+                    self.panel_params.clone(),
+                    assays,
+                    dimers,
                 ));
-                if pos_result.analysis.no_match_count > 0 {
-                    ui.colored_label(
-                        egui::Color32::from_rgb(255, 180, 100),
-                        format!(
-                            "No match: {}/{} ({:.1}%)",
-                            pos_result.analysis.no_match_count,
-                            pos_result.analysis.total_sequences,
-                            (pos_result.analysis.no_match_count as f64
-                                / pos_result.analysis.total_sequences as f64)
-                                * 100.0
-                        ),
+            }
+            Err(e) => {
+                self.panel = None;
+                self.panel_status = Some(Err(e));
+            }
+        }
+    }
+
+    fn save_panel(&mut self) {
+        let Some(panel) = &self.panel else {
+            return;
+        };
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Oligoscreen Panel", &["oligopanel"])
+            .set_file_name("panel.oligopanel")
+            .save_file()
+        {
+            self.panel_status = Some(
+                panel
+                    .to_json()
+                    .and_then(|json| {
+                        std::fs::write(&path, json)
+                            .map_err(|e| format!("Failed to write file: {}", e))
+                    })
+                    .map(|_| format!("Wrote {}", path.display())),
+            );
+        }
+    }
+
+    /// Open a saved panel, restoring its targets and builder settings. The
+    /// targets have to be screened again before the panel is rebuilt.
+    fn open_panel(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Oligoscreen Panel", &["oligopanel"])
+            .pick_file()
+        else {
+            return;
+        };
+        match std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read file: {}", e))
+            .and_then(|json| MultiplexPanel::from_json(&json))
+        {
+            Ok(panel) => {
+                self.panel_targets = panel.targets.clone();
+                self.panel_results = vec![None; panel.targets.len()];
+                self.panel_params = panel.params.clone();
+                self.panel_dyes = panel.params.dyes.join(", ");
+                self.panel = Some(panel);
+                self.panel_rx = None;
+                self.panel_progress = None;
+                self.panel_status = Some(Ok(format!("Opened {}", path.display())));
+            }
+            Err(e) => self.panel_status = Some(Err(e)),
+        }
+    }
+
+    fn export_panel_order_sheet(&mut self) {
+        let Some(panel) = &self.panel else {
+            return;
+        };
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("panel_order.csv")
+            .save_file()
+        {
+            self.panel_status = Some(
+                std::fs::write(&path, candidates_to_order_csv(&panel.candidates()))
+                    .map(|_| format!("Wrote {}", path.display()))
+                    .map_err(|e| format!("Failed to write file: {}", e)),
+            );
+        }
+    }
+}
+
+/// Read a results file written by `save_results` (JSON or binary, fully loaded)
+fn read_results_file(path: &std::path::Path) -> Result<ScreeningResults, String> {
+    if is_binary_results_file(path) {
+        let (mut results, mut lazy) = LazyResultsFile::open(path)?;
+        lazy.load_all(&mut results)?;
+        return Ok(results);
+    }
+    let json =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    serde_json::from_str::<ScreeningResults>(&json).map_err(|e| format!("Failed to parse: {}", e))
+}
+
+/// Difference heatmap of a run comparison (rows = lengths, columns = positions)
+fn show_comparison_heatmap(
+    ui: &mut egui::Ui,
+    cmp: &ScreeningComparison,
+    metric: CompareMetric,
+    zoom_level: f32,
+) {
+    let positions: Vec<usize> = {
+        let mut p: Vec<usize> = cmp.cells.iter().map(|c| c.position).collect();
+        p.sort();
+        p.dedup();
+        p
+    };
+    if positions.is_empty() {
+        ui.label("No positions in common.");
+        return;
+    }
+
+    let cell_w = (14.0 * zoom_level).max(3.0);
+    let cell_h: f32 = 24.0;
+    let label_width: f32 = 50.0;
+
+    let delta = |c: &crate::analysis::CellDiff| match metric {
+        CompareMetric::VariantsNeeded => c.variants_delta() as f64,
+        CompareMetric::NoMatchRate => c.no_match_delta(),
+    };
+    let max_abs = cmp
+        .cells
+        .iter()
+        .map(|c| delta(c).abs())
+        .fold(0.0f64, f64::max)
+        .max(f64::EPSILON);
+
+    let col_of: std::collections::HashMap<usize, usize> =
+        positions.iter().enumerate().map(|(i, &p)| (p, i)).collect();
+    let row_of: std::collections::HashMap<u32, usize> =
+        cmp.lengths.iter().enumerate().map(|(i, &l)| (l, i)).collect();
+
+    let total_width = label_width + positions.len() as f32 * cell_w;
+    let total_height = cmp.lengths.len() as f32 * cell_h;
+
+    egui::ScrollArea::horizontal()
+        .id_salt("compare_heatmap_scroll")
+        .show(ui, |ui| {
+            let (response, painter) = ui.allocate_painter(
+                egui::vec2(total_width, total_height),
+                egui::Sense::hover(),
+            );
+            let origin = response.rect.min;
+
+            for (row, &length) in cmp.lengths.iter().enumerate() {
+                painter.text(
+                    egui::pos2(
+                        origin.x + label_width - 5.0,
+                        origin.y + row as f32 * cell_h + cell_h / 2.0,
+                    ),
+                    egui::Align2::RIGHT_CENTER,
+                    format!("{} bp", length),
+                    egui::FontId::proportional(11.0),
+                    egui::Color32::LIGHT_GRAY,
+                );
+            }
+
+            let mut hovered = None;
+            for cell in &cmp.cells {
+                let col = col_of[&cell.position];
+                let row = row_of[&cell.oligo_length];
+                let rect = egui::Rect::from_min_size(
+                    egui::pos2(
+                        origin.x + label_width + col as f32 * cell_w,
+                        origin.y + row as f32 * cell_h,
+                    ),
+                    egui::vec2(cell_w - 1.0, cell_h - 1.0),
+                );
+                let color = if cell.skipped_before && cell.skipped_after {
+                    egui::Color32::from_rgb(40, 40, 40)
+                } else {
+                    diff_color(delta(cell) / max_abs)
+                };
+                painter.rect_filled(rect, 1.0, color);
+                if cell.variant_set_changed {
+                    painter.rect_stroke(
+                        rect,
+                        1.0,
+                        egui::Stroke::new(1.5, egui::Color32::YELLOW),
+                        egui::StrokeKind::Inside,
                     );
                 }
-                ui.label(format!(
-                    "Variants needed for {:.0}% coverage: {}",
-                    coverage_threshold, pos_result.variants_needed
-                ));
-                ui.label(format!(
-                    "Coverage at threshold: {:.1}%",
-                    pos_result.analysis.coverage_at_threshold
+                if response.hover_pos().is_some_and(|p| rect.contains(p)) {
+                    hovered = Some(cell);
+                }
+            }
+
+            if let Some(cell) = hovered {
+                response.on_hover_text(format!(
+                    "Position: {}, Length: {} bp\nVariants: {} → {}\nNo match: {:.1}% → {:.1}%\nVariant set {}",
+                    cell.position + 1,
+                    cell.oligo_length,
+                    cell.variants_before,
+                    cell.variants_after,
+                    cell.no_match_rate_before * 100.0,
+                    cell.no_match_rate_after * 100.0,
+                    if cell.variant_set_changed { "changed" } else { "unchanged" },
                 ));
+            }
+        });
+}
 
-                ui.separator();
+/// Diverging color for a normalized difference in -1..=1:
+/// green = improved, gray = unchanged, red = regressed.
+fn diff_color(t: f64) -> egui::Color32 {
+    let t = t.clamp(-1.0, 1.0);
+    let neutral = (70.0, 70.0, 70.0);
+    let end = if t < 0.0 { (0.0, 180.0, 0.0) } else { (220.0, 50.0, 50.0) };
+    let s = t.abs();
+    egui::Color32::from_rgb(
+        (neutral.0 + (end.0 - neutral.0) * s) as u8,
+        (neutral.1 + (end.1 - neutral.1) * s) as u8,
+        (neutral.2 + (end.2 - neutral.2) * s) as u8,
+    )
+}
 
-                // Display options
-                ui.horizontal(|ui| {
-                    ui.heading("Variants");
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        ui.checkbox(&mut self.detail_show_codon_spacing, "Codon spacing");
-                        ui.checkbox(
-                            &mut self.detail_show_reverse_complement,
-                            "Reverse complement",
-                        );
-                    });
+/// Summary statistics for one analyzed window
+/// Skipped records and invalid characters of a sequence read
+fn show_read_summary(ui: &mut egui::Ui, summary: &ReadSummary, id_salt: &str) {
+    if let Some(notes) = summary.notes() {
+        ui.colored_label(egui::Color32::YELLOW, notes);
+    }
+    if summary.invalid.is_empty() {
+        return;
+    }
+    egui::CollapsingHeader::new("Invalid characters")
+        .id_salt(format!("{}_invalid_characters", id_salt))
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_salt(format!("{}_invalid_scroll", id_salt))
+                .max_height(120.0)
+                .show(ui, |ui| {
+                    for invalid in summary.invalid.iter().take(500) {
+                        ui.label(format!(
+                            "Record {} ({}): {} invalid, first '{}' before position {}",
+                            invalid.record + 1,
+                            invalid.name,
+                            invalid.count,
+                            invalid.first.escape_debug(),
+                            invalid.position
+                        ));
+                    }
+                    if summary.invalid.len() > 500 {
+                        ui.label(format!(
+                            "... and {} more records",
+                            summary.invalid.len() - 500
+                        ));
+                    }
                 });
+        });
+}
 
-                egui::ScrollArea::vertical()
-                    .max_height(300.0)
-                    .show(ui, |ui| {
-                        egui::Grid::new("variants_grid")
-                            .striped(true)
-                            .min_col_width(50.0)
-                            .show(ui, |ui| {
-                                ui.strong("#");
-                                ui.strong("Sequence");
-                                ui.strong("Count");
-                                ui.strong("Percentage");
-                                ui.strong("Cumulative");
-                                ui.end_row();
+fn show_window_summary(
+    ui: &mut egui::Ui,
+    analysis: &WindowAnalysisResult,
+    variants_needed: usize,
+    coverage_threshold: f64,
+) {
+    if analysis.skipped {
+        ui.colored_label(
+            egui::Color32::YELLOW,
+            format!(
+                "This window was skipped: {}",
+                analysis.skip_reason.as_deref().unwrap_or("Unknown reason")
+            ),
+        );
+        return;
+    }
 
-                                let mut cumulative = 0.0;
-                                for (i, variant) in
-                                    pos_result.analysis.variants.iter().enumerate()
-                                {
-                                    cumulative += variant.percentage;
+    ui.label(format!("Total references: {}", analysis.total_sequences));
+    ui.label(format!("Matched: {}", analysis.sequences_analyzed));
+    if analysis.no_match_count > 0 {
+        ui.colored_label(
+            egui::Color32::from_rgb(255, 180, 100),
+            format!(
+                "No match: {}/{} ({:.1}%)",
+                analysis.no_match_count,
+                analysis.total_sequences,
+                (analysis.no_match_count as f64 / analysis.total_sequences as f64) * 100.0
+            ),
+        );
+    }
+    ui.label(format!(
+        "Variants needed for {:.0}% coverage: {}",
+        coverage_threshold, variants_needed
+    ));
+    ui.label(format!(
+        "Coverage at threshold: {:.1}%",
+        analysis.coverage_at_threshold
+    ));
+}
 
-                                    let is_threshold = i + 1 == pos_result.variants_needed;
+/// Variant table (sequence, count, percentage, cumulative) for one analyzed window
+fn show_variants_grid(
+    ui: &mut egui::Ui,
+    id_salt: &str,
+    analysis: &WindowAnalysisResult,
+    variants_needed: usize,
+    show_reverse_complement: bool,
+    show_codon_spacing: bool,
+) {
+    if analysis.skipped {
+        return;
+    }
 
-                                    if is_threshold {
-                                        ui.colored_label(
-                                            egui::Color32::GREEN,
-                                            format!("{}", i + 1),
-                                        );
-                                    } else {
-                                        ui.label(format!("{}", i + 1));
-                                    }
+    egui::ScrollArea::vertical()
+        .id_salt(id_salt)
+        .max_height(300.0)
+        .show(ui, |ui| {
+            egui::Grid::new(id_salt)
+                .striped(true)
+                .min_col_width(50.0)
+                .show(ui, |ui| {
+                    ui.strong("#");
+                    ui.strong("Sequence");
+                    ui.strong("Count");
+                    ui.strong("Percentage");
+                    ui.strong("Cumulative");
+                    ui.end_row();
 
-                                    let display_seq = format_sequence_for_display(
-                                        &variant.sequence,
-                                        show_reverse_complement,
-                                        show_codon_spacing,
-                                    );
-
-                                    ui.add(
-                                        egui::Label::new(
-                                            egui::RichText::new(&display_seq)
-                                                .monospace()
-                                                .size(11.0),
-                                        )
-                                        .wrap_mode(egui::TextWrapMode::Extend),
-                                    );
-
-                                    ui.label(format!("{}", variant.count));
-                                    ui.label(format!("{:.1}%", variant.percentage));
-
-                                    if is_threshold {
-                                        ui.colored_label(
-                                            egui::Color32::GREEN,
-                                            format!("{:.1}%", cumulative),
-                                        );
-                                    } else {
-                                        ui.label(format!("{:.1}%", cumulative));
-                                    }
+                    let mut cumulative = 0.0;
+                    for (i, variant) in analysis.variants.iter().enumerate() {
+                        cumulative += variant.percentage;
 
-                                    ui.end_row();
-                                }
+                        let is_threshold = i + 1 == variants_needed;
 
-                                // No match row
-                                if pos_result.analysis.no_match_count > 0 {
-                                    ui.label("");
-                                    ui.colored_label(
-                                        egui::Color32::from_rgb(255, 180, 100),
-                                        "No match",
-                                    );
-                                    ui.colored_label(
-                                        egui::Color32::from_rgb(255, 180, 100),
-                                        format!("{}", pos_result.analysis.no_match_count),
-                                    );
-                                    let no_match_pct = (pos_result.analysis.no_match_count as f64
-                                        / pos_result.analysis.total_sequences as f64)
-                                        * 100.0;
-                                    ui.colored_label(
-                                        egui::Color32::from_rgb(255, 180, 100),
-                                        format!("{:.1}%", no_match_pct),
-                                    );
-                                    ui.label("");
-                                    ui.end_row();
-                                }
-                            });
-                    });
+                        if is_threshold {
+                            ui.colored_label(egui::Color32::GREEN, format!("{}", i + 1));
+                        } else {
+                            ui.label(format!("{}", i + 1));
+                        }
+
+                        let display_seq = format_sequence_for_display(
+                            &variant.sequence,
+                            show_reverse_complement,
+                            show_codon_spacing,
+                        );
+
+                        ui.add(
+                            egui::Label::new(
+                                egui::RichText::new(&display_seq).monospace().size(11.0),
+                            )
+                            .wrap_mode(egui::TextWrapMode::Extend),
+                        );
+
+                        ui.label(format!("{}", variant.count));
+                        ui.label(format!("{:.1}%", variant.percentage));
+
+                        if is_threshold {
+                            ui.colored_label(egui::Color32::GREEN, format!("{:.1}%", cumulative));
+                        } else {
+                            ui.label(format!("{:.1}%", cumulative));
+                        }
+
+                        ui.end_row();
+                    }
+
+                    // No match row
+                    if analysis.no_match_count > 0 {
+                        ui.label("");
+                        ui.colored_label(egui::Color32::from_rgb(255, 180, 100), "No match");
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 180, 100),
+                            format!("{}", analysis.no_match_count),
+                        );
+                        let no_match_pct = (analysis.no_match_count as f64
+                            / analysis.total_sequences as f64)
+                            * 100.0;
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 180, 100),
+                            format!("{:.1}%", no_match_pct),
+                        );
+                        ui.label("");
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+/// Compact editor for an `AnalysisMethod` (method choice plus its parameters)
+fn edit_analysis_method(ui: &mut egui::Ui, id_salt: &str, method: &mut AnalysisMethod) {
+    let fixed = AnalysisMethod::FixedAmbiguities(method.get_fixed_ambiguities());
+    let incremental = AnalysisMethod::Incremental(
+        method.get_incremental_pct(),
+        method.get_incremental_max_amb(),
+    );
+
+    ui.horizontal(|ui| {
+        ui.label("Method:");
+        let selected = match method {
+            AnalysisMethod::NoAmbiguities => "No Ambiguities",
+            AnalysisMethod::FixedAmbiguities(_) => "Fixed Ambiguities",
+            AnalysisMethod::Incremental(_, _) => "Incremental",
+        };
+        egui::ComboBox::from_id_salt(id_salt)
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(method, AnalysisMethod::NoAmbiguities, "No Ambiguities");
+                ui.selectable_value(method, fixed, "Fixed Ambiguities");
+                ui.selectable_value(method, incremental, "Incremental");
             });
-    }
+
+        match method {
+            AnalysisMethod::NoAmbiguities => {}
+            AnalysisMethod::FixedAmbiguities(n) => {
+                ui.label("Max ambiguities:");
+                ui.add(egui::DragValue::new(n).range(0..=20));
+            }
+            AnalysisMethod::Incremental(pct, max_amb) => {
+                ui.label("Per step (%):");
+                ui.add(egui::DragValue::new(pct).range(1..=100));
+                let mut limit = max_amb.is_some();
+                if ui.checkbox(&mut limit, "Limit ambiguities:").changed() {
+                    *max_amb = if limit { Some(3) } else { None };
+                }
+                if let Some(n) = max_amb {
+                    ui.add(egui::DragValue::new(n).range(0..=20));
+                }
+            }
+        }
+    });
 }
 
 /// Format a sequence for display with optional transformations
@@ -1566,11 +6047,8 @@ fn add_codon_spacing(seq: &str) -> String {
         .collect()
 }
 
-/// Get color for a position based on variant count and no-match fraction.
-///
-/// Variant count gradient: green → yellow → red (configurable thresholds).
-/// No-match darkening (takes priority): original color → dark red,
-/// ramping between `nomatch_ok` and `nomatch_bad` fractions.
+/// Get color for a position based on variant count and no-match fraction
+/// (same colors as the exported heatmap figure, see `position_rgb`).
 fn position_color(
     variant_count: usize,
     no_match_fraction: f64,
@@ -1579,77 +6057,245 @@ fn position_color(
     nomatch_ok: f64,
     nomatch_bad: f64,
 ) -> egui::Color32 {
-    if variant_count == 0 {
-        return egui::Color32::from_rgb(40, 40, 40);
-    }
-
-    // 3-stop gradient: green (0,180,0) → yellow (220,200,0) → red (220,50,50)
-    let green = (0.0f64, 180.0f64, 0.0f64);
-    let yellow = (220.0f64, 200.0f64, 0.0f64);
-    let red = (220.0f64, 50.0f64, 50.0f64);
+    let [r, g, b] = position_rgb(
+        variant_count,
+        no_match_fraction,
+        green_at,
+        red_at,
+        nomatch_ok,
+        nomatch_bad,
+    );
+    egui::Color32::from_rgb(r, g, b)
+}
 
-    let t = if red_at <= green_at {
-        if variant_count <= green_at { 0.0 } else { 1.0 }
-    } else if variant_count <= green_at {
-        0.0
-    } else if variant_count >= red_at {
-        1.0
-    } else {
-        (variant_count - green_at) as f64 / (red_at - green_at) as f64
+/// Dotted alignment of variants or references against the template oligo.
+/// Differing bases are drawn in their `base_color`.
+fn show_alignment_view(ui: &mut egui::Ui, template: &str, rows: &[AlignmentRow]) {
+    const LABEL_CHARS: usize = 14;
+    let mono = egui::FontId::monospace(11.0);
+    let label_text = |label: &str| {
+        let mut label: String = label.chars().take(LABEL_CHARS).collect();
+        while label.chars().count() < LABEL_CHARS {
+            label.push(' ');
+        }
+        label
     };
 
-    // Interpolate through green → yellow (t=0..0.5) → red (t=0.5..1.0)
-    let (base_r, base_g, base_b) = if t <= 0.5 {
-        let s = t * 2.0; // 0..1 within the first half
-        (
-            green.0 + (yellow.0 - green.0) * s,
-            green.1 + (yellow.1 - green.1) * s,
-            green.2 + (yellow.2 - green.2) * s,
-        )
-    } else {
-        let s = (t - 0.5) * 2.0; // 0..1 within the second half
-        (
-            yellow.0 + (red.0 - yellow.0) * s,
-            yellow.1 + (red.1 - yellow.1) * s,
-            yellow.2 + (red.2 - yellow.2) * s,
-        )
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::Label::new(
+                egui::RichText::new(format!("{} {}", label_text("Template"), template))
+                    .font(mono.clone())
+                    .color(egui::Color32::from_rgb(100, 180, 255)),
+            )
+            .wrap_mode(egui::TextWrapMode::Extend),
+        );
+        ui.label(egui::RichText::new("count  mm").font(mono.clone()).weak());
+    });
+
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace) + 2.0;
+    egui::ScrollArea::vertical()
+        .id_salt("alignment_view_scroll")
+        .max_height(250.0)
+        .show_rows(ui, row_height, rows.len(), |ui, range| {
+            for row in &rows[range] {
+                let mut job = egui::text::LayoutJob::default();
+                job.append(
+                    &format!("{} ", label_text(&row.label)),
+                    0.0,
+                    egui::TextFormat::simple(mono.clone(), egui::Color32::LIGHT_GRAY),
+                );
+                for c in row.display.chars() {
+                    let color = if c == '.' {
+                        egui::Color32::DARK_GRAY
+                    } else {
+                        base_color(c)
+                    };
+                    job.append(
+                        &c.to_string(),
+                        0.0,
+                        egui::TextFormat::simple(mono.clone(), color),
+                    );
+                }
+                job.append(
+                    &format!(" {:>5} {:>3}", row.count, row.mismatches),
+                    0.0,
+                    egui::TextFormat::simple(mono.clone(), egui::Color32::LIGHT_GRAY),
+                );
+                ui.add(egui::Label::new(job).wrap_mode(egui::TextWrapMode::Extend));
+            }
+        });
+}
+
+/// Draw a sequence logo for one window: letters stacked by information
+/// content, colored with the `base_color` palette.
+fn show_sequence_logo(ui: &mut egui::Ui, analysis: &WindowAnalysisResult, reverse_complement: bool) {
+    let Some((columns, source)) = sequence_logo(analysis, reverse_complement) else {
+        ui.label("No matched sequences for this window.");
+        return;
     };
 
-    // No-match darkening: blend from base color toward dark red (100, 20, 20)
-    // nm_t=0 at nomatch_ok, nm_t=1 at nomatch_bad
-    let dark_red = (100.0f64, 20.0f64, 20.0f64);
-    let nm_frac = no_match_fraction.clamp(0.0, 1.0);
-    let nm_ok = nomatch_ok.clamp(0.0, 1.0);
-    let nm_bad = nomatch_bad.clamp(0.0, 1.0);
-
-    let nm_t = if nm_bad <= nm_ok {
-        if nm_frac <= nm_ok { 0.0 } else { 1.0 }
-    } else if nm_frac <= nm_ok {
-        0.0
-    } else if nm_frac >= nm_bad {
-        1.0
-    } else {
-        (nm_frac - nm_ok) / (nm_bad - nm_ok)
+    let col_w: f32 = 18.0;
+    let logo_h: f32 = 90.0;
+    let axis_w: f32 = 30.0;
+    let label_h: f32 = 14.0;
+    let bits_to_px = logo_h / 2.0;
+
+    egui::ScrollArea::horizontal()
+        .id_salt("logo_scroll")
+        .show(ui, |ui| {
+            let (response, painter) = ui.allocate_painter(
+                egui::vec2(axis_w + columns.len() as f32 * col_w, logo_h + label_h),
+                egui::Sense::hover(),
+            );
+            let origin = response.rect.min;
+            let bottom = origin.y + logo_h;
+
+            // Bits axis
+            let axis_x = origin.x + axis_w - 4.0;
+            painter.line_segment(
+                [egui::pos2(axis_x, origin.y), egui::pos2(axis_x, bottom)],
+                egui::Stroke::new(1.0, egui::Color32::GRAY),
+            );
+            for bits in [0.0f32, 1.0, 2.0] {
+                painter.text(
+                    egui::pos2(axis_x - 3.0, bottom - bits * bits_to_px),
+                    egui::Align2::RIGHT_CENTER,
+                    format!("{}", bits),
+                    egui::FontId::proportional(9.0),
+                    egui::Color32::GRAY,
+                );
+            }
+
+            for (i, column) in columns.iter().enumerate() {
+                let x = origin.x + axis_w + i as f32 * col_w;
+                let mut y = bottom;
+                for &(base, height) in &column.letters {
+                    let h = height as f32 * bits_to_px;
+                    if h >= 0.5 {
+                        let rect = egui::Rect::from_min_max(
+                            egui::pos2(x + 1.0, y - h),
+                            egui::pos2(x + col_w - 1.0, y),
+                        );
+                        paint_stretched_letter(&painter, base, rect, base_color(base));
+                    }
+                    y -= h;
+                }
+                painter.text(
+                    egui::pos2(x + col_w / 2.0, bottom + label_h / 2.0),
+                    egui::Align2::CENTER_CENTER,
+                    format!("{}", i + 1),
+                    egui::FontId::proportional(9.0),
+                    egui::Color32::GRAY,
+                );
+            }
+
+            if let Some(pointer) = response.hover_pos() {
+                let col = ((pointer.x - origin.x - axis_w) / col_w).floor();
+                if col >= 0.0 {
+                    if let Some(column) = columns.get(col as usize) {
+                        let mut text =
+                            format!("Column {}: {:.2} bits", col as usize + 1, column.information);
+                        for (base, height) in column.letters.iter().rev() {
+                            text.push_str(&format!(
+                                "\n{}: {:.0}%",
+                                base,
+                                height / column.information.max(f64::EPSILON) * 100.0
+                            ));
+                        }
+                        response.on_hover_text(text);
+                    }
+                }
+            }
+        });
+
+    let note = match source {
+        LogoSource::Matches => "From the matched reference sequences",
+        LogoSource::Variants => {
+            "From the variant list (per-reference match data was not kept for these results)"
+        }
     };
+    ui.label(egui::RichText::new(note).small().weak());
+}
 
-    let r = (base_r * (1.0 - nm_t) + dark_red.0 * nm_t).clamp(0.0, 255.0) as u8;
-    let g = (base_g * (1.0 - nm_t) + dark_red.1 * nm_t).clamp(0.0, 255.0) as u8;
-    let b = (base_b * (1.0 - nm_t) + dark_red.2 * nm_t).clamp(0.0, 255.0) as u8;
+/// Paint a glyph stretched to fill `rect` (egui text only scales uniformly,
+/// so the laid-out glyph mesh is mapped onto the rectangle directly).
+fn paint_stretched_letter(painter: &egui::Painter, letter: char, rect: egui::Rect, color: egui::Color32) {
+    let galley = painter.layout_no_wrap(letter.to_string(), egui::FontId::monospace(48.0), color);
+    let bounds = galley.mesh_bounds;
+    if bounds.width() <= 0.0 || bounds.height() <= 0.0 {
+        return;
+    }
+    // Galley meshes carry texel UVs; the tessellator normally normalizes them
+    let [tex_w, tex_h] = painter.ctx().fonts(|f| f.font_image_size());
+    let sx = rect.width() / bounds.width();
+    let sy = rect.height() / bounds.height();
+    for row in &galley.rows {
+        let mut mesh = row.visuals.mesh.clone();
+        for v in &mut mesh.vertices {
+            v.pos = egui::pos2(
+                rect.left() + (v.pos.x - bounds.left()) * sx,
+                rect.top() + (v.pos.y - bounds.top()) * sy,
+            );
+            v.uv = egui::pos2(v.uv.x / tex_w as f32, v.uv.y / tex_h as f32);
+        }
+        painter.add(mesh);
+    }
+}
 
-    egui::Color32::from_rgb(r, g, b)
+/// Draw one signal track as a line plot scaled to `0..=max`.
+/// Positions without data break the line.
+fn draw_track(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    values: &[Option<f64>],
+    max: f64,
+    cell_w: f32,
+    color: egui::Color32,
+) {
+    painter.rect_filled(rect, 0.0, egui::Color32::from_rgb(25, 25, 25));
+    let stroke = egui::Stroke::new(1.5, color);
+    let max = if max > 0.0 { max } else { 1.0 };
+
+    let mut segment: Vec<egui::Pos2> = Vec::new();
+    let flush = |segment: &mut Vec<egui::Pos2>| {
+        if segment.len() == 1 {
+            painter.circle_filled(segment[0], 1.5, color);
+        } else if segment.len() > 1 {
+            painter.add(egui::Shape::line(std::mem::take(segment), stroke));
+        }
+        segment.clear();
+    };
+    for (col, value) in values.iter().enumerate() {
+        match value {
+            Some(v) => {
+                let x = rect.left() + col as f32 * cell_w + cell_w / 2.0;
+                let y = rect.bottom() - (v / max).clamp(0.0, 1.0) as f32 * rect.height();
+                segment.push(egui::pos2(x, y));
+            }
+            None => flush(&mut segment),
+        }
+    }
+    flush(&mut segment);
 }
 
 /// Color for DNA base letters in the template display
 fn base_color(base: char) -> egui::Color32 {
-    match base {
-        'A' => egui::Color32::from_rgb(100, 200, 100), // Green
-        'T' => egui::Color32::from_rgb(220, 80, 80),   // Red
-        'G' => egui::Color32::from_rgb(255, 200, 60),   // Yellow/gold
-        'C' => egui::Color32::from_rgb(100, 150, 255),  // Blue
-        _ => egui::Color32::GRAY,
-    }
+    let [r, g, b] = base_rgb(base);
+    egui::Color32::from_rgb(r, g, b)
 }
 
+/// Closest template windows listed when a motif has no exact hit
+const MOTIF_CLOSEST_WINDOWS: usize = 10;
+
+/// FASTA file extensions offered in file dialogs (compressed files included)
+const FASTA_EXTENSIONS: &[&str] = &["fasta", "fa", "fna", "fas", "txt", "gz", "bgz", "zst"];
+
+/// Reference file extensions: FASTA and FASTQ
+const READ_FILE_EXTENSIONS: &[&str] = &[
+    "fasta", "fa", "fna", "fas", "txt", "fastq", "fq", "gz", "bgz", "zst",
+];
+
 const EXAMPLE_TEMPLATE: &str = r#">Template
 TATGGTACGTCATGTTCTAGAAATGGGCTGT
 "#;
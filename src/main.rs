@@ -8,9 +8,10 @@ use mimalloc::MiMalloc;
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
-mod analysis;
 mod app;
 
+use oligoscreen_pairwise::analysis;
+
 use app::OligoscreenApp;
 
 fn main() -> eframe::Result<()> {